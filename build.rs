@@ -102,7 +102,7 @@ fn collect(
                     format!("\n{}{}{}\n", file_prefix, file.display(), file_suffix).as_str(),
                 );
             } else {
-                out.push_str("\n");
+                out.push('\n');
             }
             out.push_str(
                 &std::fs::read_to_string(&file)
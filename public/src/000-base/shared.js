@@ -58,6 +58,30 @@ function displayServerInfo(data, containerId) {
         html += `<div class="client-ip"><strong>Client Address:</strong><span class="ip-value">${escapeHtml(data.client_addr)}</span></div>`;
     }
 
+    // Effective client as resolved through the trusted proxy chain
+    if (data.client_ip) {
+        const ci = data.client_ip;
+        html += '<div class="client-ip">';
+        html += `<div><strong>Effective Client:</strong><span class="ip-value">${escapeHtml(ci.effective_client)}</span></div>`;
+        html += `<div><strong>Effective URL base:</strong><span class="ip-value">${escapeHtml(ci.scheme)}://${escapeHtml(ci.host || '?')}</span></div>`;
+        html += `<div><strong>Resolved from:</strong><span class="ip-value">${escapeHtml(ci.source)}</span></div>`;
+        for (const w of ci.warnings || []) {
+            html += `<div class="client-ip-warning">⚠️ <strong>${escapeHtml(w.kind)}:</strong> ${escapeHtml(w.message)}</div>`;
+        }
+        html += '</div>';
+    }
+
+    // External URL as an application behind the proxy would reconstruct it
+    if (data.external_url) {
+        const eu = data.external_url;
+        html += '<div class="client-ip">';
+        html += `<div><strong>External URL:</strong><span class="ip-value">${escapeHtml(eu.url)}</span></div>`;
+        for (const msg of eu.inconsistencies || []) {
+            html += `<div class="client-ip-warning">⚠️ ${escapeHtml(msg)}</div>`;
+        }
+        html += '</div>';
+    }
+
     // Build server info section
     let serverInfoHtml = '';
     if (data.server_addr || data.hostname || data.node_name) {
@@ -76,6 +100,92 @@ function displayServerInfo(data, containerId) {
         serverInfoHtml += '</div>';
     }
 
+    // Infrastructure identified from the headers it added, outermost first
+    if (Array.isArray(data.fingerprint) && data.fingerprint.length > 0) {
+        html += '<div class="fingerprint"><strong>Detected infrastructure:</strong>';
+        for (const fp of data.fingerprint) {
+            const pct = Math.round(fp.confidence * 100);
+            html += `<details class="fingerprint-item"><summary>${escapeHtml(fp.name)}` +
+                ` <span class="fingerprint-kind">${escapeHtml(fp.kind)}</span>` +
+                ` <span class="fingerprint-confidence">${pct}%</span></summary><ul>`;
+            for (const ev of fp.evidence) {
+                html += `<li>${escapeHtml(ev)}</li>`;
+            }
+            html += '</ul></details>';
+        }
+        html += '</div>';
+    }
+
+    // Trace-context headers, validated; only shown when some arrived
+    const traceHeaders = ((data.trace && data.trace.headers) || []).filter(t => t.status !== 'dropped' && t.status !== 'absent');
+    if (traceHeaders.length > 0) {
+        html += '<div class="client-ip"><strong>Trace context:</strong>';
+        for (const t of traceHeaders) {
+            const id = t.trace_id ? `<span class="ip-value">${escapeHtml(t.trace_id)}</span>` : '';
+            html += `<div>${escapeHtml(t.kind)}${id}</div>`;
+            for (const e of t.errors || []) {
+                html += `<div class="client-ip-warning">⚠️ <strong>${escapeHtml(t.kind)}:</strong> ${escapeHtml(e)}</div>`;
+            }
+        }
+        html += '</div>';
+    }
+
+    // Client certificates forwarded by an mTLS-terminating proxy
+    for (const src of data.client_cert || []) {
+        html += `<div class="client-ip"><strong>Client certificate (${escapeHtml(src.source)}):</strong>`;
+        for (const cert of src.certificates) {
+            const icon = cert.validity === 'valid' ? '' : '⚠️ ';
+            html += `<div><strong>Subject:</strong><span class="ip-value">${escapeHtml(cert.subject)}</span></div>`;
+            html += `<div><strong>Issuer:</strong><span class="ip-value">${escapeHtml(cert.issuer)}</span></div>`;
+            if (cert.sans.length) {
+                html += `<div><strong>SANs:</strong><span class="ip-value">${escapeHtml(cert.sans.join(', '))}</span></div>`;
+            }
+            html += `<div><strong>Valid:</strong><span class="ip-value">${icon}${escapeHtml(cert.not_before)} – ${escapeHtml(cert.not_after)} (${escapeHtml(cert.validity)})</span></div>`;
+            html += `<div><strong>SHA-256:</strong><span class="ip-value">${escapeHtml(cert.sha256)}</span></div>`;
+        }
+        for (const el of src.elements || []) {
+            for (const [k, v] of Object.entries(el)) {
+                html += `<div><strong>${escapeHtml(k)}:</strong><span class="ip-value">${escapeHtml(v)}</span></div>`;
+            }
+        }
+        for (const [k, v] of Object.entries(src.fields)) {
+            html += `<div><strong>${escapeHtml(k)}:</strong><span class="ip-value">${escapeHtml(v)}</span></div>`;
+        }
+        if (src.redacted_headers.length) {
+            html += `<div>🔒 ${escapeHtml(src.redacted_headers.join(', '))} redacted</div>`;
+        }
+        for (const e of src.errors) {
+            html += `<div class="client-ip-warning">⚠️ ${escapeHtml(e)}</div>`;
+        }
+        html += '</div>';
+    }
+
+    // JWTs in Authorization, cookies and configured headers (decoded, not verified)
+    if (Array.isArray(data.tokens) && data.tokens.length > 0) {
+        html += '<div class="fingerprint"><strong>Tokens:</strong>';
+        for (const t of data.tokens) {
+            let summary = `${escapeHtml(t.source)} <span class="fingerprint-kind">${t.size} bytes</span>`;
+            if (t.redacted) {
+                summary += ' <span class="fingerprint-kind">🔒 redacted</span>';
+            } else if (t.encrypted) {
+                summary += ' <span class="fingerprint-kind">encrypted (JWE)</span>';
+            } else if (t.expires_at) {
+                summary += ` <span class="fingerprint-kind">expires ${escapeHtml(t.expires_at)}</span>`;
+            }
+            html += `<details class="fingerprint-item"><summary>${summary}</summary><ul>`;
+            if (t.issuer) html += `<li>iss: ${escapeHtml(String(t.issuer))}</li>`;
+            if (t.subject) html += `<li>sub: ${escapeHtml(String(t.subject))}</li>`;
+            if (t.audience) html += `<li>aud: ${escapeHtml(JSON.stringify(t.audience))}</li>`;
+            if (t.header) html += `<li>header: ${escapeHtml(JSON.stringify(t.header))}</li>`;
+            if (t.claims) html += `<li>claims: ${escapeHtml(JSON.stringify(t.claims))}</li>`;
+            html += '</ul></details>';
+            for (const w of t.warnings) {
+                html += `<div class="client-ip-warning">⚠️ <strong>${escapeHtml(t.source)}:</strong> ${escapeHtml(w)}</div>`;
+            }
+        }
+        html += '</div>';
+    }
+
     let tableHtml;
     if (data.origin_mismatch) {
         html = '<div class="origin-mismatch-warning">' +
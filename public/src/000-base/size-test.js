@@ -2,6 +2,8 @@
 
 const SENSILLUM_MAX_HEADER_BYTES = 16 * 1024 * 1024;
 const SENSILLUM_MAX_URI_BYTES = 65534;
+// hyper parses at most 100 request header fields over HTTP/1.1.
+const SENSILLUM_MAX_REQUEST_HEADERS = 100;
 
 // Binary search for maximum header/URL size.
 // testFunction(size) must return { ok: bool, status: number|null }
@@ -123,6 +125,66 @@ async function binarySearchHeaderSize(testFunction, resultsDiv, testType, maxByt
     }
 }
 
+// Binary search for the maximum number of headers.  Counts are small, so
+// this doubles from 8 instead of 1024 and reports plain numbers.
+// testFunction(count) must return { ok: bool, status: number|null }
+async function binarySearchHeaderCount(testFunction, resultsDiv, testType, maxCount) {
+    resultsDiv.innerHTML = `
+                <div class="result-card">
+                    <div style="font-weight: 600; color: #2d3436;">Searching for maximum ${testType}...</div>
+                    <div class="progress-bar">
+                        <div class="progress-fill" style="width: 0%"></div>
+                    </div>
+                    <div class="result-detail">Testing...</div>
+                </div>
+            `;
+    const progressBar = resultsDiv.querySelector('.progress-fill');
+    const statusDiv = resultsDiv.querySelector('.result-detail');
+
+    let maxWorking = 0;
+    let rejectionStatus = undefined;
+    let tests = 0;
+    try {
+        let current = 8;
+        let upper = maxCount + 1;
+        while (current <= maxCount) {
+            statusDiv.textContent = `Finding upper bound… ${current} headers`;
+            tests++;
+            const r = await testFunction(current);
+            if (!r.ok) {
+                rejectionStatus = r.status;
+                upper = current;
+                break;
+            }
+            maxWorking = current;
+            if (current === maxCount) break;
+            current = Math.min(current * 2, maxCount);
+        }
+        progressBar.style.width = '30%';
+
+        let lower = maxWorking;
+        const steps = Math.max(1, Math.ceil(Math.log2(Math.max(2, upper - lower))));
+        while (lower < upper - 1) {
+            const mid = Math.floor((lower + upper) / 2);
+            statusDiv.textContent = `Testing ${mid} headers…`;
+            tests++;
+            progressBar.style.width = `${Math.min(100, 30 + (tests / steps) * 70)}%`;
+            const r = await testFunction(mid);
+            if (r.ok) {
+                lower = mid;
+                maxWorking = mid;
+            } else {
+                if (rejectionStatus === undefined) rejectionStatus = r.status;
+                upper = mid;
+            }
+        }
+        return { maxCount: maxWorking, totalTests: tests, rejectionStatus };
+    } catch (err) {
+        resultsDiv.innerHTML = `<p style="color: #d63031; text-align: center;">Test failed: ${escapeHtml(err.message)}</p>`;
+        return null;
+    }
+}
+
 // Format a rejection status code as a human-readable verdict
 // isResponseTest: true when the test measures response headers (not request headers/URL),
 // because a 502 Bad Gateway is the correct proxy behaviour in that direction.
@@ -10,6 +10,23 @@ function lbElapsed(ms) {
     return (ms / 1000).toFixed(1) + ' s';
 }
 
+// Pod, namespace, Kubernetes node and container, when the server detected them.
+function lbIdentityHTML(identity) {
+    if (!identity) return '';
+    const podLabel = identity.pod
+        ? (identity.namespace ? `${identity.namespace}/${identity.pod}` : identity.pod)
+        : identity.namespace;
+    const rows = [
+        podLabel && ['pod', podLabel],
+        identity.node && ['node', identity.node],
+        identity.pod_ip && ['ip', identity.pod_ip],
+        identity.container_id && ['ctr', identity.container_id],
+    ].filter(Boolean);
+    return rows.map(([label, value]) =>
+        `<div class="lb-identity"><span class="lb-identity-label">${label}</span> ${escapeHtml(value)}</div>`
+    ).join('');
+}
+
 function lbUpdateBox(i, result, elapsedMs) {
     const box = document.getElementById(`lb-box-${i}`);
     if (!box) return;
@@ -54,6 +71,7 @@ function lbUpdateBox(i, result, elapsedMs) {
             ${numHTML}
             ${result.data.node_name ? `<div class="lb-node">${nodeInsignia}${escapeHtml(result.data.node_name)}</div>` : ''}
             ${result.data.hostname ? `<div class="lb-hostname">${hostnameInsignia}${escapeHtml(result.data.hostname)}</div>` : ''}
+            ${lbIdentityHTML(result.data.identity)}
             ${timerHTML}
         `;
     }
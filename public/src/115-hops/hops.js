@@ -0,0 +1,177 @@
+// Hop analysis — parses the Via chain and checks Max-Forwards handling on
+// OPTIONS (RFC 9110 §7.6.2), and checks hop-by-hop header stripping.
+// Browsers refuse to send TRACE or to set Connection, so those parts of the
+// test are curl commands (see CURL.md).
+
+const HOP_MAX_FORWARDS = 5;
+
+function hopVerdictMeta(verdict) {
+    switch (verdict) {
+        case 'ok': return { icon: '✅', cls: 'success' };
+        case 'unchanged': return { icon: 'ℹ️', cls: '' };
+        case 'decremented':
+        case 'not-decremented':
+        case 'mismatch':
+        case 'stripped': return { icon: '⚠️', cls: 'warning' };
+        case 'forwarded-at-zero':
+        case 'increased':
+        case 'invalid': return { icon: '❌', cls: 'fail' };
+        default: return { icon: '?', cls: '' };
+    }
+}
+
+function hopRow(icon, cls, label, detail) {
+    const clsAttr = cls ? ` class="${cls}"` : ' style="color:#636e72;"';
+    return `<div class="result-detail" style="margin-bottom:4px;">
+        <span${clsAttr}>${icon}</span> <strong>${escapeHtml(label)}</strong>: ${detail}
+    </div>`;
+}
+
+async function hopOptions(maxForwards) {
+    const response = await fetch(`${urlPrefix}/max-forwards?max_forwards=${maxForwards}`, {
+        method: 'OPTIONS',
+        headers: { 'Max-Forwards': String(maxForwards) },
+        cache: 'no-store'
+    });
+    let data = null;
+    try {
+        data = await response.json();
+    } catch (_) { }
+    // Only a Sensillum answer carries the max_forwards report.
+    const fromBackend = !!(data && data.max_forwards);
+    return { status: response.status, data: fromBackend ? data : null };
+}
+
+async function runHopTest() {
+    const btn = document.getElementById('hop-test-btn');
+    const resultsDiv = document.getElementById('hop-test-results');
+    btn.disabled = true;
+    btn.textContent = 'Testing…';
+    resultsDiv.innerHTML = '<div class="result-detail">Sending OPTIONS…</div>';
+
+    let html = '';
+
+    // --- Via chain and decrement check ---
+    let first;
+    try {
+        first = await hopOptions(HOP_MAX_FORWARDS);
+    } catch (err) {
+        first = { error: err.message };
+    }
+
+    html += '<div class="result-card"><div style="font-weight:600;color:#2d3436;margin-bottom:10px;">Via chain</div>';
+    if (first.error) {
+        html += hopRow('❌', 'fail', 'OPTIONS', `Request failed: ${escapeHtml(first.error)}`);
+    } else if (!first.data) {
+        html += hopRow('⚠️', 'warning', 'OPTIONS', `Did not reach Sensillum — the proxy answered with HTTP ${first.status}. It may block OPTIONS.`);
+    } else {
+        const via = first.data.via || { hops: [], errors: [] };
+        if (via.hops.length === 0) {
+            html += hopRow('ℹ️', '', 'Via', 'No <code>Via</code> header received — proxies in front did not identify themselves.');
+        }
+        via.hops.forEach((hop, i) => {
+            const comment = hop.comment ? ` <span style="color:#636e72;">(${escapeHtml(hop.comment)})</span>` : '';
+            html += hopRow(`${i + 1}.`, '', hop.received_by,
+                `${escapeHtml(hop.protocol)}/${escapeHtml(hop.version)}${comment}`);
+        });
+        for (const err of via.errors) {
+            html += hopRow('❌', 'fail', 'Malformed Via', escapeHtml(err));
+        }
+        const mf = first.data.max_forwards;
+        const { icon, cls } = hopVerdictMeta(mf.verdict);
+        html += hopRow(icon, cls, `Max-Forwards: ${HOP_MAX_FORWARDS}`, escapeHtml(mf.detail));
+    }
+    html += '</div>';
+    resultsDiv.innerHTML = html;
+
+    // --- Max-Forwards: 0 must stop at the first proxy ---
+    let zero;
+    try {
+        zero = await hopOptions(0);
+    } catch (err) {
+        zero = { error: err.message };
+    }
+
+    html += '<div class="result-card" style="margin-top:10px;"><div style="font-weight:600;color:#2d3436;margin-bottom:10px;">Max-Forwards: 0</div>';
+    if (zero.error) {
+        html += hopRow('❌', 'fail', 'OPTIONS', `Request failed: ${escapeHtml(zero.error)}`);
+    } else if (!zero.data) {
+        html += hopRow('✅', 'success', 'Answered by proxy', `The proxy responded itself (HTTP ${zero.status}) instead of forwarding, as RFC 9110 requires.`);
+    } else if (zero.data.max_forwards.verdict === 'forwarded-at-zero') {
+        html += hopRow('❌', 'fail', 'Forwarded', 'The request reached Sensillum although Max-Forwards was 0 — the proxy ignores Max-Forwards.');
+    } else {
+        html += hopRow('ℹ️', '', 'Reached Sensillum', 'No proxy was detected in front of Sensillum, so it answered as the first recipient.');
+    }
+    html += '</div>';
+
+    // --- TRACE ---
+    const traceUrl = `${window.location.origin}${urlPrefix}/max-forwards?max_forwards=${HOP_MAX_FORWARDS}`;
+    html += '<div class="result-card" style="margin-top:10px;"><div style="font-weight:600;color:#2d3436;margin-bottom:10px;">TRACE</div>';
+    html += hopRow('🌐', '', 'Browser blocked', 'Browsers never send TRACE. Check with curl; if the response has <code>X-Sensillum-Trace: reached-backend</code>, the proxy lets TRACE through (cross-site tracing risk):');
+    html += `<div class="result-detail"><code>curl -i -X TRACE -H 'Max-Forwards: ${HOP_MAX_FORWARDS}' '${escapeHtml(traceUrl)}'</code></div>`;
+    html += '</div>';
+
+    resultsDiv.innerHTML = html;
+    btn.disabled = false;
+    btn.textContent = 'Via / Max-Forwards';
+}
+
+// Hop-by-hop headers.  Request direction: the browser sends its own
+// Connection/Keep-Alive on HTTP/1.1, and a proxy must not forward them.
+// Response direction: /hop lists two headers in its Connection header; the
+// proxy must remove those, plus Connection, Keep-Alive and Proxy-Connection,
+// but keep the unlisted control header.
+async function runHopByHopTest() {
+    const btn = document.getElementById('hbh-test-btn');
+    const resultsDiv = document.getElementById('hop-test-results');
+    btn.disabled = true;
+    btn.textContent = 'Testing…';
+    resultsDiv.innerHTML = '<div class="result-detail">Requesting /hop…</div>';
+
+    let html = '';
+    try {
+        const response = await fetch(`${urlPrefix}/hop?expect=x-hop-a,x-hop-b`, { cache: 'no-store' });
+        const data = await response.json();
+
+        html += '<div class="result-card"><div style="font-weight:600;color:#2d3436;margin-bottom:10px;">Request → backend</div>';
+        if (data.forwarded_hop_by_hop.length === 0) {
+            html += hopRow('✅', 'success', 'Hop-by-hop headers', 'None reached Sensillum.');
+        }
+        for (const name of data.forwarded_hop_by_hop) {
+            // TE: trailers is the one value that may legitimately be forwarded (e.g. gRPC).
+            const benign = name === 'te' && data.received.te === 'trailers';
+            html += hopRow(benign ? 'ℹ️' : '⚠️', benign ? '' : 'warning', name,
+                `forwarded to the backend: <code>${escapeHtml(data.received[name])}</code>`);
+        }
+        const curlUrl = `${window.location.origin}${urlPrefix}/hop?expect=x-hop-a,x-hop-b`;
+        html += hopRow('🌐', '', 'Connection-listed headers',
+            'Browsers cannot set <code>Connection</code>. Check with curl — <code>x-hop-a</code> and <code>x-hop-b</code> should be reported with <code>"arrived": false</code>:');
+        html += `<div class="result-detail"><code>curl -s -H 'Connection: X-Hop-A, X-Hop-B' -H 'X-Hop-A: 1' -H 'X-Hop-B: 1' '${escapeHtml(curlUrl)}'</code></div>`;
+        html += '</div>';
+
+        html += '<div class="result-card" style="margin-top:10px;"><div style="font-weight:600;color:#2d3436;margin-bottom:10px;">Response → browser</div>';
+        if (data.response.stripped_by_sensillum) {
+            html += hopRow('ℹ️', '', data.protocol,
+                'The proxy talks HTTP/2 to Sensillum, which forbids connection-specific headers, so none were sent.');
+        } else {
+            const expectStripped = [...data.response.listed, 'connection', ...data.response.also_sent];
+            for (const name of expectStripped) {
+                const got = response.headers.get(name);
+                html += got === null
+                    ? hopRow('✅', 'success', name, 'stripped by the proxy')
+                    : hopRow('⚠️', 'warning', name, `reached the browser: <code>${escapeHtml(got)}</code>`);
+            }
+        }
+        const control = response.headers.get(data.response.control);
+        html += control === null
+            ? hopRow('❌', 'fail', data.response.control, 'unlisted control header was stripped too — the proxy may drop headers indiscriminately')
+            : hopRow('✅', 'success', data.response.control, 'unlisted control header arrived');
+        html += '</div>';
+    } catch (err) {
+        html = `<p style="color: #d63031; text-align: center;">Test failed: ${escapeHtml(err.message)}</p>`;
+    }
+
+    resultsDiv.innerHTML = html;
+    btn.disabled = false;
+    btn.textContent = 'Hop-by-Hop Headers';
+}
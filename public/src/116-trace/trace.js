@@ -0,0 +1,99 @@
+// Trace-context propagation.  Each request lists the trace headers it sent in
+// X-Sensillum-Trace-Sent; the server compares them with what arrived and
+// reports each one as forwarded, modified, new-trace, dropped or created.
+
+function traceRow(icon, cls, label, detail) {
+    const clsAttr = cls ? ` class="${cls}"` : ' style="color:#636e72;"';
+    return `<div class="result-detail" style="margin-bottom:4px;">
+        <span${clsAttr}>${icon}</span> <strong>${escapeHtml(label)}</strong>: ${detail}
+    </div>`;
+}
+
+function randomHex(bytes) {
+    const buf = new Uint8Array(bytes);
+    crypto.getRandomValues(buf);
+    return Array.from(buf, b => b.toString(16).padStart(2, '0')).join('');
+}
+
+function traceStatusMeta(status) {
+    switch (status) {
+        case 'forwarded': return { icon: '✅', cls: 'success' };
+        case 'modified': return { icon: 'ℹ️', cls: '' };
+        case 'created': return { icon: '➕', cls: '' };
+        case 'new-trace': return { icon: '⚠️', cls: 'warning' };
+        case 'dropped': return { icon: '❌', cls: 'fail' };
+        default: return { icon: '?', cls: '' };
+    }
+}
+
+async function traceEcho(sent) {
+    const declaration = new URLSearchParams(sent).toString();
+    const response = await fetch(`${urlPrefix}/echo`, {
+        headers: { ...sent, 'X-Sensillum-Trace-Sent': declaration },
+        cache: 'no-store'
+    });
+    const data = await response.json();
+    if (!data.trace) throw new Error('the response did not come from Sensillum');
+    return data.trace;
+}
+
+function traceRows(trace) {
+    return trace.headers.map(t => {
+        const { icon, cls } = traceStatusMeta(t.status);
+        let detail = escapeHtml(t.detail);
+        if (t.status !== 'forwarded' && t.value) {
+            detail += ` — <code>${escapeHtml(t.value)}</code>`;
+        }
+        for (const e of t.errors) {
+            detail += `<br><span class="warning">⚠️ ${escapeHtml(e)}</span>`;
+        }
+        return traceRow(icon, cls, t.kind, detail);
+    }).join('');
+}
+
+async function runTraceTest() {
+    const btn = document.getElementById('trace-test-btn');
+    const resultsDiv = document.getElementById('trace-test-results');
+    btn.disabled = true;
+    btn.textContent = 'Testing…';
+
+    const traceId = randomHex(16);
+    const spanId = randomHex(8);
+    const sent = {
+        'traceparent': `00-${traceId}-${spanId}-01`,
+        'tracestate': `sensillum=${randomHex(4)}`,
+        'b3': `${traceId}-${spanId}-1`,
+        'x-b3-traceid': traceId,
+        'x-b3-spanid': spanId,
+        'x-b3-sampled': '1',
+        'x-request-id': `sensillum-${randomHex(8)}`,
+    };
+
+    let html = '';
+    try {
+        const bare = await traceEcho({});
+        const created = bare.headers.filter(t => t.status === 'created');
+        html += '<div class="result-card"><div style="font-weight: 600; color: #2d3436; margin-bottom: 10px;">Without trace headers</div>';
+        html += created.length
+            ? traceRows({ headers: created })
+            : traceRow('ℹ️', '', 'None added', 'the proxy does not start traces for requests that arrive without one');
+        html += '</div>';
+
+        const known = await traceEcho(sent);
+        html += '<div class="result-card"><div style="font-weight: 600; color: #2d3436; margin-bottom: 10px;">With a known trace ID</div>';
+        html += `<div class="result-detail" style="margin-bottom:8px;">Sent trace ID <code>${traceId}</code></div>`;
+        html += traceRows(known);
+        if (known.trace_id_kept === true) {
+            html += traceRow('✅', 'success', 'Verdict', 'the proxy kept the trace ID');
+        } else if (known.trace_id_kept === false) {
+            html += traceRow('❌', 'fail', 'Verdict', 'the proxy did not keep the trace ID — backend spans will not join the client\'s trace');
+        }
+        html += '</div>';
+    } catch (err) {
+        html = `<p style="color: #d63031; text-align: center;">Test failed: ${escapeHtml(err.message)}</p>`;
+    }
+
+    resultsDiv.innerHTML = html;
+    btn.disabled = false;
+    btn.textContent = 'Run Test';
+}
@@ -41,13 +41,15 @@ function displayHeaderSizeResult(resultsDiv, maxSize, testType, totalTests, reje
 let _headerTestRunning = false;
 
 function _setHeaderTestButtons(disabled, activeId) {
-    const ids = ['single-header-test-btn', 'total-header-test-btn',
-                 'resp-single-header-test-btn', 'resp-total-header-test-btn'];
+    const ids = ['single-header-test-btn', 'total-header-test-btn', 'count-header-test-btn',
+                 'resp-single-header-test-btn', 'resp-total-header-test-btn', 'resp-count-header-test-btn'];
     const labels = {
         'single-header-test-btn':      'Request: Single',
         'total-header-test-btn':       'Request: Multi',
+        'count-header-test-btn':       'Request: Count',
         'resp-single-header-test-btn': 'Response: Single',
         'resp-total-header-test-btn':  'Response: Multi',
+        'resp-count-header-test-btn':  'Response: Count',
     };
     for (const id of ids) {
         const btn = document.getElementById(id);
@@ -188,3 +190,107 @@ async function runResponseTotalHeaderSizeTest() {
     _headerTestRunning = false;
     _setHeaderTestButtons(false);
 }
+
+function displayHeaderCountResult(resultsDiv, result, testType, note) {
+    const isResponseTest = testType.toLowerCase().startsWith('response');
+    const rejectionNote = result.rejectionStatus !== undefined
+        ? `<div class="result-detail" style="margin-top:8px;">
+                        🚫 Rejection: ${result.rejectionStatus === 'truncated'
+                            ? '<span style="color:#e17055;">✂️ Headers silently dropped</span> — the response arrived, but without all of its headers.'
+                            : formatRejectionStatus(result.rejectionStatus, isResponseTest)}
+                    </div>`
+        : '';
+    resultsDiv.innerHTML = `
+                <div class="result-card">
+                    <div style="font-weight: 600; color: #2d3436; margin-bottom: 10px;">Maximum ${testType}</div>
+                    <div class="result-value">${result.maxCount.toLocaleString()} headers</div>
+                    <div class="result-detail">📊 tested in ${result.totalTests} requests</div>
+                    ${rejectionNote}
+                    ${note}
+                </div>
+            `;
+}
+
+// Request Header Count Limit Test
+async function runRequestHeaderCountTest() {
+    if (_headerTestRunning) return;
+    _headerTestRunning = true;
+    _setHeaderTestButtons(true, 'count-header-test-btn');
+    const button = document.getElementById('count-header-test-btn');
+    const resultsDiv = document.getElementById('size-test-results');
+    button.textContent = 'Testing...';
+
+    // Header fields that reached Sensillum on the last successful request,
+    // including those added by the browser and proxies.
+    let arrivedCount = 0;
+
+    async function testCount(count) {
+        const headers = {};
+        for (let i = 0; i < count; i++) {
+            headers[`X-Count-Header-${i}`] = 'x';
+        }
+        try {
+            const response = await fetch(`${urlPrefix}/echo`, { headers, cache: 'no-store' });
+            if (!response.ok) return { ok: false, status: response.status };
+            const data = await response.json();
+            const arrived = Object.keys(data.headers || {}).filter(k => k.startsWith('x-count-header-')).length;
+            if (arrived < count) return { ok: false, status: 'truncated' };
+            arrivedCount = data.header_count;
+            return { ok: true, status: response.status };
+        } catch {
+            return { ok: false, status: null };
+        }
+    }
+
+    // Leave room for the headers the browser and proxies add.
+    const result = await binarySearchHeaderCount(testCount, resultsDiv, 'request header count', 1000);
+    if (result) {
+        const nearSensillumLimit = result.rejectionStatus !== undefined
+            && arrivedCount >= SENSILLUM_MAX_REQUEST_HEADERS - 5;
+        const note = nearSensillumLimit
+            ? `<div class="result-detail" style="margin-top:8px; color:#e17055;">
+                        ⚠️ This limit comes from Sensillum itself, not the proxy under test.
+                        Hyper accepts at most ${SENSILLUM_MAX_REQUEST_HEADERS} request headers over HTTP/1.1
+                        (${arrivedCount} arrived, including those added by the browser and proxies).
+                    </div>`
+            : `<div class="result-detail" style="margin-top:8px;">ℹ️ ${arrivedCount} header fields arrived in total, including those added by the browser and proxies.</div>`;
+        displayHeaderCountResult(resultsDiv, result, 'Request Header Count', note);
+    }
+
+    _headerTestRunning = false;
+    _setHeaderTestButtons(false);
+}
+
+// Response Header Count Limit Test
+async function runResponseHeaderCountTest() {
+    if (_headerTestRunning) return;
+    _headerTestRunning = true;
+    _setHeaderTestButtons(true, 'resp-count-header-test-btn');
+    const button = document.getElementById('resp-count-header-test-btn');
+    const resultsDiv = document.getElementById('size-test-results');
+    button.textContent = 'Testing...';
+
+    const MAX_RESP_COUNT = 10000; // matches server-side cap
+
+    async function testCount(count) {
+        try {
+            const response = await fetch(`${urlPrefix}/hdr?count=${count}`, { cache: 'no-store' });
+            if (!response.ok) return { ok: false, status: response.status };
+            let arrived = 0;
+            for (const [name] of response.headers) {
+                if (name.startsWith('x-response-count-')) arrived++;
+            }
+            return arrived === count ? { ok: true, status: response.status } : { ok: false, status: 'truncated' };
+        } catch {
+            return { ok: false, status: -1 };
+        }
+    }
+
+    const result = await binarySearchHeaderCount(testCount, resultsDiv, 'response header count', MAX_RESP_COUNT);
+    if (result) {
+        displayHeaderCountResult(resultsDiv, result, 'Response Header Count', '');
+    }
+
+    _headerTestRunning = false;
+    _setHeaderTestButtons(false);
+}
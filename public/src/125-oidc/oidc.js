@@ -0,0 +1,102 @@
+// OIDC login flow simulation.  /oidc/login redirects through a simulated
+// identity provider to /oidc/callback, which sets the chunked session cookie
+// and redirects to /oidc/session.  Each step answers 400 with a report if
+// something arrived damaged, so the URL the redirect chain stops at names
+// the step that broke — whether Sensillum or the proxy answered there.
+
+const OIDC_SCENARIOS = [
+    { label: 'Small', state: 256, code: 256, cookie: 2048 },
+    { label: 'Typical', state: 1024, code: 512, cookie: 6144 },
+    { label: 'Large', state: 4096, code: 1024, cookie: 12288 },
+    { label: 'Huge', state: 8192, code: 2048, cookie: 24576 },
+];
+
+const OIDC_CHUNK_SIZE = 3800;
+const OIDC_STEPS = ['login', 'authorize', 'callback', 'session'];
+
+function oidcRow(icon, cls, label, detail) {
+    const clsAttr = cls ? ` class="${cls}"` : ' style="color:#636e72;"';
+    return `<div class="result-detail" style="margin-bottom:4px;">
+        <span${clsAttr}>${icon}</span> <strong>${escapeHtml(label)}</strong>: ${detail}
+    </div>`;
+}
+
+function formatOidcBytes(n) {
+    return n >= 1024 ? `${(n / 1024).toFixed(n % 1024 ? 1 : 0)} KB` : `${n} B`;
+}
+
+// What the request to a step and its response carry, for explaining a
+// proxy error at that step.
+function oidcStepLoad(step, s, chunks) {
+    switch (step) {
+        case 'authorize': return `its URL carries the ${formatOidcBytes(s.state)} state`;
+        case 'callback': return `its URL carries the state and the ${formatOidcBytes(s.code)} code, and its response sets ${formatOidcBytes(s.cookie)} of cookies in ${chunks} chunks`;
+        case 'session': return `its request carries the ${formatOidcBytes(s.cookie)} session cookie in ${chunks} chunks`;
+        default: return 'it is a small request';
+    }
+}
+
+function oidcProxyHint(status) {
+    if (status === 414) return 'URL too long';
+    if (status === 431 || status === 400 || status === 413) return 'request headers too large';
+    if (status === 502) return 'likely the response headers exceeded the proxy\'s buffer';
+    return null;
+}
+
+async function probeOidc(s) {
+    const run = `o${Math.random().toString(36).slice(2, 12)}`;
+    const chunks = Math.ceil(s.cookie / OIDC_CHUNK_SIZE);
+    const label = `${s.label} (state ${formatOidcBytes(s.state)}, cookie ${formatOidcBytes(s.cookie)})`;
+    const url = `${urlPrefix}/oidc/login?run=${run}&state_size=${s.state}&code_size=${s.code}&cookie_size=${s.cookie}&chunk_size=${OIDC_CHUNK_SIZE}`;
+
+    let response;
+    try {
+        response = await fetch(url, { cache: 'no-store', credentials: 'same-origin' });
+    } catch {
+        return oidcRow('❌', 'fail', label,
+            `the flow ended in a network error — the proxy closed the connection or the browser refused a response; Sensillum's log shows the last step reached (<code>OIDC flow ${run}</code>)`);
+    } finally {
+        // Clear cookies left behind by a flow that never reached /oidc/session.
+        await fetch(`${urlPrefix}/oidc/logout?chunks=${chunks}`, { cache: 'no-store' }).catch(() => {});
+    }
+
+    const path = new URL(response.url).pathname;
+    const step = OIDC_STEPS.find(st => path.endsWith(`/oidc/${st}`)) ?? 'unknown';
+    let data = null;
+    try {
+        data = await response.json();
+    } catch {
+        // Not a Sensillum report: the proxy answered.
+    }
+
+    if (data && data.step === 'session' && data.ok) {
+        return oidcRow('✅', 'success', label,
+            `all ${OIDC_STEPS.length} steps intact; the session cookie arrived as ${data.received.cookie_bytes} bytes of <code>Cookie</code> in ${chunks} chunk${chunks === 1 ? '' : 's'}`);
+    }
+    if (data && data.step) {
+        const failed = (data.checks || []).filter(c => !c.ok)
+            .map(c => `${escapeHtml(c.check)} ${escapeHtml(c.detail)}`).join(', ');
+        return oidcRow('❌', 'fail', label, `broke at <strong>${escapeHtml(data.step)}</strong> — ${failed}`);
+    }
+    const hint = oidcProxyHint(response.status);
+    return oidcRow('❌', 'fail', label,
+        `broke at <strong>${escapeHtml(step)}</strong> — the proxy answered HTTP ${response.status}${hint ? ` (${hint})` : ''}; ${oidcStepLoad(step, s, chunks)}`);
+}
+
+async function runOidcTest() {
+    const btn = document.getElementById('oidc-test-btn');
+    const resultsDiv = document.getElementById('oidc-test-results');
+    btn.disabled = true;
+
+    resultsDiv.innerHTML = '<div class="result-card"></div>';
+    const card = resultsDiv.querySelector('.result-card');
+    for (const [i, scenario] of OIDC_SCENARIOS.entries()) {
+        btn.textContent = `Testing… (${i}/${OIDC_SCENARIOS.length})`;
+        card.insertAdjacentHTML('beforeend', await probeOidc(scenario));
+    }
+    card.insertAdjacentHTML('beforeend', oidcRow('ℹ️', '', 'Note',
+        `session cookies are split into ${OIDC_CHUNK_SIZE}-byte chunks as oauth2-proxy does; sizes in between can be tried with <code>/oidc/login?state_size=…&amp;cookie_size=…</code>`));
+
+    btn.disabled = false;
+    btn.textContent = 'Run Test';
+}
@@ -30,6 +30,42 @@ function renderCharResults(title, rows) {
     return html;
 }
 
+// Outcome of one byte of the response sweep, from the headers that arrived.
+function charSweepOutcome(headers, manifest, byte) {
+    const hex = byte.toString(16).padStart(2, '0');
+    if (manifest.rejected.includes(hex)) return { outcome: 'server-rejected', got: null };
+    const hv = headers.get(manifest.header_prefix + hex);
+    if (hv === null) return { outcome: 'stripped', got: null };
+    if (hv === 'probe' + String.fromCharCode(byte) + 'probe') return { outcome: 'passed', got: hv };
+    return { outcome: 'modified', got: hv };
+}
+
+// 16×16 grid of response-header outcomes, one cell per byte value.
+function renderCharGrid(headers, manifest) {
+    const counts = {};
+    let cells = '';
+    for (let byte = 0; byte < 256; byte++) {
+        const { outcome, got } = charSweepOutcome(headers, manifest, byte);
+        counts[outcome] = (counts[outcome] || 0) + 1;
+        const { text } = charOutcomeMeta(outcome);
+        let title = `${byteHex(byte)}: ${text}`;
+        if (outcome === 'modified') {
+            const gotCode = got.length > 5 ? byteHex(got.charCodeAt(5)) : '(header too short)';
+            title += ` — got ${gotCode}, header length ${got.length}`;
+        }
+        cells += `<div class="char-cell char-${outcome}" title="${escapeHtml(title)}">${byte.toString(16).padStart(2, '0').toUpperCase()}</div>`;
+    }
+    const legend = ['passed', 'stripped', 'modified', 'server-rejected']
+        .map(o => `<span class="char-cell char-${o}">${counts[o] || 0}</span> ${escapeHtml(charOutcomeMeta(o).text)}`)
+        .join(' &nbsp; ');
+    return `<div class="result-card" style="margin-top:10px;">
+        <div style="font-weight:600;color:#2d3436;margin-bottom:10px;">Response Header — all bytes (one request)</div>
+        <div class="char-grid">${cells}</div>
+        <div class="result-detail" style="margin-top:10px;">${legend}</div>
+        <div class="result-detail">Hyper can only send 0x09 and 0x20–0xFF except 0x7F; use curl for the rest (see CURL.md). Hover over a cell for details.</div>
+    </div>`;
+}
+
 async function runCharTest() {
     if (charTestRunning) return;
     charTestRunning = true;
@@ -95,49 +131,24 @@ async function runCharTest() {
     resultsDiv.innerHTML =
         renderCharResults('Request Header — High-ASCII bytes', reqRows);
 
-    const respBytes = [
-        { hex: '09', label: '0x09 (HTAB)' },
-        { hex: '80', label: '0x80 (first high-ASCII)' },
-        { hex: 'a0', label: '0xA0 (Latin-1 non-breaking space)' },
-        { hex: 'e9', label: '0xE9 (Latin-1 é)' },
-        { hex: 'fe', label: '0xFE' },
-        { hex: 'ff', label: '0xFF (highest byte)' },
-    ];
-
-    const respRows = [];
-    for (const { hex, label } of respBytes) {
-        const expectedByte = parseInt(hex, 16);
-        let outcome, detail;
-        try {
-            const response = await fetch(`${urlPrefix}/hdr?byte=${hex}`);
-            const data = await response.json();
-            if (!data.ok) {
-                outcome = 'server-rejected';
-                detail = `Hyper cannot place this byte in a header (${escapeHtml(data.reason)}). Only bytes 0x09 and 0x20–0xFF are valid in HTTP response headers per RFC 9110.`;
-            } else {
-                const hv = response.headers.get('x-charset-test');
-                if (hv === null) {
-                    outcome = 'stripped';
-                    detail = 'Proxy stripped the <code>x-charset-test</code> response header entirely.';
-                } else if (hv.length === 11 && hv.charCodeAt(5) === expectedByte) {
-                    outcome = 'passed';
-                    detail = `Byte ${byteHex(expectedByte)} arrived in the response header intact.`;
-                } else {
-                    const gotCode = hv.length > 5 ? byteHex(hv.charCodeAt(5)) : '(header too short)';
-                    outcome = 'modified';
-                    detail = `Header present but byte was altered: expected ${byteHex(expectedByte)}, got ${escapeHtml(gotCode)} (header length: ${hv.length}).`;
-                }
-            }
-        } catch (err) {
-            outcome = 'error';
-            detail = `Fetch failed — proxy may have blocked the response entirely: ${escapeHtml(err.message)}`;
-        }
-        respRows.push({ label, outcome, detail });
+    // Response direction: one /hdr?sweep response carries a header for every
+    // byte hyper can send; the manifest in the body says which ones those are.
+    let respHtml;
+    try {
+        const response = await fetch(`${urlPrefix}/hdr?sweep`, { cache: 'no-store' });
+        const manifest = await response.json();
+        respHtml = renderCharGrid(response.headers, manifest);
+    } catch (err) {
+        respHtml = renderCharResults('Response Header — all bytes', [{
+            label: '/hdr?sweep',
+            outcome: 'error',
+            detail: `Fetch failed — proxy may have blocked the response entirely: ${escapeHtml(err.message)}`,
+        }]);
     }
 
     const placeholder = resultsDiv.querySelector('.result-detail:last-child');
     if (placeholder) placeholder.remove();
-    resultsDiv.insertAdjacentHTML('beforeend', renderCharResults('Response Header — High-ASCII bytes', respRows));
+    resultsDiv.insertAdjacentHTML('beforeend', respHtml);
 
     charTestRunning = false;
     if (btn) { btn.disabled = false; btn.textContent = 'Run Test'; }
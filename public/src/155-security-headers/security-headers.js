@@ -0,0 +1,73 @@
+// Security header audit.  /security-headers sends a fixed baseline (some
+// headers present, some deliberately absent) and returns it in the body; the
+// difference from what arrived is what the proxy changed.
+
+// Checks for values a proxy injects; return a warning string, or null if the value looks sound.
+const SECHDR_CHECKS = {
+    'strict-transport-security': v => {
+        const m = /max-age\s*=\s*"?(\d+)/i.exec(v);
+        if (!m) return 'no max-age';
+        if (Number(m[1]) < 15552000) return `max-age ${m[1]} is under 180 days`;
+        return window.location.protocol === 'http:' ? 'browsers ignore HSTS on plain HTTP' : null;
+    },
+    'x-content-type-options': v => v.trim().toLowerCase() === 'nosniff' ? null : 'the only valid value is nosniff',
+    'x-frame-options': v => /^(deny|sameorigin)$/i.test(v.trim()) ? null : 'expected DENY or SAMEORIGIN',
+};
+
+function secHdrRow(icon, cls, label, detail) {
+    const clsAttr = cls ? ` class="${cls}"` : ' style="color:#636e72;"';
+    return `<div class="result-detail" style="margin-bottom:4px;">
+        <span${clsAttr}>${icon}</span> <strong>${escapeHtml(label)}</strong>: ${detail}
+    </div>`;
+}
+
+function gradeSecurityHeader(h, arrived) {
+    const label = `${h.name} (${h.description})`;
+    if (h.sent) {
+        if (arrived === null) {
+            return { kind: 'removed', html: secHdrRow('⚠️', 'warning', label, `removed by the proxy (sent <code>${escapeHtml(h.value)}</code>)`) };
+        }
+        if (arrived !== h.value) {
+            return { kind: 'rewritten', html: secHdrRow('⚠️', 'warning', label, `rewritten: sent <code>${escapeHtml(h.value)}</code>, received <code>${escapeHtml(arrived)}</code>`) };
+        }
+        return { kind: 'unchanged', html: secHdrRow('✅', 'success', label, `passed through unchanged`) };
+    }
+    if (arrived === null) {
+        return { kind: 'absent', html: secHdrRow('ℹ️', '', label, 'not sent by Sensillum and not added by the proxy') };
+    }
+    const problem = SECHDR_CHECKS[h.name]?.(arrived);
+    return problem
+        ? { kind: 'injected', html: secHdrRow('⚠️', 'warning', label, `injected by the proxy: <code>${escapeHtml(arrived)}</code> — ${escapeHtml(problem)}`) }
+        : { kind: 'injected', html: secHdrRow('✅', 'success', label, `injected by the proxy: <code>${escapeHtml(arrived)}</code>`) };
+}
+
+async function runSecurityHeaderTest() {
+    const btn = document.getElementById('sechdr-test-btn');
+    const resultsDiv = document.getElementById('sechdr-test-results');
+    btn.disabled = true;
+    btn.textContent = 'Testing…';
+
+    let html;
+    try {
+        const response = await fetch(`${urlPrefix}/security-headers`, { cache: 'no-store' });
+        const { baseline } = await response.json();
+        const counts = { injected: 0, removed: 0, rewritten: 0, unchanged: 0, absent: 0 };
+        let rows = '';
+        for (const h of baseline) {
+            const { kind, html: row } = gradeSecurityHeader(h, response.headers.get(h.name));
+            counts[kind]++;
+            rows += row;
+        }
+        html = `<div class="result-card">
+            <div style="font-weight:600;color:#2d3436;margin-bottom:10px;">Security headers</div>
+            <div class="result-detail" style="margin-bottom:10px;">The proxy injected ${counts.injected}, removed ${counts.removed} and rewrote ${counts.rewritten}; ${counts.unchanged} passed through unchanged.</div>
+            ${rows}
+        </div>`;
+    } catch (err) {
+        html = `<p style="color: #d63031; text-align: center;">Test failed: ${escapeHtml(err.message)}</p>`;
+    }
+
+    resultsDiv.innerHTML = html;
+    btn.disabled = false;
+    btn.textContent = 'Run Test';
+}
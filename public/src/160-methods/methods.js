@@ -0,0 +1,99 @@
+// HTTP method test — sends each method to /echo and compares the method
+// Sensillum received with the one sent.  Browsers refuse CONNECT, TRACE and
+// TRACK, so those are not tested here (see CURL.md for TRACE).
+
+const METHOD_TESTS = [
+    { method: 'GET', category: 'Standard' },
+    { method: 'HEAD', category: 'Standard' },
+    { method: 'POST', category: 'Standard' },
+    { method: 'PUT', category: 'Standard' },
+    { method: 'PATCH', category: 'Standard' },
+    { method: 'DELETE', category: 'Standard' },
+    { method: 'OPTIONS', category: 'Standard' },
+    { method: 'PROPFIND', category: 'Extension' },
+    { method: 'MKCOL', category: 'Extension' },
+    { method: 'PURGE', category: 'Extension' },
+    { method: 'BAN', category: 'Extension' },
+    { method: 'SENSILLUM', category: 'Extension' },
+];
+
+// Overrides are sent on a POST; a proxy that honours them turns the POST into DELETE.
+const METHOD_OVERRIDE_TESTS = [
+    { label: 'X-HTTP-Method-Override', headers: { 'X-HTTP-Method-Override': 'DELETE' }, query: '' },
+    { label: 'X-HTTP-Method', headers: { 'X-HTTP-Method': 'DELETE' }, query: '' },
+    { label: 'X-Method-Override', headers: { 'X-Method-Override': 'DELETE' }, query: '' },
+    { label: '_method query parameter', headers: {}, query: '?_method=DELETE' },
+];
+
+function methodRow(icon, cls, label, detail) {
+    const clsAttr = cls ? ` class="${cls}"` : ' style="color:#636e72;"';
+    return `<div class="result-detail" style="margin-bottom:4px;">
+        <span${clsAttr}>${icon}</span> <strong>${escapeHtml(label)}</strong>: ${detail}
+    </div>`;
+}
+
+async function probeMethod(method, headers, query) {
+    const response = await fetch(`${urlPrefix}/echo${query}`, { method, headers, cache: 'no-store' });
+    // HEAD has no body; Sensillum reports the method in a header instead.
+    const received = response.headers.get('x-sensillum-method');
+    let data = null;
+    if (method !== 'HEAD') {
+        try { data = await response.json(); } catch (_) { }
+    }
+    return { status: response.status, received: data?.method ?? received, data };
+}
+
+async function runMethodTest() {
+    const btn = document.getElementById('method-test-btn');
+    const resultsDiv = document.getElementById('method-test-results');
+    btn.disabled = true;
+
+    let html = '';
+    let category = null;
+    for (const [i, { method, category: cat }] of METHOD_TESTS.entries()) {
+        btn.textContent = `Testing… (${i}/${METHOD_TESTS.length})`;
+        if (cat !== category) {
+            if (category !== null) html += '</div>';
+            html += `<div class="result-card" style="margin-top:10px;"><div style="font-weight:600;color:#2d3436;margin-bottom:10px;">${escapeHtml(cat)} methods</div>`;
+            category = cat;
+        }
+        try {
+            const r = await probeMethod(method, {}, '');
+            if (r.received === method) {
+                html += methodRow('✅', 'success', method, `allowed (HTTP ${r.status})`);
+            } else if (r.received) {
+                html += methodRow('⚠️', 'warning', method, `rewritten — Sensillum received <code>${escapeHtml(r.received)}</code>`);
+            } else {
+                html += methodRow('🚫', 'fail', method, `blocked — the proxy answered HTTP ${r.status}`);
+            }
+        } catch (err) {
+            html += methodRow('❌', 'fail', method, `request failed: ${escapeHtml(err.message)}`);
+        }
+        resultsDiv.innerHTML = html + '</div>';
+    }
+    html += '</div>';
+
+    html += '<div class="result-card" style="margin-top:10px;"><div style="font-weight:600;color:#2d3436;margin-bottom:10px;">Method overrides (POST → DELETE)</div>';
+    for (const { label, headers, query } of METHOD_OVERRIDE_TESTS) {
+        try {
+            const r = await probeMethod('POST', headers, query);
+            if (!r.data) {
+                html += methodRow('🚫', 'fail', label, `blocked — the proxy answered HTTP ${r.status}`);
+            } else if (r.received === 'DELETE') {
+                html += methodRow('⚠️', 'warning', label, 'the proxy applied the override — Sensillum received DELETE');
+            } else if (Object.keys(r.data.method_overrides || {}).length > 0) {
+                html += methodRow('ℹ️', '', label, 'passed through unchanged — the backend decides whether to honour it');
+            } else {
+                html += methodRow('✅', 'success', label, 'stripped by the proxy');
+            }
+        } catch (err) {
+            html += methodRow('❌', 'fail', label, `request failed: ${escapeHtml(err.message)}`);
+        }
+        resultsDiv.innerHTML = html + '</div>';
+    }
+    html += '</div>';
+
+    resultsDiv.innerHTML = html;
+    btn.disabled = false;
+    btn.textContent = 'Run Test';
+}
@@ -0,0 +1,63 @@
+// Informational (1xx) responses.  fetch() never exposes a 1xx response, but
+// Resource Timing records when the first one arrived
+// (firstInterimResponseStart, Chromium only).  Browsers never send
+// Expect: 100-continue, so that part of the test is a curl command.
+
+const EARLY_HINTS_DELAY_MS = 800;
+
+function interimRow(icon, cls, label, detail) {
+    const clsAttr = cls ? ` class="${cls}"` : ' style="color:#636e72;"';
+    return `<div class="result-detail" style="margin-bottom:4px;">
+        <span${clsAttr}>${icon}</span> <strong>${escapeHtml(label)}</strong>: ${detail}
+    </div>`;
+}
+
+async function runInterimTest() {
+    const btn = document.getElementById('interim-test-btn');
+    const resultsDiv = document.getElementById('interim-test-results');
+    btn.disabled = true;
+    btn.textContent = 'Testing…';
+    resultsDiv.innerHTML = '<div class="result-detail">Requesting /early-hints…</div>';
+
+    let html = '<div class="result-card"><div style="font-weight:600;color:#2d3436;margin-bottom:10px;">103 Early Hints</div>';
+    const url = new URL(`${urlPrefix}/early-hints?delay_ms=${EARLY_HINTS_DELAY_MS}&t=${Date.now()}`, window.location.origin);
+    try {
+        const response = await fetch(url, { cache: 'no-store' });
+        let data = null;
+        try { data = await response.json(); } catch (_) { }
+        const timing = performance.getEntriesByName(url.href, 'resource').pop();
+
+        if (!data) {
+            html += interimRow('❌', 'fail', 'Request', `Did not reach Sensillum — the proxy answered with HTTP ${response.status}`);
+        } else if (!data.early_hints_sent) {
+            html += interimRow('ℹ️', '', data.protocol, 'The proxy talks HTTP/2 to Sensillum, which cannot send 1xx responses over HTTP/2, so no hint was sent.');
+        } else if (!timing || timing.firstInterimResponseStart === undefined) {
+            html += interimRow('ℹ️', '', 'Unknown', 'Sensillum sent 103 Early Hints, but this browser does not report interim responses. Try Chrome, or curl below.');
+        } else if (timing.firstInterimResponseStart > 0) {
+            const lead = timing.responseStart - timing.firstInterimResponseStart;
+            html += interimRow('✅', 'success', 'Forwarded', `103 arrived ${Math.round(lead)} ms before the final response`);
+        } else {
+            html += interimRow('⚠️', 'warning', 'Swallowed', 'Sensillum sent 103 Early Hints, but it never reached the browser');
+        }
+        if (data) {
+            html += interimRow('🔗', '', 'Link', `<code>${escapeHtml(data.link)}</code>`);
+        }
+    } catch (err) {
+        html += interimRow('❌', 'fail', 'Request', `failed: ${escapeHtml(err.message)}`);
+    }
+    const hintsUrl = `${window.location.origin}${urlPrefix}/early-hints`;
+    html += `<div class="result-detail"><code>curl -sv -o /dev/null '${escapeHtml(hintsUrl)}' 2&gt;&amp;1 | grep '^&lt; HTTP'</code></div>`;
+    html += '</div>';
+
+    const continueUrl = `${window.location.origin}${urlPrefix}/continue`;
+    html += '<div class="result-card" style="margin-top:10px;"><div style="font-weight:600;color:#2d3436;margin-bottom:10px;">100 Continue</div>';
+    html += interimRow('🌐', '', 'Browser blocked', 'Browsers never send <code>Expect: 100-continue</code>. Check with curl; Sensillum holds the 100 back for <code>delay_ms</code> and reports when the body arrived (<code>verdict</code>: <code>after-continue</code>, <code>before-continue</code>, <code>with-head</code> or <code>no-expect</code>):');
+    html += `<div class="result-detail"><code>curl -s -H 'Expect: 100-continue' --expect100-timeout 10 --data-binary hello '${escapeHtml(continueUrl)}?delay_ms=2000'</code></div>`;
+    html += interimRow('🌐', '', 'Refused', 'With <code>refuse</code>, Sensillum answers <code>417</code> without reading the body; the proxy should pass that on rather than send the body:');
+    html += `<div class="result-detail"><code>curl -si -H 'Expect: 100-continue' --data-binary hello '${escapeHtml(continueUrl)}?refuse'</code></div>`;
+    html += '</div>';
+
+    resultsDiv.innerHTML = html;
+    btn.disabled = false;
+    btn.textContent = 'Run Test';
+}
@@ -0,0 +1,84 @@
+// Path normalisation probe.  The case list comes from GET /norm so that the
+// server and the UI agree on it.  Browsers resolve dot-segments and turn
+// backslashes into slashes before sending, so the UI declares the path it
+// actually sent in X-Norm-Sent; those cases need `curl --path-as-is`.
+
+function normVerdictMeta(verdict) {
+    switch (verdict) {
+        case 'preserved': return { icon: '✅', cls: 'success', text: 'Preserved' };
+        case 're-encoded': return { icon: '⚠️', cls: 'warning', text: 'Re-encoded' };
+        case 'decoded': return { icon: '⚠️', cls: 'warning', text: 'Decoded' };
+        case 'modified': return { icon: '❌', cls: 'fail', text: 'Modified' };
+        case 'blocked': return { icon: '🚫', cls: '', text: 'Blocked' };
+        case 'error': return { icon: '❌', cls: 'fail', text: 'Error' };
+        default: return { icon: '?', cls: '', text: verdict };
+    }
+}
+
+async function runNormTest() {
+    const btn = document.getElementById('norm-test-btn');
+    const resultsDiv = document.getElementById('norm-test-results');
+    btn.disabled = true;
+    btn.textContent = 'Testing…';
+
+    let cases;
+    try {
+        const response = await fetch(`${urlPrefix}/norm`, { cache: 'no-store' });
+        cases = (await response.json()).cases;
+    } catch (err) {
+        resultsDiv.innerHTML = `<p style="color: #d63031; text-align: center;">Could not load test cases: ${escapeHtml(err.message)}</p>`;
+        btn.disabled = false;
+        btn.textContent = 'Run Test';
+        return;
+    }
+
+    const rows = [];
+    for (const [i, c] of cases.entries()) {
+        btn.textContent = `Testing… (${i}/${cases.length})`;
+        const intended = urlPrefix + c.path;
+        const url = new URL(intended, window.location.origin);
+        const sent = url.pathname;
+        const row = { c, intended, sent, browserChanged: sent !== intended };
+        try {
+            const response = await fetch(url, {
+                headers: { 'X-Norm-Case': c.id, 'X-Norm-Sent': sent },
+                cache: 'no-store'
+            });
+            let data = null;
+            try { data = await response.json(); } catch (_) { }
+            if (data && data.case === c.id) {
+                row.verdict = data.verdict;
+                row.arrived = data.arrived;
+            } else {
+                // Normalised out of /norm/, or rejected by the proxy
+                row.verdict = 'blocked';
+                row.arrived = `HTTP ${response.status}`;
+            }
+        } catch (err) {
+            row.verdict = 'error';
+            row.arrived = err.message;
+        }
+        rows.push(row);
+    }
+
+    let html = '<table class="norm-table"><thead><tr><th>Case</th><th>Sent</th><th>Arrived</th><th>Result</th></tr></thead><tbody>';
+    for (const { c, sent, browserChanged, verdict, arrived } of rows) {
+        const { icon, cls, text } = normVerdictMeta(verdict);
+        const clsAttr = cls ? ` class="${cls}"` : ' style="color:#636e72;"';
+        const browserNote = browserChanged
+            ? `<div style="color:#636e72;">🌐 browser sent this instead of <code>${escapeHtml(urlPrefix + c.path)}</code></div>`
+            : '';
+        html += `<tr>
+            <td>${escapeHtml(c.description)}</td>
+            <td><code>${escapeHtml(sent)}</code>${browserNote}</td>
+            <td><code>${escapeHtml(arrived)}</code></td>
+            <td><span${clsAttr}>${icon} ${escapeHtml(text)}</span></td>
+        </tr>`;
+    }
+    html += '</tbody></table>';
+    html += '<div class="result-detail" style="margin-top:10px;">Cases marked 🌐 were normalised by the browser; see CURL.md to send them verbatim.</div>';
+    resultsDiv.innerHTML = html;
+
+    btn.disabled = false;
+    btn.textContent = 'Run Test';
+}
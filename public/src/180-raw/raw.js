@@ -0,0 +1,88 @@
+// Malformed response test.  The case list comes from GET /raw; each case is
+// written byte for byte by Sensillum.  The browser's own parser sits behind
+// the proxy, so "accepted" means the proxy and the browser both let it
+// through, while a 5xx without X-Raw-Case means the proxy refused it.
+
+function rawRow(icon, cls, label, detail) {
+    const clsAttr = cls ? ` class="${cls}"` : ' style="color:#636e72;"';
+    return `<div class="result-detail" style="margin-bottom:4px;">
+        <span${clsAttr}>${icon}</span> <strong>${escapeHtml(label)}</strong>: ${detail}
+    </div>`;
+}
+
+async function probeRawCase(c) {
+    let response;
+    try {
+        response = await fetch(`${urlPrefix}/raw?case=${encodeURIComponent(c.id)}`, { cache: 'no-store' });
+    } catch (err) {
+        return rawRow('🔌', '', c.description, `no usable response — the proxy reset the connection or the browser rejected it (${escapeHtml(err.message)})`);
+    }
+    let body = '';
+    try { body = await response.text(); } catch (_) { }
+
+    if (response.headers.get('x-raw-case') === null && c.id !== 'http09') {
+        return response.status >= 500
+            ? rawRow('✅', 'success', c.description, `rejected by the proxy (HTTP ${response.status})`)
+            : rawRow('ℹ️', '', c.description, `the proxy answered HTTP ${response.status} itself`);
+    }
+
+    const details = [`HTTP ${response.status}`, `body <code>${escapeHtml(body.slice(0, 40))}</code>`];
+    for (const [name, value] of response.headers) {
+        if (name.startsWith('x-raw-') && name !== 'x-raw-case') {
+            details.push(`<code>${escapeHtml(name)}: ${escapeHtml(value)}</code>`);
+        }
+    }
+    if (c.id === 'baseline') {
+        return rawRow(body === 'raw-ok' ? '✅' : '⚠️', body === 'raw-ok' ? 'success' : 'warning', c.description, details.join(', '));
+    }
+    return rawRow('⚠️', 'warning', c.description, `accepted — ${details.join(', ')}`);
+}
+
+async function runRawTest() {
+    const btn = document.getElementById('raw-test-btn');
+    const resultsDiv = document.getElementById('raw-test-results');
+    btn.disabled = true;
+    btn.textContent = 'Testing…';
+
+    let manifest;
+    try {
+        const response = await fetch(`${urlPrefix}/raw`, { cache: 'no-store' });
+        manifest = await response.json();
+    } catch (err) {
+        resultsDiv.innerHTML = `<p style="color: #d63031; text-align: center;">Could not load test cases: ${escapeHtml(err.message)}</p>`;
+        btn.disabled = false;
+        btn.textContent = 'Run Test';
+        return;
+    }
+    if (!manifest.enabled) {
+        resultsDiv.innerHTML = '<p style="color: #636e72; text-align: center;">Raw responses are disabled. Start Sensillum with <code>--raw-responses</code> (or set <code>SENSILLUM_RAW_RESPONSES</code>) to run this test. They can desynchronise the proxy\'s connections, so avoid shared production proxies.</p>';
+        btn.disabled = false;
+        btn.textContent = 'Run Test';
+        return;
+    }
+
+    let html = '<div class="result-card">';
+    for (const [i, c] of manifest.cases.entries()) {
+        btn.textContent = `Testing… (${i}/${manifest.cases.length})`;
+        html += await probeRawCase(c);
+
+        // A proxy that reuses the connection may hand the leftover response
+        // to the next request.
+        if (c.id === 'trailing-response') {
+            try {
+                const next = await fetch(`${urlPrefix}/echo`, { cache: 'no-store' });
+                html += next.headers.get('x-raw-smuggled') !== null
+                    ? rawRow('❌', 'fail', 'Follow-up request', 'received the leftover response — the proxy\'s response queue is poisoned')
+                    : rawRow('✅', 'success', 'Follow-up request', 'received its own response');
+            } catch (err) {
+                html += rawRow('❌', 'fail', 'Follow-up request', `failed: ${escapeHtml(err.message)}`);
+            }
+        }
+        resultsDiv.innerHTML = html + '</div>';
+    }
+    html += '</div>';
+
+    resultsDiv.innerHTML = html;
+    btn.disabled = false;
+    btn.textContent = 'Run Test';
+}
@@ -0,0 +1,79 @@
+// Error page interception.  Each status comes back from /error with a unique
+// marker in the body and in X-Sensillum-Error-Marker; a body without the
+// marker was replaced by the proxy.  Replacement pages are rendered in a
+// shadow root, like proxy error bodies in the LB test.
+
+// Server software and versions worth flagging in a replacement page.
+const ERRPAGE_LEAK_PATTERN = /\b(nginx|openresty|apache|envoy|haproxy|traefik|varnish|squid|cloudflare|akamai|iis|tomcat|jetty|caddy)(\/[\d.]+)?/gi;
+
+function errPageRow(icon, cls, label, detail) {
+    const clsAttr = cls ? ` class="${cls}"` : ' style="color:#636e72;"';
+    return `<div class="result-detail" style="margin-bottom:4px;">
+        <span${clsAttr}>${icon}</span> <strong>${escapeHtml(label)}</strong>: ${detail}
+    </div>`;
+}
+
+async function probeErrorStatus(status) {
+    const marker = `m${status}-${Math.random().toString(36).slice(2, 10)}`;
+    let response;
+    try {
+        response = await fetch(`${urlPrefix}/error?status=${status}&marker=${marker}`, { cache: 'no-store' });
+    } catch (err) {
+        return { row: errPageRow('🔌', 'fail', `HTTP ${status}`, `no response — the connection was reset (${escapeHtml(err.message)})`) };
+    }
+    const body = await response.text().catch(() => '');
+    const bodyMarked = body.includes(marker);
+    const headerMarked = response.headers.get('x-sensillum-error-marker') === marker;
+    const statusNote = response.status === status ? '' : ` and changed the status to <strong>${response.status}</strong>`;
+
+    if (bodyMarked) {
+        const headerNote = headerMarked ? '' : ' (marker header stripped)';
+        return response.status === status
+            ? { row: errPageRow('✅', 'success', `HTTP ${status}`, `passed through${headerNote}`) }
+            : { row: errPageRow('⚠️', 'warning', `HTTP ${status}`, `body passed through, but the proxy changed the status to <strong>${response.status}</strong>${headerNote}`) };
+    }
+
+    const leaks = [...new Set((body.match(ERRPAGE_LEAK_PATTERN) || []).map(s => s.toLowerCase()))];
+    const leakNote = leaks.length ? ` — mentions <code>${escapeHtml(leaks.join(', '))}</code>` : '';
+    const origin = headerMarked ? 'the backend headers arrived, so the proxy swapped the body' : 'the proxy generated its own response';
+    return {
+        row: errPageRow(leaks.length ? '❌' : '⚠️', leaks.length ? 'fail' : 'warning', `HTTP ${status}`,
+            `replaced${statusNote}; ${origin}${leakNote}`),
+        body,
+    };
+}
+
+async function runErrorPageTest() {
+    const btn = document.getElementById('errpage-test-btn');
+    const resultsDiv = document.getElementById('errpage-test-results');
+    btn.disabled = true;
+
+    let statuses;
+    try {
+        statuses = (await (await fetch(`${urlPrefix}/error`, { cache: 'no-store' })).json()).statuses;
+    } catch (err) {
+        resultsDiv.innerHTML = `<p style="color: #d63031; text-align: center;">Could not load statuses: ${escapeHtml(err.message)}</p>`;
+        btn.disabled = false;
+        return;
+    }
+
+    resultsDiv.innerHTML = '<div class="result-card"></div>';
+    const card = resultsDiv.querySelector('.result-card');
+    for (const [i, status] of statuses.entries()) {
+        btn.textContent = `Testing… (${i}/${statuses.length})`;
+        const { row, body } = await probeErrorStatus(status);
+        card.insertAdjacentHTML('beforeend', row);
+        if (body) {
+            card.insertAdjacentHTML('beforeend', `<details class="errpage-body">
+                <summary>Show replacement page</summary>
+                <div class="errpage-shadow-host"></div>
+            </details>`);
+            const hosts = card.querySelectorAll('.errpage-shadow-host');
+            const shadow = hosts[hosts.length - 1].attachShadow({ mode: 'open' });
+            shadow.innerHTML = body;
+        }
+    }
+
+    btn.disabled = false;
+    btn.textContent = 'Run Test';
+}
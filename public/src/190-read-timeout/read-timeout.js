@@ -0,0 +1,93 @@
+// Proxy read-timeout finder.  /slow holds back its first byte for the
+// requested time; the longest delay that still gets a 200 through the proxy
+// brackets the proxy's upstream read timeout.
+
+const READ_TIMEOUT_MAX_S = 300;
+
+async function probeSlowResponse(seconds) {
+    try {
+        const response = await fetch(`${urlPrefix}/slow?delay_ms=${seconds * 1000}`, { cache: 'no-store' });
+        if (!response.ok) return { ok: false, status: response.status };
+        const data = await response.json();
+        return { ok: data.delay_ms === seconds * 1000, status: response.status };
+    } catch {
+        return { ok: false, status: null }; // reset or closed without a response
+    }
+}
+
+function formatReadTimeoutFailure(status) {
+    if (status === null) {
+        return '<span style="color:#e17055;">🔌 Connection reset</span> — the proxy closed the connection without an error response.';
+    }
+    if (status === 504) {
+        return '<span style="color:#00b894;">✅ HTTP 504 Gateway Timeout</span> — the correct response for an upstream timeout (RFC 9110 §15.6.5).';
+    }
+    if (status === 502) {
+        return '<span style="color:#e17055;">⚠️ HTTP 502 Bad Gateway</span> — common, but 504 would be more accurate for a timeout.';
+    }
+    return `<span style="color:#e17055;">⚠️ HTTP ${status}</span> — an unusual status for an upstream timeout.`;
+}
+
+async function runReadTimeoutTest() {
+    const btn = document.getElementById('readtimeout-test-btn');
+    const resultsDiv = document.getElementById('readtimeout-test-results');
+    btn.disabled = true;
+    btn.textContent = 'Testing…';
+    resultsDiv.innerHTML = `
+                <div class="result-card">
+                    <div style="font-weight: 600; color: #2d3436;">Searching for the proxy read timeout...</div>
+                    <div class="result-detail">Starting…</div>
+                </div>
+            `;
+    const statusDiv = resultsDiv.querySelector('.result-detail');
+
+    // Known good delay, first failing delay and how it failed.
+    let lower = 0;
+    let upper = null;
+    let failure;
+    let tests = 0;
+
+    async function attempt(seconds) {
+        tests++;
+        const t0 = performance.now();
+        const timer = setInterval(() => {
+            const waited = Math.round((performance.now() - t0) / 1000);
+            statusDiv.textContent = `Waiting for a ${seconds} s response… ${waited} s (request ${tests}; works up to ${lower} s so far)`;
+        }, 1000);
+        const r = await probeSlowResponse(seconds);
+        clearInterval(timer);
+        if (r.ok) {
+            lower = seconds;
+        } else {
+            upper = seconds;
+            failure = r.status;
+        }
+        return r.ok;
+    }
+
+    // Double until something fails, then bisect to one-second precision.
+    for (let s = 2; upper === null; s = Math.min(s * 2, READ_TIMEOUT_MAX_S)) {
+        if (!await attempt(s) && s === 2) break;
+        if (s === READ_TIMEOUT_MAX_S) break;
+    }
+    while (upper !== null && upper - lower > 1) {
+        await attempt(Math.floor((lower + upper) / 2));
+    }
+
+    let html;
+    if (upper === null) {
+        html = `<div class="result-value">&gt; ${READ_TIMEOUT_MAX_S} s</div>
+            <div class="result-detail">A ${READ_TIMEOUT_MAX_S} s response still came through; the proxy timeout is longer than this test checks.</div>`;
+    } else {
+        html = `<div class="result-value">${lower}–${upper} s</div>
+            <div class="result-detail">📊 A ${lower} s response came through; a ${upper} s response did not (${tests} requests).</div>
+            <div class="result-detail" style="margin-top:8px;">🚫 On timeout: ${formatReadTimeoutFailure(failure)}</div>`;
+    }
+    resultsDiv.innerHTML = `<div class="result-card">
+            <div style="font-weight: 600; color: #2d3436; margin-bottom: 10px;">Proxy read timeout</div>
+            ${html}
+            <div class="result-detail" style="margin-top:8px;">Sensillum's log says whether the proxy was still connected when each slow response was ready.</div>
+        </div>`;
+    btn.disabled = false;
+    btn.textContent = 'Run Test';
+}
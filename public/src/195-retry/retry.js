@@ -0,0 +1,91 @@
+// Proxy retry detection.  /retry fails the first attempt each node sees for
+// a token; every node logs the attempts it received.  The logs are collected
+// by asking /retry/log through the load balancer until each node has
+// answered a few times.
+
+const RETRY_SCENARIOS = [
+    { method: 'GET', mode: 'status', label: 'GET, 503' },
+    { method: 'POST', mode: 'status', label: 'POST, 503' },
+    { method: 'GET', mode: 'reset', label: 'GET, connection reset' },
+    { method: 'POST', mode: 'reset', label: 'POST, connection reset' },
+];
+
+// How many /retry/log requests to spread over the nodes behind the proxy.
+const RETRY_LOG_REQUESTS = 10;
+
+function retryRow(icon, cls, label, detail) {
+    const clsAttr = cls ? ` class="${cls}"` : ' style="color:#636e72;"';
+    return `<div class="result-detail" style="margin-bottom:4px;">
+        <span${clsAttr}>${icon}</span> <strong>${escapeHtml(label)}</strong>: ${detail}
+    </div>`;
+}
+
+async function collectRetryAttempts(token) {
+    const seen = new Map();
+    for (let i = 0; i < RETRY_LOG_REQUESTS; i++) {
+        try {
+            const response = await fetch(`${urlPrefix}/retry/log?token=${token}`, { cache: 'no-store' });
+            const data = await response.json();
+            for (const a of data.attempts || []) {
+                seen.set(`${a.node}|${a.attempt}|${a.at_ms}`, a);
+            }
+        } catch {
+            // A node that cannot answer simply contributes nothing.
+        }
+    }
+    return [...seen.values()].sort((a, b) => a.at_ms - b.at_ms);
+}
+
+async function probeRetry(scenario) {
+    const token = `r${Math.random().toString(36).slice(2, 12)}`;
+    let received;
+    try {
+        const response = await fetch(`${urlPrefix}/retry?token=${token}&fail=1&mode=${scenario.mode}`, {
+            method: scenario.method,
+            cache: 'no-store',
+            ...(scenario.method === 'POST' ? { body: 'retry=1', headers: { 'Content-Type': 'application/x-www-form-urlencoded' } } : {}),
+        });
+        received = `HTTP ${response.status}`;
+    } catch {
+        received = 'a network error';
+    }
+
+    const attempts = await collectRetryAttempts(token);
+    const nodes = [...new Set(attempts.map(a => a.node ?? '(unnamed node)'))];
+    const retries = attempts.length - 1;
+    let row;
+    if (attempts.length === 0) {
+        row = retryRow('⚠️', 'warning', scenario.label, `no attempt found in the logs of the nodes reached (client got ${received})`);
+    } else if (retries === 0) {
+        row = retryRow('✅', 'success', scenario.label, `not retried — the client got ${received}`);
+    } else {
+        const where = nodes.length > 1 ? `across ${nodes.length} nodes` : 'on the same node';
+        const [icon, cls, why] = scenario.method === 'POST'
+            ? ['❌', 'fail', ' — a non-idempotent request was sent more than once']
+            : ['⚠️', 'warning', ''];
+        row = retryRow(icon, cls, scenario.label, `retried ${retries}× ${where}; the client got ${received}${why}`);
+    }
+    const t0 = attempts.length ? attempts[0].at_ms : 0;
+    const log = attempts.map(a =>
+        `+${a.at_ms - t0} ms · ${escapeHtml(a.node ?? '(unnamed node)')} · conn ${a.connection} · ${escapeHtml(a.method)} · ${escapeHtml(a.outcome)}`
+    ).join('<br>');
+    return row + (log ? `<div class="retry-attempts">${log}</div>` : '');
+}
+
+async function runRetryTest() {
+    const btn = document.getElementById('retry-test-btn');
+    const resultsDiv = document.getElementById('retry-test-results');
+    btn.disabled = true;
+
+    resultsDiv.innerHTML = '<div class="result-card"></div>';
+    const card = resultsDiv.querySelector('.result-card');
+    for (const [i, scenario] of RETRY_SCENARIOS.entries()) {
+        btn.textContent = `Testing… (${i}/${RETRY_SCENARIOS.length})`;
+        card.insertAdjacentHTML('beforeend', await probeRetry(scenario));
+    }
+    card.insertAdjacentHTML('beforeend', retryRow('ℹ️', '', 'Note',
+        'each node fails the first attempt it sees, so a retry on another node fails too; name your nodes with <code>--node</code> to tell them apart'));
+
+    btn.disabled = false;
+    btn.textContent = 'Run Test';
+}
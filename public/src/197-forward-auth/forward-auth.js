@@ -0,0 +1,109 @@
+// Forward auth sub-request viewer.  Every node logs the /auth sub-requests
+// it answered; the logs are collected by asking /auth-log through the load
+// balancer a few times.  An echo request shows whether this page itself went
+// through forward auth and which of the returned headers reached the backend.
+
+const FORWARD_AUTH_LOG_REQUESTS = 5;
+const FORWARD_AUTH_SHOWN = 20;
+
+function forwardAuthRow(icon, cls, label, detail) {
+    const clsAttr = cls ? ` class="${cls}"` : ' style="color:#636e72;"';
+    return `<div class="result-detail" style="margin-bottom:4px;">
+        <span${clsAttr}>${icon}</span> <strong>${escapeHtml(label)}</strong>: ${detail}
+    </div>`;
+}
+
+function forwardAuthHeaderValue(value) {
+    if (typeof value === 'string') return value;
+    return value && value.redacted ? '(redacted)' : '(binary)';
+}
+
+function forwardAuthHeaderList(headers) {
+    const lines = Object.entries(headers || {})
+        .map(([name, value]) => `${escapeHtml(name)}: ${escapeHtml(forwardAuthHeaderValue(value))}`);
+    return lines.length ? lines.join('<br>') : '<em>none</em>';
+}
+
+async function collectForwardAuthLog() {
+    const seen = new Map();
+    for (let i = 0; i < FORWARD_AUTH_LOG_REQUESTS; i++) {
+        try {
+            const response = await fetch(`${urlPrefix}/auth-log`, { cache: 'no-store' });
+            const data = await response.json();
+            for (const r of data.requests || []) {
+                seen.set(r.id, r);
+            }
+        } catch {
+            // A node that cannot answer simply contributes nothing.
+        }
+    }
+    return [...seen.values()].sort((a, b) => b.at_ms - a.at_ms);
+}
+
+// Did this page's own request pass through /auth, and did the headers the
+// allowed answer carried arrive at the backend?
+function forwardAuthEchoRow(echoHeaders, requests) {
+    const id = echoHeaders['x-sensillum-auth-id'];
+    if (typeof id !== 'string') {
+        return forwardAuthRow('ℹ️', '', 'This page',
+            'the request did not carry <code>X-Sensillum-Auth-Id</code> — it did not pass through Sensillum\'s <code>/auth</code>, or the proxy does not copy auth response headers upstream');
+    }
+    const entry = requests.find(r => r.id === id);
+    if (!entry) {
+        return forwardAuthRow('✅', 'success', 'This page', `passed forward auth (sub-request <code>${escapeHtml(id)}</code>, not in the logs collected)`);
+    }
+    const missing = (entry.injected || []).filter(h => !(h.name.toLowerCase() in echoHeaders)).map(h => h.name);
+    if (missing.length) {
+        return forwardAuthRow('⚠️', 'warning', 'This page',
+            `passed forward auth, but the proxy did not pass ${missing.map(n => `<code>${escapeHtml(n)}</code>`).join(', ')} to the backend`);
+    }
+    return forwardAuthRow('✅', 'success', 'This page',
+        `passed forward auth (sub-request <code>${escapeHtml(id)}</code>) and every returned header reached the backend`);
+}
+
+function forwardAuthEntry(r, now) {
+    const o = r.original || {};
+    const label = `${o.method} ${o.uri ?? '(original URI unknown)'}`;
+    const decision = r.rule ? `by rule <code>${escapeHtml(r.rule)}</code>` : 'no rule matched';
+    const ago = Math.max(0, Math.round((now - r.at_ms) / 1000));
+    const detail = `${r.status} ${decision} · ${escapeHtml(o.source)}${o.host ? ` · host ${escapeHtml(o.host)}` : ''} · ${escapeHtml(r.node ?? '(unnamed node)')} · ${ago} s ago`;
+    const row = r.allowed
+        ? forwardAuthRow('✅', 'success', label, detail)
+        : forwardAuthRow('⛔', 'fail', label, detail);
+    return row + `<details class="auth-subrequest">
+        <summary>${escapeHtml(r.method)} ${escapeHtml(r.uri)} · ${escapeHtml(r.protocol)} · ${r.body_bytes} body bytes</summary>
+        <div class="auth-subrequest-headers"><strong>Forwarding headers</strong><br>${forwardAuthHeaderList(r.forwarded)}</div>
+        <div class="auth-subrequest-headers"><strong>All headers</strong><br>${forwardAuthHeaderList(r.headers)}</div>
+    </details>`;
+}
+
+async function loadForwardAuth() {
+    const btn = document.getElementById('forward-auth-btn');
+    const resultsDiv = document.getElementById('forward-auth-results');
+    btn.disabled = true;
+    btn.textContent = 'Loading…';
+
+    let echoHeaders = {};
+    try {
+        const response = await fetch(`${urlPrefix}/echo`, { cache: 'no-store' });
+        echoHeaders = (await response.json()).headers || {};
+    } catch {
+        // Without the echo only the logs are shown.
+    }
+    const requests = await collectForwardAuthLog();
+
+    resultsDiv.innerHTML = '<div class="result-card"></div>';
+    const card = resultsDiv.querySelector('.result-card');
+    card.insertAdjacentHTML('beforeend', forwardAuthEchoRow(echoHeaders, requests));
+    if (requests.length === 0) {
+        card.insertAdjacentHTML('beforeend', forwardAuthRow('ℹ️', '', 'Sub-requests',
+            `none yet — configure the proxy to ask <code>${escapeHtml(location.origin + urlPrefix)}/auth</code> (or Sensillum's address inside your network)`));
+    }
+    const now = Date.now();
+    for (const r of requests.slice(0, FORWARD_AUTH_SHOWN)) {
+        card.insertAdjacentHTML('beforeend', forwardAuthEntry(r, now));
+    }
+
+    btn.disabled = false;
+    btn.textContent = 'Refresh';
+}
@@ -0,0 +1,564 @@
+use hyper::HeaderMap;
+use serde_json::{json, Map, Value};
+use sha2::{Digest, Sha256};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::handlers::common::{base64_decode, percent_decode_bytes};
+
+/// A proxy convention for passing the TLS client certificate on: headers
+/// carrying the certificate itself, and headers carrying details the proxy
+/// already extracted.
+struct CertSource {
+    id: &'static str,
+    cert_headers: &'static [&'static str],
+    info_prefix: Option<&'static str>,
+}
+
+static SOURCES: &[CertSource] = &[
+    CertSource { id: "nginx", cert_headers: &["ssl-client-cert", "x-ssl-client-cert", "x-ssl-cert"], info_prefix: Some("x-ssl-client-") },
+    CertSource { id: "traefik", cert_headers: &["x-forwarded-tls-client-cert"], info_prefix: Some("x-forwarded-tls-client-cert-") },
+    CertSource { id: "aws-alb", cert_headers: &["x-amzn-mtls-clientcert", "x-amzn-mtls-clientcert-leaf"], info_prefix: Some("x-amzn-mtls-clientcert-") },
+];
+
+/// Envoy's `X-Forwarded-Client-Cert`, one element per proxy that added one.
+const XFCC: &str = "x-forwarded-client-cert";
+
+// --- DER -------------------------------------------------------------------
+
+/// Reads DER TLVs from a byte slice.
+struct Der<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Der<'a> {
+    fn new(data: &'a [u8]) -> Der<'a> {
+        Der { data }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    fn peek_tag(&self) -> Option<u8> {
+        self.data.first().copied()
+    }
+
+    /// The next TLV's tag and contents.
+    fn read(&mut self) -> Result<(u8, &'a [u8]), String> {
+        let [tag, first, rest @ ..] = self.data else {
+            return Err("truncated DER".to_string());
+        };
+        let (len, rest) = match *first {
+            n if n < 0x80 => (n as usize, rest),
+            n @ 0x81..=0x84 => {
+                let count = (n & 0x7f) as usize;
+                if rest.len() < count {
+                    return Err("truncated DER length".to_string());
+                }
+                let len = rest[..count].iter().fold(0usize, |acc, b| (acc << 8) | *b as usize);
+                (len, &rest[count..])
+            }
+            _ => return Err("unsupported DER length".to_string()),
+        };
+        if rest.len() < len {
+            return Err("truncated DER contents".to_string());
+        }
+        let (contents, remaining) = rest.split_at(len);
+        self.data = remaining;
+        Ok((*tag, contents))
+    }
+
+    fn expect(&mut self, want: u8, what: &str) -> Result<&'a [u8], String> {
+        match self.read()? {
+            (tag, contents) if tag == want => Ok(contents),
+            (tag, _) => Err(format!("expected {what}, found tag 0x{tag:02x}")),
+        }
+    }
+}
+
+const SEQUENCE: u8 = 0x30;
+const SET: u8 = 0x31;
+const OID: u8 = 0x06;
+const OCTET_STRING: u8 = 0x04;
+const INTEGER: u8 = 0x02;
+const UTC_TIME: u8 = 0x17;
+const GENERALIZED_TIME: u8 = 0x18;
+
+fn oid_string(bytes: &[u8]) -> String {
+    let mut parts: Vec<u64> = Vec::new();
+    let mut value: u64 = 0;
+    for b in bytes {
+        value = (value << 7) | (b & 0x7f) as u64;
+        if b & 0x80 == 0 {
+            if parts.is_empty() {
+                let first = (value / 40).min(2);
+                parts.push(first);
+                parts.push(value - first * 40);
+            } else {
+                parts.push(value);
+            }
+            value = 0;
+        }
+    }
+    parts.iter().map(u64::to_string).collect::<Vec<_>>().join(".")
+}
+
+fn attribute_name(oid: &str) -> String {
+    match oid {
+        "2.5.4.3" => "CN",
+        "2.5.4.5" => "serialNumber",
+        "2.5.4.6" => "C",
+        "2.5.4.7" => "L",
+        "2.5.4.8" => "ST",
+        "2.5.4.9" => "street",
+        "2.5.4.10" => "O",
+        "2.5.4.11" => "OU",
+        "1.2.840.113549.1.9.1" => "emailAddress",
+        "0.9.2342.19200300.100.1.1" => "UID",
+        "0.9.2342.19200300.100.1.25" => "DC",
+        other => other,
+    }
+    .to_string()
+}
+
+/// A Name as an RFC 4514 string (most specific attribute first).
+fn name_string(contents: &[u8]) -> Result<String, String> {
+    let mut rdns = Vec::new();
+    let mut names = Der::new(contents);
+    while !names.is_empty() {
+        let mut set = Der::new(names.expect(SET, "RDN set")?);
+        let mut parts = Vec::new();
+        while !set.is_empty() {
+            let mut atv = Der::new(set.expect(SEQUENCE, "attribute")?);
+            let oid = oid_string(atv.expect(OID, "attribute type")?);
+            let (_, value) = atv.read()?;
+            let value = String::from_utf8_lossy(value).replace(',', "\\,");
+            parts.push(format!("{}={}", attribute_name(&oid), value));
+        }
+        rdns.push(parts.join("+"));
+    }
+    rdns.reverse();
+    Ok(rdns.join(","))
+}
+
+/// Days since 1970-01-01 of a proleptic Gregorian date.
+fn days_from_civil(y: i64, m: i64, d: i64) -> i64 {
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let doy = (153 * (m + if m > 2 { -3 } else { 9 }) + 2) / 5 + d - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+/// A UTCTime or GeneralizedTime as ISO 8601 and Unix seconds.
+fn parse_time(tag: u8, contents: &[u8]) -> Result<(String, i64), String> {
+    let text = std::str::from_utf8(contents).map_err(|_| "time is not text".to_string())?;
+    let digits = text.strip_suffix('Z').ok_or("time is not in UTC")?;
+    let full = match (tag, digits.len()) {
+        (UTC_TIME, 12) => {
+            let yy: u32 = digits[..2].parse().map_err(|_| "bad year")?;
+            format!("{}{}", if yy >= 50 { "19" } else { "20" }, digits)
+        }
+        (GENERALIZED_TIME, 14) => digits.to_string(),
+        _ => return Err(format!("unsupported time '{text}'")),
+    };
+    if !full.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(format!("unsupported time '{text}'"));
+    }
+    let n = |r: std::ops::Range<usize>| full[r].parse::<i64>().unwrap_or(0);
+    let (y, mo, d, h, mi, s) = (n(0..4), n(4..6), n(6..8), n(8..10), n(10..12), n(12..14));
+    let unix = days_from_civil(y, mo, d) * 86400 + h * 3600 + mi * 60 + s;
+    Ok((format!("{y:04}-{mo:02}-{d:02}T{h:02}:{mi:02}:{s:02}Z"), unix))
+}
+
+/// `GeneralNames` from the subjectAltName extension.
+fn subject_alt_names(contents: &[u8]) -> Result<Vec<String>, String> {
+    let mut names = Der::new(Der::new(contents).expect(SEQUENCE, "GeneralNames")?);
+    let mut out = Vec::new();
+    while !names.is_empty() {
+        let (tag, value) = names.read()?;
+        let text = || String::from_utf8_lossy(value).into_owned();
+        out.push(match tag {
+            0x81 => format!("email:{}", text()),
+            0x82 => format!("DNS:{}", text()),
+            0x86 => format!("URI:{}", text()),
+            0x87 => match value.len() {
+                4 => format!("IP:{}", std::net::Ipv4Addr::new(value[0], value[1], value[2], value[3])),
+                16 => {
+                    let octets: [u8; 16] = value.try_into().unwrap_or([0; 16]);
+                    format!("IP:{}", std::net::Ipv6Addr::from(octets))
+                }
+                _ => "IP:(invalid)".to_string(),
+            },
+            0xa4 => format!("dirName:{}", name_string(Der::new(value).expect(SEQUENCE, "directory name")?)?),
+            0xa0 => "otherName".to_string(),
+            other => format!("(tag 0x{other:02x})"),
+        });
+    }
+    Ok(out)
+}
+
+/// Decode a DER certificate into the fields that identify the client.
+fn decode_certificate(der: &[u8]) -> Result<Value, String> {
+    let mut cert = Der::new(Der::new(der).expect(SEQUENCE, "certificate")?);
+    let mut tbs = Der::new(cert.expect(SEQUENCE, "tbsCertificate")?);
+    if tbs.peek_tag() == Some(0xa0) {
+        tbs.read()?; // version
+    }
+    let serial = tbs.expect(INTEGER, "serial number")?;
+    tbs.expect(SEQUENCE, "signature algorithm")?;
+    let issuer = name_string(tbs.expect(SEQUENCE, "issuer")?)?;
+    let mut validity = Der::new(tbs.expect(SEQUENCE, "validity")?);
+    let (tag, nb) = validity.read()?;
+    let (not_before, nb_unix) = parse_time(tag, nb)?;
+    let (tag, na) = validity.read()?;
+    let (not_after, na_unix) = parse_time(tag, na)?;
+    let subject = name_string(tbs.expect(SEQUENCE, "subject")?)?;
+    tbs.expect(SEQUENCE, "subject public key info")?;
+
+    let mut sans = Vec::new();
+    while !tbs.is_empty() {
+        let (tag, contents) = tbs.read()?;
+        if tag != 0xa3 {
+            continue; // issuer/subject unique IDs
+        }
+        let mut extensions = Der::new(Der::new(contents).expect(SEQUENCE, "extensions")?);
+        while !extensions.is_empty() {
+            let mut ext = Der::new(extensions.expect(SEQUENCE, "extension")?);
+            let oid = oid_string(ext.expect(OID, "extension ID")?);
+            if ext.peek_tag() == Some(0x01) {
+                ext.read()?; // critical
+            }
+            let value = ext.expect(OCTET_STRING, "extension value")?;
+            if oid == "2.5.29.17" {
+                sans = subject_alt_names(value)?;
+            }
+        }
+    }
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+    let validity = if now < nb_unix {
+        "not yet valid"
+    } else if now > na_unix {
+        "expired"
+    } else {
+        "valid"
+    };
+    Ok(json!({
+        "subject": subject,
+        "issuer": issuer,
+        "serial": serial.iter().map(|b| format!("{b:02x}")).collect::<String>(),
+        "sans": sans,
+        "not_before": not_before,
+        "not_after": not_after,
+        "validity": validity,
+        "self_signed": subject == issuer,
+        "sha256": Sha256::digest(der).iter().map(|b| format!("{b:02x}")).collect::<String>(),
+    }))
+}
+
+// --- Encodings ---------------------------------------------------------------
+
+/// The DER certificates in a header value: URL-encoded or plain PEM, or
+/// (Traefik) comma-separated base64 without the PEM armour.
+fn certificates_in(value: &str) -> Result<Vec<Vec<u8>>, String> {
+    let decoded = if value.contains('%') {
+        String::from_utf8_lossy(&percent_decode_bytes(value)).into_owned()
+    } else {
+        value.to_string()
+    };
+    const BEGIN: &str = "-----BEGIN CERTIFICATE-----";
+    const END: &str = "-----END CERTIFICATE-----";
+    if decoded.contains(BEGIN) {
+        decoded
+            .split(BEGIN)
+            .skip(1)
+            .map(|block| {
+                let body = block.split(END).next().unwrap_or("");
+                base64_decode(body)
+            })
+            .collect()
+    } else {
+        decoded
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(base64_decode)
+            .collect()
+    }
+}
+
+/// Split on `sep` outside double quotes.
+fn split_unquoted(s: &str, sep: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let (mut start, mut quoted, mut escaped) = (0, false, false);
+    for (i, c) in s.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            c if c == sep && !quoted => {
+                parts.push(&s[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&s[start..]);
+    parts
+}
+
+fn unquote(s: &str) -> String {
+    match s.strip_prefix('"').and_then(|s| s.strip_suffix('"')) {
+        Some(inner) => inner.replace("\\\"", "\"").replace("\\\\", "\\"),
+        None => s.to_string(),
+    }
+}
+
+// --- Report ------------------------------------------------------------------
+
+fn header_text(headers: &HeaderMap, name: &str) -> Option<String> {
+    headers
+        .get(name)
+        .map(|v| String::from_utf8_lossy(v.as_bytes()).into_owned())
+}
+
+fn decode_into(value: &str, header: &str, certificates: &mut Vec<Value>, errors: &mut Vec<String>) -> Vec<String> {
+    let mut fingerprints = Vec::new();
+    match certificates_in(value) {
+        Ok(ders) => {
+            for der in ders {
+                match decode_certificate(&der) {
+                    Ok(mut cert) => {
+                        fingerprints.push(cert["sha256"].as_str().unwrap_or("").to_string());
+                        cert["header"] = json!(header);
+                        certificates.push(cert);
+                    }
+                    Err(e) => errors.push(format!("{header}: {e}")),
+                }
+            }
+        }
+        Err(e) => errors.push(format!("{header}: {e}")),
+    }
+    fingerprints
+}
+
+/// Envoy's XFCC: comma-separated elements of `Key=Value` pairs separated by
+/// `;`, with `Cert` and `Chain` holding URL-encoded PEM.
+fn envoy_source(value: &str) -> Value {
+    let mut certificates = Vec::new();
+    let mut errors = Vec::new();
+    let mut elements = Vec::new();
+    for element in split_unquoted(value, ',') {
+        let mut fields = Map::new();
+        let mut cert_hashes = Vec::new();
+        let mut hash = None;
+        for pair in split_unquoted(element.trim(), ';') {
+            let Some((key, val)) = pair.split_once('=') else { continue };
+            let val = unquote(val.trim());
+            match key.trim().to_ascii_lowercase().as_str() {
+                "cert" => cert_hashes = decode_into(&val, "Cert", &mut certificates, &mut errors),
+                "chain" => {
+                    decode_into(&val, "Chain", &mut certificates, &mut errors);
+                }
+                "hash" => {
+                    hash = Some(val.to_ascii_lowercase());
+                    fields.insert(key.trim().to_string(), json!(val));
+                }
+                _ => {
+                    fields.insert(key.trim().to_string(), json!(val));
+                }
+            }
+        }
+        if let (Some(hash), Some(actual)) = (&hash, cert_hashes.first()) {
+            if hash != actual {
+                errors.push(format!("Hash {hash} does not match the SHA-256 of Cert ({actual})"));
+            }
+        }
+        elements.push(Value::Object(fields));
+    }
+    json!({
+        "source": "envoy",
+        "headers": [XFCC],
+        "elements": elements,
+        "certificates": certificates,
+        "fields": {},
+        "redacted_headers": [],
+        "errors": errors,
+    })
+}
+
+/// Client certificates forwarded by the proxy, per proxy convention: the
+/// decoded certificates (subject, issuer, SANs, validity, SHA-256) and any
+/// details the proxy extracted itself.  Redacted headers are left alone.
+pub fn client_cert_json(headers: &HeaderMap, redact_prefixes: &[String]) -> Value {
+    let redacted = |name: &str| redact_prefixes.iter().any(|p| name.starts_with(p.as_str()));
+    let mut sources = Vec::new();
+
+    if let Some(value) = header_text(headers, XFCC) {
+        sources.push(if redacted(XFCC) {
+            json!({
+                "source": "envoy",
+                "headers": [XFCC],
+                "elements": [],
+                "certificates": [],
+                "fields": {},
+                "redacted_headers": [XFCC],
+                "errors": [],
+            })
+        } else {
+            envoy_source(&value)
+        });
+    }
+
+    for source in SOURCES {
+        let names: Vec<&str> = headers
+            .keys()
+            .map(|k| k.as_str())
+            .filter(|k| source.cert_headers.contains(k) || source.info_prefix.is_some_and(|p| k.starts_with(p)))
+            .collect();
+        if names.is_empty() {
+            continue;
+        }
+        let mut certificates = Vec::new();
+        let mut errors = Vec::new();
+        let mut fields = Map::new();
+        let mut hidden = Vec::new();
+        for name in &names {
+            let value = header_text(headers, name).unwrap_or_default();
+            if redacted(name) {
+                hidden.push(*name);
+            } else if source.cert_headers.contains(name) {
+                decode_into(&value, name, &mut certificates, &mut errors);
+            } else {
+                let text = String::from_utf8_lossy(&percent_decode_bytes(&value)).into_owned();
+                fields.insert(name.to_string(), json!(text));
+            }
+        }
+        sources.push(json!({
+            "source": source.id,
+            "headers": names,
+            "certificates": certificates,
+            "fields": fields,
+            "redacted_headers": hidden,
+            "errors": errors,
+        }));
+    }
+
+    Value::Array(sources)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::headers;
+
+    /// Self-signed P-256 certificate for `client.example`, valid 2025–2035.
+    const PEM: &str = "-----BEGIN CERTIFICATE-----\n\
+MIICDjCCAbSgAwIBAgICEjQwCgYIKoZIzj0EAwIwPzELMAkGA1UEBhMCTkwxFzAV\n\
+BgNVBAoMDlNlbnNpbGx1bSBUZXN0MRcwFQYDVQQDDA5jbGllbnQuZXhhbXBsZTAe\n\
+Fw0yNTAxMDEwMDAwMDBaFw0zNTAxMDEwMDAwMDBaMD8xCzAJBgNVBAYTAk5MMRcw\n\
+FQYDVQQKDA5TZW5zaWxsdW0gVGVzdDEXMBUGA1UEAwwOY2xpZW50LmV4YW1wbGUw\n\
+WTATBgcqhkjOPQIBBggqhkjOPQMBBwNCAAQnMWHIrUGYdxzH88g9zSEopBIXhHSI\n\
+SUnFdh2aL5zpRiQ4YcjpJAphliVhvh6j+/c7e09W0y0dQwWJg7AGl175o4GfMIGc\n\
+MB0GA1UdDgQWBBS26mvhCe8aW5G6TyQYbJSb1Ur7XzAfBgNVHSMEGDAWgBS26mvh\n\
+Ce8aW5G6TyQYbJSb1Ur7XzAPBgNVHRMBAf8EBTADAQH/MEkGA1UdEQRCMECCDmNs\n\
+aWVudC5leGFtcGxlhwQKAAABgQ9vcHNAZXhhbXBsZS5jb22GF3NwaWZmZTovL2V4\n\
+YW1wbGUvbnMvd2ViMAoGCCqGSM49BAMCA0gAMEUCIQDynLkUktlJCPBqyJBlI62b\n\
+JLjY0OP/jpYLZteVueanVAIgIoqhF5BfJ3VvNkpJCbM408Vr6faOhvduN0CuRJhz\n\
+14M=\n\
+-----END CERTIFICATE-----";
+    const SHA256: &str = "883433a918b95b6adf6db948c72b1c86731d0a49de072b76362248146f6ac9f2";
+
+    /// What nginx's `$ssl_client_escaped_cert` sends.
+    fn url_encoded(pem: &str) -> String {
+        pem.replace('%', "%25")
+            .replace('\n', "%0A")
+            .replace(' ', "%20")
+            .replace('+', "%2B")
+            .replace('/', "%2F")
+            .replace('=', "%3D")
+    }
+
+    #[test]
+    fn decodes_certificate() {
+        let der = certificates_in(PEM).unwrap();
+        assert_eq!(der.len(), 1);
+        let cert = decode_certificate(&der[0]).unwrap();
+        assert_eq!(cert["subject"], "CN=client.example,O=Sensillum Test,C=NL");
+        assert_eq!(cert["issuer"], cert["subject"]);
+        assert_eq!(cert["self_signed"], true);
+        assert_eq!(cert["serial"], "1234");
+        assert_eq!(cert["not_before"], "2025-01-01T00:00:00Z");
+        assert_eq!(cert["not_after"], "2035-01-01T00:00:00Z");
+        assert_eq!(
+            cert["sans"],
+            json!(["DNS:client.example", "IP:10.0.0.1", "email:ops@example.com", "URI:spiffe://example/ns/web"])
+        );
+        assert_eq!(cert["sha256"], SHA256);
+    }
+
+    #[test]
+    fn certificate_encodings() {
+        let der = certificates_in(PEM).unwrap();
+        assert_eq!(certificates_in(&url_encoded(PEM)).unwrap(), der);
+        // Traefik: base64 without the armour, certificates separated by commas.
+        let bare: String = PEM.lines().filter(|l| !l.starts_with("-----")).collect();
+        assert_eq!(certificates_in(&format!("{bare},{bare}")).unwrap(), [der[0].clone(), der[0].clone()]);
+    }
+
+    #[test]
+    fn truncated_certificate() {
+        let der = certificates_in(PEM).unwrap().remove(0);
+        assert_eq!(decode_certificate(&der[..100]).unwrap_err(), "truncated DER contents");
+        assert_eq!(decode_certificate(&[0x02, 0x01, 0x00]).unwrap_err(), "expected certificate, found tag 0x02");
+        assert_eq!(Der::new(&[0x30, 0x85, 0, 0, 0, 0, 0]).read().unwrap_err(), "unsupported DER length");
+    }
+
+    #[test]
+    fn oids_and_times() {
+        assert_eq!(oid_string(&[0x55, 0x04, 0x03]), "2.5.4.3");
+        assert_eq!(oid_string(&[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x09, 0x01]), "1.2.840.113549.1.9.1");
+        assert_eq!(parse_time(UTC_TIME, b"491231235959Z").unwrap(), ("2049-12-31T23:59:59Z".to_string(), 2524607999));
+        assert_eq!(parse_time(UTC_TIME, b"500101000000Z").unwrap().0, "1950-01-01T00:00:00Z");
+        assert_eq!(parse_time(GENERALIZED_TIME, b"20000229120000Z").unwrap().1, 951825600);
+        assert!(parse_time(UTC_TIME, b"500101000000+0100").is_err());
+    }
+
+    #[test]
+    fn envoy_xfcc() {
+        let xfcc = format!(
+            "By=spiffe://example/ns/proxy;Hash={SHA256};Cert=\"{}\";Subject=\"CN=client.example,O=Sensillum Test\";URI=spiffe://example/ns/web",
+            url_encoded(PEM)
+        );
+        let report = client_cert_json(&headers(&[("x-forwarded-client-cert", &xfcc)]), &[]);
+        let envoy = &report[0];
+        assert_eq!(envoy["source"], "envoy");
+        assert_eq!(envoy["errors"], json!([]));
+        assert_eq!(envoy["certificates"][0]["header"], "Cert");
+        assert_eq!(envoy["elements"][0]["Subject"], "CN=client.example,O=Sensillum Test");
+        assert_eq!(envoy["elements"][0]["By"], "spiffe://example/ns/proxy");
+
+        let wrong = xfcc.replace(SHA256, &"0".repeat(64));
+        let report = client_cert_json(&headers(&[("x-forwarded-client-cert", &wrong)]), &[]);
+        assert!(report[0]["errors"][0].as_str().unwrap().contains("does not match"));
+    }
+
+    #[test]
+    fn proxy_headers_and_redaction() {
+        let escaped = url_encoded(PEM);
+        let map = headers(&[("ssl-client-cert", &escaped), ("x-ssl-client-verify", "SUCCESS")]);
+        let report = client_cert_json(&map, &[]);
+        assert_eq!(report[0]["source"], "nginx");
+        assert_eq!(report[0]["certificates"][0]["sha256"], SHA256);
+        assert_eq!(report[0]["fields"]["x-ssl-client-verify"], "SUCCESS");
+
+        let report = client_cert_json(&map, &["ssl-client".to_string()]);
+        assert_eq!(report[0]["certificates"], json!([]));
+        assert_eq!(report[0]["redacted_headers"], json!(["ssl-client-cert"]));
+    }
+}
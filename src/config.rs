@@ -1,14 +1,52 @@
+use crate::forward_auth::{parse_auth_header, AuthRule};
+use crate::forwarded::Cidr;
+use crate::identity::Identity;
+
 /// Headers to redact by default. Must be lower case.
 const DEFAULT_REDACT_PREFIXES: &[&str] = &["x-origin-secret"];
 
+/// Headers besides `Authorization` and cookies searched for JWTs by default:
+/// those set by oauth2-proxy and the AWS ALB OIDC integration.  Must be lower case.
+const DEFAULT_JWT_HEADERS: &[&str] = &[
+    "x-forwarded-access-token",
+    "x-auth-request-access-token",
+    "x-amzn-oidc-data",
+    "x-amzn-oidc-accesstoken",
+];
+
+/// Headers an allowed forward-auth answer carries by default, named as
+/// oauth2-proxy names them.
+const DEFAULT_AUTH_HEADERS: &[&str] = &[
+    "X-Auth-Request-User: sensillum",
+    "X-Auth-Request-Email: sensillum@example.com",
+];
+
 #[derive(Clone)]
 pub struct ServerConfig {
     pub port: u16,
     pub node_name: Option<String>,
     pub hostname: String,
-    pub url_prefix: Option<String>,
+    /// Pod, namespace, node, pod IP and container ID, where detectable.
+    pub identity: Identity,
+    /// Static URL prefixes, longest first so that nested prefixes match correctly.
+    pub url_prefixes: Vec<String>,
+    /// Take the prefix from `X-Forwarded-Prefix` when the proxy sends one.
+    pub prefix_from_header: bool,
     pub redact_prefixes: Vec<String>,
+    /// Headers other than `Authorization` and `Cookie` that may carry a JWT.
+    pub jwt_headers: Vec<String>,
     pub privacy_mode: bool,
+    pub trusted_proxies: Vec<Cidr>,
+    /// Enable `/raw`, which writes malformed responses straight to the socket.
+    pub raw_responses: bool,
+    /// Run the request-smuggling probe against this proxy URL instead of serving.
+    pub desync_probe: Option<String>,
+    /// Export a span per request and WebSocket/SSE session to this OTLP/HTTP collector.
+    pub otlp_endpoint: Option<String>,
+    /// Forward-auth rules for `/auth`, tried in order; no match allows.
+    pub auth_rules: Vec<AuthRule>,
+    /// Headers an allowed `/auth` answer carries, for the proxy to pass upstream.
+    pub auth_headers: Vec<(String, String)>,
 }
 
 pub fn parse_config() -> ServerConfig {
@@ -24,16 +62,42 @@ pub fn parse_config() -> ServerConfig {
         .unwrap_or(3030);
 
     let mut node_name: Option<String> = std::env::var("SENSILLUM_NODE").ok();
+    let mut node_template: Option<String> = std::env::var("SENSILLUM_NODE_TEMPLATE").ok();
 
-    let mut url_prefix: Option<String> = std::env::var("SENSILLUM_PREFIX")
-        .ok()
-        .map(|v| parse_prefix(&v));
+    let mut url_prefixes: Vec<String> = std::env::var("SENSILLUM_PREFIX")
+        .map(|v| {
+            v.split([',', ' '])
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .map(parse_prefix)
+                .collect()
+        })
+        .unwrap_or_default();
+    let mut prefixes_from_cli: Vec<String> = Vec::new();
+
+    let mut prefix_from_header: bool =
+        std::env::var("SENSILLUM_PREFIX_FROM_HEADER").is_ok_and(|v| !v.is_empty());
 
     let mut privacy_mode: bool = std::env::var("SENSILLUM_PRIVACY").is_ok_and(|v| !v.is_empty());
 
+    let mut raw_responses: bool =
+        std::env::var("SENSILLUM_RAW_RESPONSES").is_ok_and(|v| !v.is_empty());
+
+    let mut desync_probe: Option<String> = std::env::var("SENSILLUM_DESYNC_PROBE").ok();
+
+    let mut otlp_endpoint: Option<String> = std::env::var("SENSILLUM_OTLP_ENDPOINT")
+        .ok()
+        .filter(|v| !v.is_empty());
+    // The standard OpenTelemetry variable is honoured too, unless Sensillum's
+    // own setting is given.  It is often set for other software (and to https
+    // collectors), so an unusable value only disables export.
+    let otel_endpoint: Option<String> = std::env::var("OTEL_EXPORTER_OTLP_ENDPOINT")
+        .ok()
+        .filter(|v| !v.is_empty());
+
     let mut redact_prefixes: Vec<String> = std::env::var("SENSILLUM_REDACT")
         .map(|v| {
-            v.split(|c| c == ',' || c == ' ')
+            v.split([',', ' '])
                 .map(|s| s.trim().to_lowercase())
                 .filter(|s| !s.is_empty())
                 .collect()
@@ -46,6 +110,50 @@ pub fn parse_config() -> ServerConfig {
         });
     let mut redact_from_cli: Vec<String> = Vec::new();
 
+    let mut jwt_headers: Vec<String> = std::env::var("SENSILLUM_JWT_HEADERS")
+        .map(|v| {
+            v.split([',', ' '])
+                .map(|s| s.trim().to_lowercase())
+                .filter(|s| !s.is_empty())
+                .collect()
+        })
+        .unwrap_or_else(|_| DEFAULT_JWT_HEADERS.iter().map(|s| s.to_string()).collect());
+    let mut jwt_from_cli: Vec<String> = Vec::new();
+
+    // Rules and headers contain spaces and commas, so the lists are ';'-separated.
+    let mut auth_rules: Vec<AuthRule> = std::env::var("SENSILLUM_AUTH_RULES")
+        .map(|v| {
+            v.split(';')
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .map(parse_auth_rule)
+                .collect()
+        })
+        .unwrap_or_default();
+    let mut auth_rules_from_cli: Vec<AuthRule> = Vec::new();
+
+    let mut auth_headers: Vec<(String, String)> = std::env::var("SENSILLUM_AUTH_HEADERS")
+        .map(|v| {
+            v.split(';')
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .map(parse_header)
+                .collect()
+        })
+        .unwrap_or_else(|_| DEFAULT_AUTH_HEADERS.iter().map(|h| parse_header(h)).collect());
+    let mut auth_headers_from_cli: Vec<(String, String)> = Vec::new();
+
+    let mut trusted_proxies: Vec<Cidr> = std::env::var("SENSILLUM_TRUSTED_PROXIES")
+        .map(|v| {
+            v.split([',', ' '])
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .map(parse_cidr)
+                .collect()
+        })
+        .unwrap_or_default();
+    let mut trusted_from_cli: Vec<Cidr> = Vec::new();
+
     // Now parse CLI args, which override environment variables.
     let mut args = std::env::args().skip(1);
     while let Some(arg) = args.next() {
@@ -69,17 +177,47 @@ pub fn parse_config() -> ServerConfig {
                     std::process::exit(1);
                 }
             }
+            "-N" | "--node-template" => {
+                if let Some(template) = args.next() {
+                    node_template = Some(template);
+                } else {
+                    eprintln!("Error: --node-template requires a value");
+                    std::process::exit(1);
+                }
+            }
             "-x" | "--prefix" => {
                 if let Some(prefix) = args.next() {
-                    url_prefix = Some(parse_prefix(&prefix));
+                    prefixes_from_cli.push(parse_prefix(&prefix));
                 } else {
                     eprintln!("Error: --prefix requires a value");
                     std::process::exit(1);
                 }
             }
+            "-X" | "--prefix-from-header" => {
+                prefix_from_header = true;
+            }
             "-P" | "--privacy" => {
                 privacy_mode = true;
             }
+            "-R" | "--raw-responses" => {
+                raw_responses = true;
+            }
+            "-D" | "--desync-probe" => {
+                if let Some(url) = args.next() {
+                    desync_probe = Some(url);
+                } else {
+                    eprintln!("Error: --desync-probe requires a value");
+                    std::process::exit(1);
+                }
+            }
+            "-O" | "--otlp-endpoint" => {
+                if let Some(url) = args.next() {
+                    otlp_endpoint = Some(url);
+                } else {
+                    eprintln!("Error: --otlp-endpoint requires a value");
+                    std::process::exit(1);
+                }
+            }
             "-r" | "--redact" => {
                 if let Some(prefix) = args.next() {
                     redact_from_cli.push(prefix.to_lowercase());
@@ -88,21 +226,72 @@ pub fn parse_config() -> ServerConfig {
                     std::process::exit(1);
                 }
             }
+            "-j" | "--jwt-header" => {
+                if let Some(name) = args.next() {
+                    jwt_from_cli.push(name.to_lowercase());
+                } else {
+                    eprintln!("Error: --jwt-header requires a value");
+                    std::process::exit(1);
+                }
+            }
+            "-a" | "--auth-rule" => {
+                if let Some(rule) = args.next() {
+                    auth_rules_from_cli.push(parse_auth_rule(&rule));
+                } else {
+                    eprintln!("Error: --auth-rule requires a value");
+                    std::process::exit(1);
+                }
+            }
+            "-H" | "--auth-header" => {
+                if let Some(header) = args.next() {
+                    auth_headers_from_cli.push(parse_header(&header));
+                } else {
+                    eprintln!("Error: --auth-header requires a value");
+                    std::process::exit(1);
+                }
+            }
+            "-t" | "--trusted-proxy" => {
+                if let Some(cidr) = args.next() {
+                    trusted_from_cli.push(parse_cidr(&cidr));
+                } else {
+                    eprintln!("Error: --trusted-proxy requires a value");
+                    std::process::exit(1);
+                }
+            }
             "-h" | "--help" => {
                 println!("Usage: sensillum [OPTIONS]");
                 println!("\nOptions:");
                 println!("  -p, --port <PORT>      Port to listen on [default: 3030]");
                 println!("  -n, --node <NAME>      Node name for identification");
-                println!("  -x, --prefix <PATH>    URL prefix for reverse proxy [e.g., /api]");
+                println!("  -N, --node-template <TEMPLATE>  Build the node name from detected identity [e.g., {{namespace}}/{{pod}}]");
+                println!("  -x, --prefix <PATH>    URL prefix for reverse proxy, repeatable [e.g., /api]");
+                println!("  -X, --prefix-from-header  Detect the URL prefix from X-Forwarded-Prefix (needs -t)");
                 println!("  -r, --redact <PREFIX>  Header prefix to redact (repeatable) [default: x-origin-secret]");
+                println!("  -j, --jwt-header <NAME>  Header besides Authorization and cookies to decode JWTs from (repeatable)");
+                println!("  -t, --trusted-proxy <CIDR>  Proxy address/network whose forwarding headers are trusted (repeatable)");
+                println!("  -a, --auth-rule <RULE>  Forward-auth rule for /auth, repeatable, first match wins [e.g., 'deny:401 *']");
+                println!("  -H, --auth-header <HEADER>  Header for allowed /auth answers to carry (repeatable) [e.g., 'X-User: alice']");
                 println!("  -P, --privacy          Suppress server-identifying fields from client responses");
+                println!("  -R, --raw-responses    Enable /raw, which sends deliberately malformed responses");
+                println!("  -D, --desync-probe <URL>  Probe the proxy at URL for request smuggling, then exit");
+                println!("  -O, --otlp-endpoint <URL>  Export request spans to this OTLP/HTTP collector [e.g., http://localhost:4318]");
                 println!("  -h, --help             Print help");
                 println!("\nEnvironment variables (overridden by CLI flags):");
                 println!("  SENSILLUM_PORT         Same as --port");
                 println!("  SENSILLUM_NODE         Same as --node");
-                println!("  SENSILLUM_PREFIX       Same as --prefix");
+                println!("  SENSILLUM_NODE_TEMPLATE  Same as --node-template");
+                println!("  SENSILLUM_PODINFO_DIR  Downward API volume to read pod details from [default: /etc/podinfo]");
+                println!("  SENSILLUM_PREFIX       Comma/space-separated list of prefixes, same as --prefix");
+                println!("  SENSILLUM_PREFIX_FROM_HEADER  Set to enable --prefix-from-header");
                 println!("  SENSILLUM_REDACT       Comma/space-separated list of prefixes, same as --redact");
+                println!("  SENSILLUM_JWT_HEADERS  Comma/space-separated list of header names, same as --jwt-header");
+                println!("  SENSILLUM_TRUSTED_PROXIES  Comma/space-separated list of CIDRs, same as --trusted-proxy");
+                println!("  SENSILLUM_AUTH_RULES   Semicolon-separated list of rules, same as --auth-rule");
+                println!("  SENSILLUM_AUTH_HEADERS  Semicolon-separated list of headers, same as --auth-header");
                 println!("  SENSILLUM_PRIVACY      Set to enable privacy mode");
+                println!("  SENSILLUM_RAW_RESPONSES  Set to enable --raw-responses");
+                println!("  SENSILLUM_DESYNC_PROBE  Same as --desync-probe");
+                println!("  SENSILLUM_OTLP_ENDPOINT  Same as --otlp-endpoint (falls back to OTEL_EXPORTER_OTLP_ENDPOINT)");
                 std::process::exit(0);
             }
             _ => {
@@ -118,23 +307,88 @@ pub fn parse_config() -> ServerConfig {
         .and_then(|h| h.into_string().ok())
         .unwrap_or_else(|| "unknown".to_string());
 
+    // An explicit node name wins over the template.
+    let identity = Identity::detect(&hostname);
+    if node_name.is_none() {
+        if let Some(template) = node_template {
+            node_name = Some(identity.expand(&template, &hostname).unwrap_or_else(|e| {
+                eprintln!("Error: {e}");
+                std::process::exit(1);
+            }));
+        }
+    }
+
     // CLI --redact flags replace the env-var/default list entirely.
     if !redact_from_cli.is_empty() {
         redact_prefixes = redact_from_cli;
     }
 
+    // Likewise for --jwt-header, --auth-rule, --auth-header, --prefix and --trusted-proxy.
+    if !jwt_from_cli.is_empty() {
+        jwt_headers = jwt_from_cli;
+    }
+    if !auth_rules_from_cli.is_empty() {
+        auth_rules = auth_rules_from_cli;
+    }
+    if !auth_headers_from_cli.is_empty() {
+        auth_headers = auth_headers_from_cli;
+    }
+    if !prefixes_from_cli.is_empty() {
+        url_prefixes = prefixes_from_cli;
+    }
+    if !trusted_from_cli.is_empty() {
+        trusted_proxies = trusted_from_cli;
+    }
+
+    if prefix_from_header && trusted_proxies.is_empty() {
+        eprintln!("Error: --prefix-from-header requires --trusted-proxy, or any client could choose the prefix");
+        std::process::exit(1);
+    }
+
+    let valid_otlp = |url: &str| url.starts_with("http://") && url.parse::<hyper::Uri>().is_ok();
+    match (&otlp_endpoint, otel_endpoint) {
+        (Some(url), _) if !valid_otlp(url) => {
+            eprintln!("Error: OTLP endpoint '{url}' must be an http:// URL; https is not supported");
+            std::process::exit(1);
+        }
+        (Some(_), _) | (None, None) => {}
+        (None, Some(url)) if valid_otlp(&url) => otlp_endpoint = Some(url),
+        (None, Some(url)) => eprintln!(
+            "Warning: OTEL_EXPORTER_OTLP_ENDPOINT '{url}' is not an http:// URL; OTLP export is disabled"
+        ),
+    }
+
+    url_prefixes.sort_by_key(|p| std::cmp::Reverse(p.len()));
+    url_prefixes.dedup();
+
     ServerConfig {
         port,
         node_name,
         hostname,
-        url_prefix,
+        identity,
+        url_prefixes,
+        prefix_from_header,
         redact_prefixes,
+        jwt_headers,
         privacy_mode,
+        trusted_proxies,
+        raw_responses,
+        desync_probe,
+        otlp_endpoint,
+        auth_rules,
+        auth_headers,
+    }
+}
+
+impl ServerConfig {
+    /// Whether `ip` falls within one of the configured trusted proxy networks.
+    pub fn is_trusted_proxy(&self, ip: std::net::IpAddr) -> bool {
+        self.trusted_proxies.iter().any(|c| c.contains(ip))
     }
 }
 
 /// Validate and normalise a URL prefix string: strip trailing slashes,
-/// require a leading `/` (unless the result is empty, which maps to `None`).
+/// require a leading `/` (unless the result is empty, i.e. the root).
 fn parse_prefix(raw: &str) -> String {
     let prefix = raw.trim_end_matches('/');
     if !prefix.is_empty() && !prefix.starts_with('/') {
@@ -143,3 +397,27 @@ fn parse_prefix(raw: &str) -> String {
     }
     prefix.to_string()
 }
+
+/// Parse a trusted-proxy CIDR, exiting with an error message if it is invalid.
+fn parse_cidr(raw: &str) -> Cidr {
+    Cidr::parse(raw).unwrap_or_else(|e| {
+        eprintln!("Error: Invalid trusted proxy '{raw}': {e}");
+        std::process::exit(1);
+    })
+}
+
+/// Parse a forward-auth rule, exiting with an error message if it is invalid.
+fn parse_auth_rule(raw: &str) -> AuthRule {
+    AuthRule::parse(raw).unwrap_or_else(|e| {
+        eprintln!("Error: Invalid auth rule '{raw}': {e}");
+        std::process::exit(1);
+    })
+}
+
+/// Parse a forward-auth response header, exiting with an error message if it is invalid.
+fn parse_header(raw: &str) -> (String, String) {
+    parse_auth_header(raw).unwrap_or_else(|e| {
+        eprintln!("Error: Invalid auth header: {e}");
+        std::process::exit(1);
+    })
+}
@@ -0,0 +1,183 @@
+use hyper::{Body, Request};
+use std::future::poll_fn;
+use std::io;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::TcpStream;
+
+/// The TCP stream hyper serves a connection on, shared with the handlers
+/// through [`RawConn`] so they can write bytes hyper cannot produce itself
+/// (1xx informational responses, deliberately malformed responses).
+///
+/// On HTTP/1 the request handler runs inside the connection task and hyper
+/// does not write while a request is being handled, so the two never write
+/// at the same time.
+pub struct ConnIo {
+    stream: Arc<Mutex<TcpStream>>,
+    bytes_read: Arc<AtomicU64>,
+    reset: Arc<AtomicBool>,
+}
+
+/// Handle to the raw connection, inserted into the extensions of HTTP/1.x
+/// requests.
+#[derive(Clone)]
+pub struct RawConn {
+    stream: Arc<Mutex<TcpStream>>,
+    bytes_read: Arc<AtomicU64>,
+    reset: Arc<AtomicBool>,
+}
+
+/// Which backend connection a request arrived on, and its position on it
+/// (1 for the first request).  Inserted into every request's extensions.
+#[derive(Clone, Copy)]
+pub struct ConnMeta {
+    pub id: u64,
+    pub request_index: u64,
+}
+
+impl ConnMeta {
+    pub fn of(req: &Request<Body>) -> ConnMeta {
+        req.extensions()
+            .get::<ConnMeta>()
+            .copied()
+            .unwrap_or(ConnMeta { id: 0, request_index: 0 })
+    }
+}
+
+/// Wrap `stream` for hyper, returning the handle handlers use alongside it.
+pub fn wrap(stream: TcpStream) -> (ConnIo, RawConn) {
+    let stream = Arc::new(Mutex::new(stream));
+    let bytes_read = Arc::new(AtomicU64::new(0));
+    let reset = Arc::new(AtomicBool::new(false));
+    let raw = RawConn {
+        stream: stream.clone(),
+        bytes_read: bytes_read.clone(),
+        reset: reset.clone(),
+    };
+    (ConnIo { stream, bytes_read, reset }, raw)
+}
+
+impl ConnIo {
+    /// Set once [`RawConn::reset`] was called: hyper must not write again.
+    fn check_reset(&self) -> io::Result<()> {
+        if self.reset.load(Ordering::Relaxed) {
+            return Err(io::ErrorKind::ConnectionReset.into());
+        }
+        Ok(())
+    }
+}
+
+impl AsyncRead for ConnIo {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let before = buf.filled().len();
+        let result = Pin::new(&mut *self.stream.lock().unwrap()).poll_read(cx, buf);
+        if let Poll::Ready(Ok(())) = result {
+            self.bytes_read
+                .fetch_add((buf.filled().len() - before) as u64, Ordering::Relaxed);
+        }
+        result
+    }
+}
+
+impl AsyncWrite for ConnIo {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.check_reset()?;
+        Pin::new(&mut *self.stream.lock().unwrap()).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.check_reset()?;
+        Pin::new(&mut *self.stream.lock().unwrap()).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut *self.stream.lock().unwrap()).poll_shutdown(cx)
+    }
+}
+
+impl RawConn {
+    /// The raw connection of an HTTP/1.x request, if it has one.
+    pub fn of(req: &Request<Body>) -> Option<RawConn> {
+        req.extensions().get::<RawConn>().cloned()
+    }
+
+    /// Total bytes hyper has read from the socket so far.  If this has not
+    /// moved since the request head was parsed, anything hyper hands over
+    /// next was already buffered together with the head.
+    pub fn bytes_read(&self) -> u64 {
+        self.bytes_read.load(Ordering::Relaxed)
+    }
+
+    /// Write `buf` straight to the socket, bypassing hyper.
+    pub async fn write_all(&self, mut buf: &[u8]) -> io::Result<()> {
+        while !buf.is_empty() {
+            let n = poll_fn(|cx| Pin::new(&mut *self.stream.lock().unwrap()).poll_write(cx, buf)).await?;
+            if n == 0 {
+                return Err(io::ErrorKind::WriteZero.into());
+            }
+            buf = &buf[n..];
+        }
+        poll_fn(|cx| Pin::new(&mut *self.stream.lock().unwrap()).poll_flush(cx)).await
+    }
+
+    /// Close the write side of the socket.  Anything hyper tries to write
+    /// afterwards fails, which ends the connection.
+    pub async fn shutdown(&self) -> io::Result<()> {
+        poll_fn(|cx| Pin::new(&mut *self.stream.lock().unwrap()).poll_shutdown(cx)).await
+    }
+
+    /// Abort the connection with a TCP RST instead of a FIN.  Linger is set
+    /// to zero and hyper's next write fails, so it drops the socket, and
+    /// closing a socket with zero linger resets the connection.
+    // Deprecated because a non-zero linger blocks the thread on drop; a zero
+    // linger never does.
+    #[allow(deprecated)]
+    pub fn reset(&self) -> io::Result<()> {
+        self.stream.lock().unwrap().set_linger(Some(Duration::ZERO))?;
+        self.reset.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Wait up to `timeout` for the peer to close the connection.  Returns
+    /// whether it did; bytes sent in the meantime are left unread.
+    pub async fn wait_closed(&self, timeout: Duration) -> bool {
+        let deadline = tokio::time::Instant::now() + timeout;
+        let mut byte = [0u8; 1];
+        let peek = poll_fn(|cx| {
+            let mut buf = ReadBuf::new(&mut byte);
+            self.stream.lock().unwrap().poll_peek(cx, &mut buf)
+        });
+        match tokio::time::timeout_at(deadline, peek).await {
+            Ok(Ok(0)) | Ok(Err(_)) => true,
+            Ok(Ok(_)) => {
+                // Pipelined data, not a close; peeking again would spin.
+                tokio::time::sleep_until(deadline).await;
+                false
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// Wait up to `timeout` for unread bytes (or EOF) on the socket without
+    /// consuming them.  Returns whether anything arrived in time.
+    pub async fn wait_readable(&self, timeout: Duration) -> bool {
+        let mut byte = [0u8; 1];
+        let peek = poll_fn(|cx| {
+            let mut buf = ReadBuf::new(&mut byte);
+            self.stream.lock().unwrap().poll_peek(cx, &mut buf)
+        });
+        matches!(tokio::time::timeout(timeout, peek).await, Ok(Ok(_)))
+    }
+}
@@ -0,0 +1,513 @@
+use futures_util::Stream;
+use hyper::body::{Bytes, HttpBody};
+use hyper::header::{CONTENT_LENGTH, EXPECT};
+use hyper::{Body, Request, Version};
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{ready, Context, Poll};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::conn::ConnMeta;
+use crate::handlers::common::parse_query;
+
+/// How many probes, smuggled requests, parse errors, short bodies, extra
+/// requests and connections are remembered.
+const MAX_EVENTS: usize = 200;
+
+/// Path suffix that smuggled requests target.  It is matched before prefix
+/// routing, because a smuggled request never passes through the proxy that
+/// would normally strip or add the prefix.
+pub const SMUGGLED_PATH: &str = "/desync/smuggled";
+
+struct Probe {
+    canary: String,
+    variant: String,
+    conn: ConnMeta,
+    at_ms: u64,
+    body_bytes: usize,
+    smuggled_in_body: bool,
+}
+
+struct Smuggled {
+    canary: Option<String>,
+    variant: Option<String>,
+    conn: ConnMeta,
+    at_ms: u64,
+    method: String,
+    /// The `X-Ignore` header the smuggled prefix ends with.  When the prefix
+    /// was glued to someone else's request, this holds their request line.
+    absorbed: Option<String>,
+}
+
+struct ParseError {
+    conn_id: u64,
+    requests: u64,
+    at_ms: u64,
+    error: String,
+}
+
+/// A request body that ended before its `Content-Length`.
+struct ShortBody {
+    conn: ConnMeta,
+    at_ms: u64,
+    method: String,
+    path: String,
+    declared: u64,
+    received: u64,
+    error: String,
+}
+
+/// A request that followed a probe on its backend connection without
+/// carrying the probe's canary, so the probe client did not send it there.
+struct Extra {
+    canary: String,
+    conn: ConnMeta,
+    at_ms: u64,
+    method: String,
+    /// Only the path: the request may be another client's, so its query is
+    /// not repeated.
+    path: String,
+}
+
+/// How many requests a backend connection has carried so far.
+struct ConnStats {
+    id: u64,
+    first_ms: u64,
+    last_ms: u64,
+    requests: u64,
+}
+
+#[derive(Default)]
+struct Events {
+    probes: VecDeque<Probe>,
+    smuggled: VecDeque<Smuggled>,
+    parse_errors: VecDeque<ParseError>,
+    short_bodies: VecDeque<ShortBody>,
+    extra: VecDeque<Extra>,
+    connections: VecDeque<ConnStats>,
+}
+
+/// Records what a front-end proxy's framing mistakes look like from the
+/// backend: requests to the smuggled path, probe bodies that carried the
+/// smuggled request instead, connections that ended in a parse error
+/// because leftover bytes did not form a valid request, and bodies that
+/// ended before their `Content-Length`.  It also counts the requests on
+/// every connection, and flags a request that follows a probe on its
+/// connection without the probe's canary: a proxy that turned one request
+/// into two.
+#[derive(Default)]
+pub struct DesyncMonitor {
+    events: Mutex<Events>,
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+fn push_capped<T>(queue: &mut VecDeque<T>, item: T) {
+    if queue.len() == MAX_EVENTS {
+        queue.pop_front();
+    }
+    queue.push_back(item);
+}
+
+/// What a watched body reports to the monitor when it ends early.
+struct BodyWatch {
+    monitor: Arc<DesyncMonitor>,
+    conn: ConnMeta,
+    method: String,
+    path: String,
+    declared: u64,
+}
+
+impl BodyWatch {
+    fn short_body(self, received: u64, error: &hyper::Error) {
+        println!(
+            "Request body on connection {} ended after {} of {} bytes: {}",
+            self.conn.id, received, self.declared, error
+        );
+        let mut events = self.monitor.events.lock().unwrap();
+        push_capped(
+            &mut events.short_bodies,
+            ShortBody {
+                conn: self.conn,
+                at_ms: now_ms(),
+                method: self.method,
+                path: self.path,
+                declared: self.declared,
+                received,
+                error: error.to_string(),
+            },
+        );
+    }
+}
+
+/// Most of an unread body drained in the background.  Past this the body is
+/// dropped, and hyper closes the connection as it would have anyway.
+const MAX_DRAIN: u64 = 64 * 1024;
+
+/// A request body that counts what arrives.  If the endpoint drops it
+/// unread, up to `MAX_DRAIN` more bytes are read in the background, so short
+/// ignored bodies are checked too.  Without this hyper tries a single read
+/// and then closes the connection.
+struct WatchedBody {
+    body: Body,
+    watch: Option<BodyWatch>,
+    received: u64,
+    finished: bool,
+    drain: bool,
+}
+
+impl Stream for WatchedBody {
+    type Item = Result<Bytes, hyper::Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        match ready!(Pin::new(&mut this.body).poll_data(cx)) {
+            Some(Ok(chunk)) => {
+                this.received += chunk.len() as u64;
+                Poll::Ready(Some(Ok(chunk)))
+            }
+            Some(Err(e)) => {
+                this.finished = true;
+                if let Some(watch) = this.watch.take() {
+                    watch.short_body(this.received, &e);
+                }
+                Poll::Ready(Some(Err(e)))
+            }
+            None => {
+                this.finished = true;
+                Poll::Ready(None)
+            }
+        }
+    }
+}
+
+impl Drop for WatchedBody {
+    fn drop(&mut self) {
+        if self.finished || !self.drain {
+            return;
+        }
+        let (Some(watch), Ok(runtime)) = (self.watch.take(), tokio::runtime::Handle::try_current()) else {
+            return;
+        };
+        let mut body = std::mem::take(&mut self.body);
+        let mut received = self.received;
+        let limit = received.saturating_add(MAX_DRAIN);
+        runtime.spawn(async move {
+            while let Some(chunk) = body.data().await {
+                match chunk {
+                    Ok(chunk) => received += chunk.len() as u64,
+                    Err(e) => return watch.short_body(received, &e),
+                }
+                if received >= limit {
+                    return;
+                }
+            }
+        });
+    }
+}
+
+impl DesyncMonitor {
+    /// Count `req` on its connection, flag it if it follows a probe it does
+    /// not belong to and, for an HTTP/1 request with a `Content-Length`,
+    /// watch its body for ending early.
+    pub fn watch(self: &Arc<Self>, req: &mut Request<Body>) {
+        let conn = ConnMeta::of(req);
+        let now = now_ms();
+        {
+            let mut events = self.events.lock().unwrap();
+            let after_probe = events
+                .probes
+                .iter()
+                .rev()
+                .find(|p| p.conn.id == conn.id && p.conn.request_index + 1 == conn.request_index)
+                .map(|p| p.canary.clone());
+            if let Some(canary) = after_probe {
+                let query = req.uri().query().unwrap_or("");
+                if !parse_query(query).pairs.iter().any(|p| p.key == "canary" && p.value == canary) {
+                    println!(
+                        "Unexpected request {} {} followed probe {} on connection {}",
+                        req.method(),
+                        req.uri().path(),
+                        canary,
+                        conn.id
+                    );
+                    push_capped(
+                        &mut events.extra,
+                        Extra {
+                            canary,
+                            conn,
+                            at_ms: now,
+                            method: req.method().to_string(),
+                            path: req.uri().path().to_string(),
+                        },
+                    );
+                }
+            }
+            match events.connections.iter_mut().rev().find(|c| c.id == conn.id) {
+                Some(stats) => {
+                    stats.requests = stats.requests.max(conn.request_index);
+                    stats.last_ms = now;
+                }
+                None => push_capped(
+                    &mut events.connections,
+                    ConnStats {
+                        id: conn.id,
+                        first_ms: now,
+                        last_ms: now,
+                        requests: conn.request_index,
+                    },
+                ),
+            }
+        }
+
+        let declared = req
+            .headers()
+            .get(CONTENT_LENGTH)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.trim().parse::<u64>().ok())
+            .filter(|&n| n > 0);
+        let Some(declared) = declared.filter(|_| req.version() < Version::HTTP_2) else {
+            return;
+        };
+        let watch = BodyWatch {
+            monitor: self.clone(),
+            conn,
+            method: req.method().to_string(),
+            path: req.uri().path().to_string(),
+            declared,
+        };
+        // Draining would make hyper send the 100 Continue the endpoint held back.
+        let drain = !req.headers().contains_key(EXPECT);
+        let body = std::mem::take(req.body_mut());
+        *req.body_mut() = Body::wrap_stream(WatchedBody {
+            body,
+            watch: Some(watch),
+            received: 0,
+            finished: false,
+            drain,
+        });
+    }
+
+    pub fn probe(&self, canary: &str, variant: &str, conn: ConnMeta, body: &[u8]) {
+        let smuggled_in_body = body
+            .windows(SMUGGLED_PATH.len())
+            .any(|w| w == SMUGGLED_PATH.as_bytes());
+        let mut events = self.events.lock().unwrap();
+        push_capped(
+            &mut events.probes,
+            Probe {
+                canary: canary.to_string(),
+                variant: variant.to_string(),
+                conn,
+                at_ms: now_ms(),
+                body_bytes: body.len(),
+                smuggled_in_body,
+            },
+        );
+    }
+
+    pub fn smuggled(&self, req: &Request<Body>, canary: Option<&str>, variant: Option<&str>) {
+        println!(
+            "Smuggled request reached the backend (canary {}) — a proxy in front is desynchronised",
+            canary.unwrap_or("none")
+        );
+        let absorbed = req
+            .headers()
+            .get("x-ignore")
+            .map(|v| String::from_utf8_lossy(v.as_bytes()).into_owned());
+        let mut events = self.events.lock().unwrap();
+        push_capped(
+            &mut events.smuggled,
+            Smuggled {
+                canary: canary.map(str::to_string),
+                variant: variant.map(str::to_string),
+                conn: ConnMeta::of(req),
+                at_ms: now_ms(),
+                method: req.method().to_string(),
+                absorbed,
+            },
+        );
+    }
+
+    pub fn parse_error(&self, conn_id: u64, requests: u64, error: &hyper::Error) {
+        let mut events = self.events.lock().unwrap();
+        push_capped(
+            &mut events.parse_errors,
+            ParseError {
+                conn_id,
+                requests,
+                at_ms: now_ms(),
+                error: error.to_string(),
+            },
+        );
+    }
+
+    /// Everything recorded, or only what relates to `canary`: its probes, the
+    /// smuggled requests carrying it, the extra requests that followed it, and
+    /// the parse errors, short bodies and request counts of the probes'
+    /// connections.
+    pub fn report(&self, canary: Option<&str>) -> Value {
+        let events = self.events.lock().unwrap();
+        let probes: Vec<&Probe> = events
+            .probes
+            .iter()
+            .filter(|p| canary.is_none_or(|c| p.canary == c))
+            .collect();
+        let probe_conns: Vec<u64> = probes.iter().map(|p| p.conn.id).collect();
+
+        let probes_json: Vec<Value> = probes
+            .iter()
+            .map(|p| {
+                json!({
+                    "canary": p.canary,
+                    "variant": p.variant,
+                    "connection": p.conn.id,
+                    "request_index": p.conn.request_index,
+                    "at_ms": p.at_ms,
+                    "body_bytes": p.body_bytes,
+                    "smuggled_in_body": p.smuggled_in_body,
+                })
+            })
+            .collect();
+
+        let smuggled_json: Vec<Value> = events
+            .smuggled
+            .iter()
+            .filter(|s| canary.is_none_or(|c| s.canary.as_deref() == Some(c)))
+            .map(|s| {
+                // Directly after the probe on the same backend connection
+                // means the probe's leftover bytes became this request.
+                let after_probe = probes.iter().any(|p| {
+                    p.conn.id == s.conn.id && p.conn.request_index + 1 == s.conn.request_index
+                });
+                json!({
+                    "canary": s.canary,
+                    "variant": s.variant,
+                    "connection": s.conn.id,
+                    "request_index": s.conn.request_index,
+                    "at_ms": s.at_ms,
+                    "method": s.method,
+                    "absorbed": s.absorbed,
+                    "same_connection_as_probe": after_probe,
+                })
+            })
+            .collect();
+
+        let parse_errors_json: Vec<Value> = events
+            .parse_errors
+            .iter()
+            .filter(|e| canary.is_none() || probe_conns.contains(&e.conn_id))
+            .map(|e| {
+                json!({
+                    "connection": e.conn_id,
+                    "requests": e.requests,
+                    "at_ms": e.at_ms,
+                    "error": e.error,
+                })
+            })
+            .collect();
+
+        let short_bodies_json: Vec<Value> = events
+            .short_bodies
+            .iter()
+            .filter(|b| canary.is_none() || probe_conns.contains(&b.conn.id))
+            .map(|b| {
+                json!({
+                    "connection": b.conn.id,
+                    "request_index": b.conn.request_index,
+                    "at_ms": b.at_ms,
+                    "method": b.method,
+                    "path": b.path,
+                    "content_length": b.declared,
+                    "received": b.received,
+                    "error": b.error,
+                })
+            })
+            .collect();
+
+        let extra_json: Vec<Value> = events
+            .extra
+            .iter()
+            .filter(|x| canary.is_none_or(|c| x.canary == c))
+            .map(|x| {
+                json!({
+                    "canary": x.canary,
+                    "connection": x.conn.id,
+                    "request_index": x.conn.request_index,
+                    "at_ms": x.at_ms,
+                    "method": x.method,
+                    "path": x.path,
+                })
+            })
+            .collect();
+
+        let connections_json: Vec<Value> = events
+            .connections
+            .iter()
+            .rev()
+            .filter(|c| canary.is_none() || probe_conns.contains(&c.id))
+            .map(|c| {
+                json!({
+                    "connection": c.id,
+                    "requests": c.requests,
+                    "first_ms": c.first_ms,
+                    "last_ms": c.last_ms,
+                })
+            })
+            .collect();
+
+        let desync = !smuggled_json.is_empty();
+        let anomalies =
+            !parse_errors_json.is_empty() || !short_bodies_json.is_empty() || !extra_json.is_empty();
+        json!({
+            "canary": canary,
+            "desync_detected": desync,
+            "anomalies_detected": anomalies,
+            "probes": probes_json,
+            "smuggled": smuggled_json,
+            "parse_errors": parse_errors_json,
+            "short_bodies": short_bodies_json,
+            "extra_requests": extra_json,
+            "connections": connections_json,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(uri: &str, id: u64, request_index: u64) -> Request<Body> {
+        let mut req = Request::builder().uri(uri).body(Body::empty()).unwrap();
+        req.extensions_mut().insert(ConnMeta { id, request_index });
+        req
+    }
+
+    #[test]
+    fn request_after_a_probe_without_its_canary_is_flagged() {
+        let monitor = Arc::new(DesyncMonitor::default());
+        monitor.probe("abc", "cl-te", ConnMeta { id: 1, request_index: 1 }, b"");
+        monitor.watch(&mut request("/echo?token=secret", 1, 2));
+        monitor.watch(&mut request("/desync?canary=abc", 1, 3));
+        monitor.watch(&mut request("/echo", 2, 2));
+
+        let report = monitor.report(Some("abc"));
+        let extra = report["extra_requests"].as_array().unwrap();
+        assert_eq!(extra.len(), 1);
+        assert_eq!(extra[0]["path"], "/echo");
+        assert_eq!(extra[0]["request_index"], 2);
+        assert_eq!(report["anomalies_detected"], true);
+
+        let monitor = Arc::new(DesyncMonitor::default());
+        monitor.probe("abc", "cl-te", ConnMeta { id: 1, request_index: 1 }, b"");
+        monitor.watch(&mut request("/desync?canary=abc", 1, 2));
+        assert_eq!(monitor.report(Some("abc"))["extra_requests"], json!([]));
+    }
+}
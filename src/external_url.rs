@@ -0,0 +1,292 @@
+use hyper::{HeaderMap, Uri};
+use serde_json::{json, Value};
+
+use crate::forwarded::{self, Cidr};
+use std::net::SocketAddr;
+
+/// Forwarding headers that only count when a trusted proxy sent them.
+const FORWARDING_HEADERS: [(&str, &str); 6] = [
+    ("forwarded", "Forwarded"),
+    ("x-forwarded-for", "X-Forwarded-For"),
+    ("x-forwarded-proto", "X-Forwarded-Proto"),
+    ("x-forwarded-host", "X-Forwarded-Host"),
+    ("x-forwarded-port", "X-Forwarded-Port"),
+    ("x-forwarded-prefix", "X-Forwarded-Prefix"),
+];
+
+/// Validate an `X-Forwarded-Prefix` value: first list entry, trailing slashes
+/// removed, must be an absolute path (not `//`, which browsers treat as a host)
+/// made only of path characters.
+pub fn parse_forwarded_prefix(raw: &str) -> Result<String, String> {
+    let first = raw.split(',').next().unwrap_or("").trim();
+    let prefix = first.trim_end_matches('/');
+    if prefix.is_empty() {
+        return Ok(String::new());
+    }
+    if !prefix.starts_with('/') || prefix.starts_with("//") {
+        return Err(format!("'{first}' is not an absolute path"));
+    }
+    if !prefix
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || "-._~!$&'()*+,;=:@%/".contains(c))
+    {
+        return Err(format!("'{first}' contains characters that are not valid in a path"));
+    }
+    Ok(prefix.to_string())
+}
+
+/// The prefix from `X-Forwarded-Prefix`, if present and valid.
+pub fn forwarded_prefix(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get("x-forwarded-prefix")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| parse_forwarded_prefix(v).ok())
+}
+
+/// Split `host[:port]` (including bracketed IPv6) into its parts.
+fn split_host_port(authority: &str) -> (&str, Option<&str>) {
+    if authority.starts_with('[') {
+        match authority.find(']') {
+            Some(end) => (
+                &authority[..=end],
+                authority[end + 1..].strip_prefix(':'),
+            ),
+            None => (authority, None),
+        }
+    } else {
+        match authority.rsplit_once(':') {
+            Some((h, p)) => (h, Some(p)),
+            None => (authority, None),
+        }
+    }
+}
+
+fn default_port(scheme: &str) -> Option<u16> {
+    match scheme {
+        "http" | "ws" => Some(80),
+        "https" | "wss" => Some(443),
+        _ => None,
+    }
+}
+
+/// Reconstruct the external URL the application would build for this request,
+/// and flag any places where the headers it comes from disagree.
+///
+/// Scheme and host come from the client's hop as found by
+/// [`forwarded::resolve`], so only values added by trusted proxies count;
+/// `X-Forwarded-Port` and `X-Forwarded-Prefix` are honoured only when the
+/// socket peer is a trusted proxy.  `url_prefix` is the prefix Sensillum
+/// matched for routing; it is used when the proxy sends no `X-Forwarded-Prefix`.
+pub fn reconstruct(
+    headers: &HeaderMap,
+    uri: &Uri,
+    url_prefix: &str,
+    client_addr: SocketAddr,
+    trusted: &[Cidr],
+) -> Value {
+    let mut inconsistencies: Vec<String> = Vec::new();
+    let resolved = forwarded::resolve(headers, client_addr, trusted);
+
+    let host_header = headers
+        .get("host")
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string)
+        .or_else(|| uri.authority().map(|a| a.to_string()));
+
+    let present: Vec<&str> = FORWARDING_HEADERS
+        .iter()
+        .filter(|(name, _)| headers.contains_key(*name))
+        .map(|(_, display)| *display)
+        .collect();
+    if !resolved.peer_trusted && !present.is_empty() {
+        inconsistencies.push(format!(
+            "{} came from untrusted peer {} and {} ignored",
+            present.join(", "),
+            client_addr.ip(),
+            if present.len() == 1 { "was" } else { "were" }
+        ));
+    } else if resolved.source == "socket"
+        && (headers.contains_key("x-forwarded-proto") || headers.contains_key("x-forwarded-host"))
+    {
+        inconsistencies.push(
+            "X-Forwarded-Proto/-Host were sent without X-Forwarded-For or Forwarded, so they belong to no hop and were ignored"
+                .to_string(),
+        );
+    }
+    let (x_port, x_prefix) = if resolved.peer_trusted {
+        (
+            forwarded::header_list(headers, "x-forwarded-port"),
+            forwarded::header_list(headers, "x-forwarded-prefix"),
+        )
+    } else {
+        (Vec::new(), Vec::new())
+    };
+    for (name, values) in [("X-Forwarded-Port", &x_port), ("X-Forwarded-Prefix", &x_prefix)] {
+        if values.len() > 1 {
+            inconsistencies.push(format!(
+                "{name} has {} values ({}); the first was used",
+                values.len(),
+                values.join(", ")
+            ));
+        }
+    }
+
+    // --- scheme ---
+    let via_forwarded = resolved.source == "forwarded";
+    let (scheme, scheme_source) = match resolved.proto {
+        Some(p) => (
+            p.to_ascii_lowercase(),
+            if via_forwarded { "forwarded" } else { "x-forwarded-proto" },
+        ),
+        None => ("http".to_string(), "default"),
+    };
+    if default_port(&scheme).is_none() {
+        inconsistencies.push(format!("scheme '{scheme}' is not http or https"));
+    }
+
+    // --- host ---
+    let (authority, host_source) = match resolved.host {
+        Some(h) => (Some(h), if via_forwarded { "forwarded" } else { "x-forwarded-host" }),
+        None => (host_header.clone(), "host"),
+    };
+    if host_source != "host" {
+        if let (Some(a), Some(h)) = (&authority, &host_header) {
+            if !a.eq_ignore_ascii_case(h) {
+                inconsistencies.push(format!(
+                    "Host is '{h}' but the proxy reports '{a}'; an application that only reads Host builds URLs for the wrong host"
+                ));
+            }
+        }
+    }
+    let authority = authority.unwrap_or_default();
+    let (host, authority_port) = split_host_port(&authority);
+
+    // --- port ---
+    let forwarded_port = match x_port.first() {
+        Some(p) => match p.parse::<u16>() {
+            Ok(n) if n > 0 => Some(n),
+            _ => {
+                inconsistencies.push(format!("X-Forwarded-Port '{p}' is not a valid port"));
+                None
+            }
+        },
+        None => None,
+    };
+    let authority_port = match authority_port {
+        Some(p) => match p.parse::<u16>() {
+            Ok(n) => Some(n),
+            Err(_) => {
+                inconsistencies.push(format!("port in '{authority}' is not a valid port"));
+                None
+            }
+        },
+        None => None,
+    };
+    if let (Some(xp), Some(ap)) = (forwarded_port, authority_port) {
+        if xp != ap {
+            inconsistencies.push(format!(
+                "X-Forwarded-Port: {xp} disagrees with the port in '{authority}'"
+            ));
+        }
+    }
+    let (port, port_source) = match (forwarded_port, authority_port) {
+        (Some(p), _) => (Some(p), "x-forwarded-port"),
+        (None, Some(p)) => (Some(p), host_source),
+        (None, None) => (default_port(&scheme), "default"),
+    };
+    if let (Some(p), Some(d)) = (port, default_port(&scheme)) {
+        if (p == 80 || p == 443) && p != d {
+            inconsistencies.push(format!(
+                "port {p} is unusual for scheme {scheme}; the proxy may be reporting its own listener"
+            ));
+        }
+    }
+
+    // --- prefix ---
+    let (prefix, prefix_source) = match x_prefix.first() {
+        Some(raw) => match parse_forwarded_prefix(raw) {
+            Ok(p) => (p, "x-forwarded-prefix"),
+            Err(e) => {
+                inconsistencies.push(format!("X-Forwarded-Prefix {e}"));
+                (url_prefix.to_string(), "config")
+            }
+        },
+        None => (url_prefix.to_string(), "config"),
+    };
+    if prefix_source == "x-forwarded-prefix" && !url_prefix.is_empty() && prefix != url_prefix {
+        inconsistencies.push(format!(
+            "X-Forwarded-Prefix '{prefix}' differs from the prefix Sensillum matched ('{url_prefix}')"
+        ));
+    }
+
+    // The proxy either forwarded the full path or stripped the prefix from it.
+    let path = uri.path();
+    let path = match path.strip_prefix(url_prefix) {
+        Some(rest) if !url_prefix.is_empty() => rest,
+        _ => path,
+    };
+    let path = if path.is_empty() { "/" } else { path };
+
+    let mut url = format!("{scheme}://{host}");
+    if let Some(p) = port {
+        if Some(p) != default_port(&scheme) {
+            url.push_str(&format!(":{p}"));
+        }
+    }
+    url.push_str(&prefix);
+    url.push_str(path);
+    if let Some(q) = uri.query() {
+        url.push('?');
+        url.push_str(q);
+    }
+
+    json!({
+        "url": url,
+        "scheme": {"value": scheme, "source": scheme_source},
+        "host": {"value": host, "source": host_source},
+        "port": {"value": port, "source": port_source},
+        "prefix": {"value": prefix, "source": prefix_source},
+        "inconsistencies": inconsistencies,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::headers;
+
+    fn peer(s: &str) -> SocketAddr {
+        format!("{s}:40000").parse().unwrap()
+    }
+
+    #[test]
+    fn uses_the_trusted_hop_not_the_leftmost_values() {
+        let trusted = [Cidr::parse("10.0.0.0/8").unwrap()];
+        let h = headers(&[
+            ("host", "backend:8080"),
+            ("x-forwarded-for", "6.6.6.6, 203.0.113.7, 10.0.0.2"),
+            ("x-forwarded-proto", "http, https, https"),
+            ("x-forwarded-host", "evil.example, app.example, app.example"),
+        ]);
+        let uri: Uri = "/echo?x=1".parse().unwrap();
+        let out = reconstruct(&h, &uri, "", peer("10.0.0.1"), &trusted);
+        assert_eq!(out["url"], "https://app.example/echo?x=1");
+        assert_eq!(out["scheme"]["source"], "x-forwarded-proto");
+        assert_eq!(out["host"]["source"], "x-forwarded-host");
+    }
+
+    #[test]
+    fn untrusted_peer_headers_are_ignored() {
+        let h = headers(&[
+            ("host", "backend:8080"),
+            ("forwarded", "for=1.2.3.4;proto=https;host=evil.example"),
+            ("x-forwarded-prefix", "/evil"),
+        ]);
+        let uri: Uri = "/api/echo".parse().unwrap();
+        let out = reconstruct(&h, &uri, "/api", peer("198.51.100.9"), &[]);
+        assert_eq!(out["url"], "http://backend:8080/api/echo");
+        assert_eq!(out["prefix"]["source"], "config");
+        let notes = out["inconsistencies"].as_array().unwrap();
+        assert!(notes[0].as_str().unwrap().starts_with("Forwarded, X-Forwarded-Prefix came from untrusted peer"));
+    }
+}
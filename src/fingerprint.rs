@@ -0,0 +1,424 @@
+use hyper::HeaderMap;
+use serde_json::{json, Value};
+
+/// Where in the request path a piece of infrastructure usually sits.
+/// Declared outermost-first, so sorting by layer gives the likely chain order.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Layer {
+    Cdn,
+    Waf,
+    LoadBalancer,
+    Proxy,
+    Platform,
+    ServiceMesh,
+}
+
+impl Layer {
+    fn as_str(self) -> &'static str {
+        match self {
+            Layer::Cdn => "cdn",
+            Layer::Waf => "waf",
+            Layer::LoadBalancer => "load-balancer",
+            Layer::Proxy => "proxy",
+            Layer::Platform => "platform",
+            Layer::ServiceMesh => "service-mesh",
+        }
+    }
+}
+
+enum Match {
+    /// The named header is present.
+    Header(&'static str),
+    /// Any header whose name starts with this prefix is present.
+    Prefix(&'static str),
+    /// The named header contains this substring (case-insensitive).
+    Contains(&'static str, &'static str),
+}
+
+struct Rule {
+    m: Match,
+    /// Probability-like strength of this piece of evidence on its own.
+    weight: f64,
+}
+
+const fn header(name: &'static str, weight: f64) -> Rule {
+    Rule { m: Match::Header(name), weight }
+}
+
+const fn prefix(name: &'static str, weight: f64) -> Rule {
+    Rule { m: Match::Prefix(name), weight }
+}
+
+const fn contains(name: &'static str, needle: &'static str, weight: f64) -> Rule {
+    Rule { m: Match::Contains(name, needle), weight }
+}
+
+struct Signature {
+    name: &'static str,
+    layer: Layer,
+    rules: &'static [Rule],
+}
+
+/// Header fingerprints of common CDNs, load balancers, proxies and meshes.
+/// Header names must be lower case.
+static SIGNATURES: &[Signature] = &[
+    Signature {
+        name: "Cloudflare",
+        layer: Layer::Cdn,
+        rules: &[
+            header("cf-ray", 0.9),
+            header("cf-connecting-ip", 0.6),
+            header("cf-ipcountry", 0.4),
+            header("cf-visitor", 0.4),
+            header("cf-worker", 0.5),
+            contains("cdn-loop", "cloudflare", 0.8),
+        ],
+    },
+    Signature {
+        name: "Amazon CloudFront",
+        layer: Layer::Cdn,
+        rules: &[
+            header("x-amz-cf-id", 0.9),
+            prefix("cloudfront-viewer-", 0.6),
+            prefix("cloudfront-is-", 0.6),
+            header("cloudfront-forwarded-proto", 0.6),
+            contains("via", "cloudfront", 0.8),
+        ],
+    },
+    Signature {
+        name: "Fastly",
+        layer: Layer::Cdn,
+        rules: &[
+            prefix("fastly-", 0.7),
+            header("fastly-ff", 0.8),
+            contains("cdn-loop", "fastly", 0.8),
+        ],
+    },
+    Signature {
+        name: "Akamai",
+        layer: Layer::Cdn,
+        rules: &[
+            header("akamai-origin-hop", 0.8),
+            prefix("x-akamai-", 0.8),
+            prefix("akamai-", 0.5),
+            header("true-client-ip", 0.3),
+            contains("cdn-loop", "akamai", 0.8),
+            contains("via", "akamai", 0.8),
+        ],
+    },
+    Signature {
+        name: "Azure Front Door",
+        layer: Layer::Cdn,
+        rules: &[
+            header("x-azure-ref", 0.9),
+            prefix("x-fd-", 0.7),
+            header("x-azure-clientip", 0.5),
+            header("x-azure-socketip", 0.5),
+        ],
+    },
+    Signature {
+        name: "Gcore",
+        layer: Layer::Cdn,
+        rules: &[contains("cdn-loop", "gcore", 0.8)],
+    },
+    Signature {
+        name: "Imperva",
+        layer: Layer::Waf,
+        rules: &[header("incap-client-ip", 0.9), prefix("x-iinfo", 0.5)],
+    },
+    Signature {
+        name: "Sucuri",
+        layer: Layer::Waf,
+        rules: &[prefix("x-sucuri-", 0.9)],
+    },
+    Signature {
+        name: "AWS Application Load Balancer",
+        layer: Layer::LoadBalancer,
+        rules: &[
+            header("x-amzn-trace-id", 0.8),
+            prefix("x-amzn-mtls-", 0.8),
+            header("x-amzn-oidc-data", 0.8),
+        ],
+    },
+    Signature {
+        name: "Google Cloud Load Balancer",
+        layer: Layer::LoadBalancer,
+        rules: &[
+            contains("via", "google", 0.8),
+            header("x-cloud-trace-context", 0.5),
+            prefix("x-goog-", 0.4),
+        ],
+    },
+    Signature {
+        name: "Varnish",
+        layer: Layer::Proxy,
+        rules: &[header("x-varnish", 0.8), contains("via", "varnish", 0.8)],
+    },
+    Signature {
+        name: "Squid",
+        layer: Layer::Proxy,
+        rules: &[contains("via", "squid", 0.8)],
+    },
+    Signature {
+        name: "Kong",
+        layer: Layer::Proxy,
+        rules: &[prefix("x-kong-", 0.8), contains("via", "kong", 0.8)],
+    },
+    Signature {
+        name: "Caddy",
+        layer: Layer::Proxy,
+        rules: &[contains("via", "caddy", 0.8)],
+    },
+    Signature {
+        name: "Apache httpd",
+        layer: Layer::Proxy,
+        rules: &[
+            contains("via", "apache", 0.7),
+            header("x-forwarded-server", 0.2),
+        ],
+    },
+    Signature {
+        name: "Traefik",
+        layer: Layer::Proxy,
+        rules: &[
+            header("x-forwarded-server", 0.2),
+            prefix("x-forwarded-tls-client-cert", 0.6),
+        ],
+    },
+    Signature {
+        name: "nginx",
+        layer: Layer::Proxy,
+        rules: &[
+            header("x-real-ip", 0.3),
+            header("x-forwarded-scheme", 0.2),
+            prefix("x-ssl-client-", 0.4),
+            header("ssl-client-cert", 0.5),
+        ],
+    },
+    Signature {
+        name: "Envoy",
+        layer: Layer::Proxy,
+        rules: &[
+            prefix("x-envoy-", 0.8),
+            header("x-forwarded-client-cert", 0.5),
+            prefix("x-b3-", 0.2),
+            header("x-request-id", 0.1),
+        ],
+    },
+    Signature {
+        name: "Heroku router",
+        layer: Layer::Platform,
+        rules: &[contains("via", "vegur", 0.9), header("x-request-start", 0.3)],
+    },
+    Signature {
+        name: "Azure App Service",
+        layer: Layer::Platform,
+        rules: &[
+            header("x-arr-log-id", 0.8),
+            header("x-arr-ssl", 0.8),
+            header("x-waws-unencoded-url", 0.8),
+        ],
+    },
+    Signature {
+        name: "Vercel",
+        layer: Layer::Platform,
+        rules: &[header("x-vercel-id", 0.9), prefix("x-vercel-", 0.5)],
+    },
+    Signature {
+        name: "Netlify",
+        layer: Layer::Platform,
+        rules: &[header("x-nf-request-id", 0.9)],
+    },
+    Signature {
+        name: "Istio",
+        layer: Layer::ServiceMesh,
+        rules: &[
+            header("x-envoy-peer-metadata", 0.9),
+            header("x-envoy-peer-metadata-id", 0.9),
+            contains("x-forwarded-client-cert", "spiffe://", 0.7),
+            prefix("x-b3-", 0.2),
+        ],
+    },
+    Signature {
+        name: "Linkerd",
+        layer: Layer::ServiceMesh,
+        rules: &[prefix("l5d-", 0.9)],
+    },
+];
+
+/// Anything weaker than this is noise (e.g. a lone `X-Real-IP`).
+const MIN_CONFIDENCE: f64 = 0.3;
+
+/// Longest header value quoted as evidence.
+const MAX_EVIDENCE_VALUE: usize = 80;
+
+/// Identify the CDNs, load balancers, proxies and service meshes in front of
+/// Sensillum from the request headers they add.
+///
+/// Each matching header is treated as independent evidence, weighted by the
+/// strongest rule it matches, so confidence is `1 - Π(1 - weight)`.
+/// `CDN-Loop` (RFC 8586) entries that no signature claims are reported as
+/// unidentified CDNs.  Results are ordered outermost-first.  Values of
+/// redacted headers are not quoted, so a redacted `CDN-Loop` reports no
+/// unidentified CDNs.
+pub fn fingerprint(headers: &HeaderMap, redact_prefixes: &[String]) -> Value {
+    let evidence_for = |name: &str| -> String {
+        if redact_prefixes.iter().any(|p| name.starts_with(p.as_str())) {
+            return format!("{name}: (redacted)");
+        }
+        match headers.get(name).and_then(|v| v.to_str().ok()) {
+            Some(v) if v.len() > MAX_EVIDENCE_VALUE => {
+                let mut end = MAX_EVIDENCE_VALUE;
+                while !v.is_char_boundary(end) {
+                    end -= 1;
+                }
+                format!("{name}: {}…", &v[..end])
+            }
+            Some(v) => format!("{name}: {v}"),
+            None => format!("{name} present"),
+        }
+    };
+
+    let mut matches: Vec<(Layer, f64, Value)> = Vec::new();
+
+    for sig in SIGNATURES {
+        // A header matched by several rules (`fastly-ff` by both `fastly-ff`
+        // and `fastly-*`) is still one piece of evidence: it counts once, at
+        // the weight of its strongest rule.
+        let mut hits: Vec<(String, f64)> = Vec::new();
+        for rule in sig.rules {
+            let names: Vec<&str> = match rule.m {
+                Match::Header(name) => headers.contains_key(name).then_some(name).into_iter().collect(),
+                Match::Prefix(p) => headers
+                    .keys()
+                    .map(|k| k.as_str())
+                    .filter(|k| k.starts_with(p))
+                    .collect(),
+                Match::Contains(name, needle) => headers
+                    .get_all(name)
+                    .iter()
+                    .filter_map(|v| v.to_str().ok())
+                    .any(|v| v.to_ascii_lowercase().contains(needle))
+                    .then_some(name)
+                    .into_iter()
+                    .collect(),
+            };
+            for name in names {
+                match hits.iter_mut().find(|(n, _)| n == name) {
+                    Some((_, weight)) => *weight = weight.max(rule.weight),
+                    None => hits.push((name.to_string(), rule.weight)),
+                }
+            }
+        }
+        let miss: f64 = hits.iter().map(|(_, w)| 1.0 - w).product();
+        let evidence: Vec<String> = hits.iter().map(|(name, _)| evidence_for(name)).collect();
+        let confidence = 1.0 - miss;
+        if confidence >= MIN_CONFIDENCE {
+            matches.push((
+                sig.layer,
+                confidence,
+                json!({
+                    "name": sig.name,
+                    "kind": sig.layer.as_str(),
+                    "confidence": (confidence * 100.0).round() / 100.0,
+                    "evidence": evidence,
+                }),
+            ));
+        }
+    }
+
+    // CDN-Loop entries are `cdn-id *( OWS ";" OWS parameter )`.  An unclaimed
+    // id would be quoted as the match's name, so skip them when redacted.
+    let cdn_loop_redacted = redact_prefixes.iter().any(|p| "cdn-loop".starts_with(p.as_str()));
+    let claimed: Vec<String> = SIGNATURES
+        .iter()
+        .flat_map(|s| s.rules.iter())
+        .filter_map(|r| match r.m {
+            Match::Contains("cdn-loop", needle) => Some(needle.to_string()),
+            _ => None,
+        })
+        .collect();
+    let cdn_loop_ids = headers
+        .get_all("cdn-loop")
+        .iter()
+        .filter(|_| !cdn_loop_redacted)
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .filter_map(|entry| entry.split(';').next())
+        .map(str::trim)
+        .filter(|id| !id.is_empty());
+    for id in cdn_loop_ids {
+        let lower = id.to_ascii_lowercase();
+        if !claimed.iter().any(|c| lower.contains(c.as_str())) {
+            matches.push((
+                Layer::Cdn,
+                0.7,
+                json!({
+                    "name": id,
+                    "kind": Layer::Cdn.as_str(),
+                    "confidence": 0.7,
+                    "evidence": [format!("cdn-loop: {id}")],
+                }),
+            ));
+        }
+    }
+
+    matches.sort_by(|a, b| a.0.cmp(&b.0).then(b.1.total_cmp(&a.1)));
+    Value::Array(matches.into_iter().map(|(_, _, v)| v).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::headers;
+
+    fn find<'a>(result: &'a Value, name: &str) -> Option<&'a Value> {
+        result.as_array().unwrap().iter().find(|m| m["name"] == name)
+    }
+
+    #[test]
+    fn header_matched_by_two_rules_counts_once() {
+        let result = fingerprint(&headers(&[("fastly-ff", "cache-ams1")]), &[]);
+        let fastly = find(&result, "Fastly").unwrap();
+        assert_eq!(fastly["confidence"], 0.8);
+        assert_eq!(fastly["evidence"].as_array().unwrap().len(), 1);
+
+        let result = fingerprint(&headers(&[("akamai-origin-hop", "2")]), &[]);
+        assert_eq!(find(&result, "Akamai").unwrap()["confidence"], 0.8);
+    }
+
+    #[test]
+    fn independent_headers_combine() {
+        let result = fingerprint(&headers(&[("cf-ray", "abc-AMS"), ("cf-connecting-ip", "1.2.3.4")]), &[]);
+        // 1 - (1 - 0.9) * (1 - 0.6)
+        assert_eq!(find(&result, "Cloudflare").unwrap()["confidence"], 0.96);
+    }
+
+    #[test]
+    fn weak_evidence_is_dropped() {
+        let result = fingerprint(&headers(&[("x-request-id", "1")]), &[]);
+        assert!(result.as_array().unwrap().is_empty());
+    }
+
+    #[test]
+    fn unclaimed_cdn_loop_and_ordering() {
+        let result = fingerprint(
+            &headers(&[("cdn-loop", "cloudflare, mycdn; v=1"), ("l5d-dst-canonical", "svc"), ("x-amzn-trace-id", "Root=1")]),
+            &[],
+        );
+        let names: Vec<&str> = result.as_array().unwrap().iter().map(|m| m["name"].as_str().unwrap()).collect();
+        assert_eq!(names, ["Cloudflare", "mycdn", "AWS Application Load Balancer", "Linkerd"]);
+    }
+
+    #[test]
+    fn redacted_values_are_not_quoted() {
+        let result = fingerprint(&headers(&[("x-amzn-oidc-data", "secret")]), &["x-amzn-oidc".to_string()]);
+        let alb = find(&result, "AWS Application Load Balancer").unwrap();
+        assert_eq!(alb["evidence"][0], "x-amzn-oidc-data: (redacted)");
+
+        let result = fingerprint(&headers(&[("cdn-loop", "cloudflare, mycdn")]), &["cdn-".to_string()]);
+        let names: Vec<&str> = result.as_array().unwrap().iter().map(|m| m["name"].as_str().unwrap()).collect();
+        assert_eq!(names, ["Cloudflare"]);
+        assert_eq!(find(&result, "Cloudflare").unwrap()["evidence"][0], "cdn-loop: (redacted)");
+    }
+}
@@ -0,0 +1,382 @@
+use hyper::{HeaderMap, Method, StatusCode};
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// How many auth sub-requests are remembered; the oldest is forgotten first.
+const MAX_ENTRIES: usize = 50;
+
+/// Headers a forwarding proxy uses to describe the original request, listed
+/// separately in the log.  Prefixes; must be lower case.
+const FORWARDED_PREFIXES: &[&str] = &[
+    "x-forwarded-",
+    "x-original-",
+    "x-real-ip",
+    "forwarded",
+    "x-envoy-",
+    "x-auth-request-redirect",
+    "x-sent-from",
+];
+
+/// What a matching rule answers.
+#[derive(Clone, Debug)]
+pub enum Action {
+    Allow,
+    Deny(StatusCode),
+}
+
+#[derive(Clone, Debug)]
+enum Condition {
+    Always,
+    /// The original path starts with this.
+    Path(String),
+    Method(Method),
+    /// The header is present, or has exactly this value.
+    Header(String, Option<String>),
+    Cookie(String),
+}
+
+/// One forward-auth rule, `<action> [<condition>]`:
+///
+/// - action: `allow`, `deny` (403) or `deny:<status>` (any 4xx or 5xx)
+/// - condition: `*` (the default), `path:<prefix>`, `method:<METHOD>`,
+///   `header:<name>`, `header:<name>=<value>` or `cookie:<name>`
+///
+/// Rules are tried in order; the first that matches decides, and a request
+/// no rule matches is allowed.
+#[derive(Clone, Debug)]
+pub struct AuthRule {
+    text: String,
+    action: Action,
+    condition: Condition,
+}
+
+impl AuthRule {
+    pub fn parse(s: &str) -> Result<AuthRule, String> {
+        let text = s.trim();
+        let (action, condition) = text.split_once(char::is_whitespace).unwrap_or((text, "*"));
+        let action = match action.split_once(':') {
+            None if action == "allow" => Action::Allow,
+            None if action == "deny" => Action::Deny(StatusCode::FORBIDDEN),
+            Some(("deny", status)) => {
+                let status = status
+                    .parse::<u16>()
+                    .ok()
+                    .and_then(|n| StatusCode::from_u16(n).ok())
+                    .filter(|s| s.is_client_error() || s.is_server_error())
+                    .ok_or_else(|| format!("'{status}' is not a 4xx or 5xx status"))?;
+                Action::Deny(status)
+            }
+            _ => return Err(format!("unknown action '{action}'; use allow, deny or deny:<status>")),
+        };
+
+        let condition = condition.trim();
+        let condition = match condition.split_once(':') {
+            _ if condition == "*" => Condition::Always,
+            Some(("path", prefix)) if prefix.starts_with('/') => Condition::Path(prefix.to_string()),
+            Some(("method", method)) => Condition::Method(
+                Method::from_bytes(method.to_ascii_uppercase().as_bytes())
+                    .map_err(|_| format!("'{method}' is not a valid method"))?,
+            ),
+            Some(("header", header)) if !header.is_empty() => match header.split_once('=') {
+                Some((name, value)) => Condition::Header(name.to_lowercase(), Some(value.to_string())),
+                None => Condition::Header(header.to_lowercase(), None),
+            },
+            Some(("cookie", name)) if !name.is_empty() => Condition::Cookie(name.to_string()),
+            _ => {
+                return Err(format!(
+                    "unknown condition '{condition}'; use *, path:/prefix, method:GET, header:name[=value] or cookie:name"
+                ))
+            }
+        };
+        Ok(AuthRule { text: text.to_string(), action, condition })
+    }
+
+    pub fn action(&self) -> &Action {
+        &self.action
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    fn matches(&self, original: &Original, headers: &HeaderMap) -> bool {
+        match &self.condition {
+            Condition::Always => true,
+            Condition::Path(prefix) => original.path().starts_with(prefix.as_str()),
+            Condition::Method(method) => original.method.eq_ignore_ascii_case(method.as_str()),
+            Condition::Header(name, value) => headers
+                .get_all(name.as_str())
+                .iter()
+                .any(|v| value.as_ref().is_none_or(|want| v.as_bytes() == want.as_bytes())),
+            Condition::Cookie(name) => headers
+                .get_all("cookie")
+                .iter()
+                .filter_map(|v| v.to_str().ok())
+                .flat_map(|v| v.split(';'))
+                .any(|pair| pair.trim().split_once('=').is_some_and(|(n, _)| n == name)),
+        }
+    }
+}
+
+/// Parse a `Name: Value` header for allowed responses to carry.
+pub fn parse_auth_header(s: &str) -> Result<(String, String), String> {
+    let (name, value) = s
+        .split_once(':')
+        .ok_or_else(|| format!("'{s}' is not of the form 'Name: Value'"))?;
+    let name = name.trim();
+    let value = value.trim();
+    if hyper::header::HeaderName::from_bytes(name.as_bytes()).is_err()
+        || hyper::header::HeaderValue::from_str(value).is_err()
+    {
+        return Err(format!("'{s}' is not a valid header"));
+    }
+    Ok((name.to_string(), value.to_string()))
+}
+
+/// The request the proxy is asking about, as far as the sub-request reveals it.
+pub struct Original {
+    pub method: String,
+    pub uri: Option<String>,
+    pub host: Option<String>,
+    pub proto: Option<String>,
+    /// Which convention described the original request.
+    pub source: &'static str,
+}
+
+impl Original {
+    /// Traefik ForwardAuth sends `X-Forwarded-Method`/`-Uri`/`-Host`/
+    /// `-Proto`; nginx `auth_request` setups conventionally add
+    /// `X-Original-URI` and `X-Original-Method`, ingress-nginx sends
+    /// `X-Original-URL`; Envoy ext_authz appends the original path to the
+    /// auth path (`suffix`) and keeps the method and `Host`.
+    pub fn from_request(method: &Method, headers: &HeaderMap, suffix: Option<&str>) -> Original {
+        let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok()).map(str::to_string);
+        let host = header("x-forwarded-host");
+        let proto = header("x-forwarded-proto");
+        let original_method = header("x-forwarded-method").or_else(|| header("x-original-method"));
+
+        let (uri, source) = if let Some(uri) = header("x-forwarded-uri") {
+            (Some(uri), "Traefik ForwardAuth (X-Forwarded-Uri)")
+        } else if let Some(uri) = header("x-original-uri") {
+            (Some(uri), "nginx auth_request (X-Original-URI)")
+        } else if let Some(url) = header("x-original-url") {
+            (Some(url), "ingress-nginx (X-Original-URL)")
+        } else if let Some(suffix) = suffix {
+            (Some(suffix.to_string()), "Envoy ext_authz (path suffix)")
+        } else {
+            (None, "unknown — no X-Forwarded-Uri, X-Original-URI or path suffix")
+        };
+        // Envoy keeps the original Host; the others name it separately.
+        let host = host.or_else(|| suffix.and_then(|_| header("host")));
+
+        Original {
+            method: original_method.unwrap_or_else(|| method.to_string()),
+            uri,
+            host,
+            proto,
+            source,
+        }
+    }
+
+    /// The original path without the query, for `path:` rules.  An absolute
+    /// URL (X-Original-URL) is reduced to its path.
+    fn path(&self) -> &str {
+        let uri = self.uri.as_deref().unwrap_or("");
+        let uri = match uri.split_once("://") {
+            Some((_, rest)) => rest.find('/').map_or("/", |i| &rest[i..]),
+            None => uri,
+        };
+        uri.split(['?', '#']).next().unwrap_or("")
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "method": self.method,
+            "uri": self.uri,
+            "host": self.host,
+            "proto": self.proto,
+            "source": self.source,
+        })
+    }
+}
+
+/// The first rule in `rules` that matches, with its action; `None` when the
+/// request falls through to the default (allow).
+pub fn decide<'a>(rules: &'a [AuthRule], original: &Original, headers: &HeaderMap) -> Option<&'a AuthRule> {
+    rules.iter().find(|r| r.matches(original, headers))
+}
+
+/// The headers in `headers_json` output that describe forwarding.
+pub fn forwarded_headers(headers: &Value) -> Value {
+    let forwarded = headers
+        .as_object()
+        .into_iter()
+        .flatten()
+        .filter(|(name, _)| FORWARDED_PREFIXES.iter().any(|p| name.starts_with(p)))
+        .map(|(name, value)| (name.clone(), value.clone()))
+        .collect();
+    Value::Object(forwarded)
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// The most recent auth sub-requests this node answered.  As with the retry
+/// log, each node keeps its own; the UI merges them through the load balancer.
+#[derive(Default)]
+pub struct AuthLog {
+    entries: Mutex<VecDeque<Value>>,
+}
+
+impl AuthLog {
+    /// Store `entry`, stamping it with the time.
+    pub fn record(&self, mut entry: Value) {
+        entry["at_ms"] = json!(now_ms());
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() == MAX_ENTRIES {
+            entries.pop_front();
+        }
+        entries.push_back(entry);
+    }
+
+    /// Newest first.
+    pub fn recent(&self) -> Vec<Value> {
+        self.entries.lock().unwrap().iter().rev().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::headers;
+
+    fn original(uri: &str, method: &str) -> Original {
+        Original {
+            method: method.to_string(),
+            uri: Some(uri.to_string()),
+            host: None,
+            proto: None,
+            source: "test",
+        }
+    }
+
+    fn matches(rule: &str, original: &Original, headers: &HeaderMap) -> bool {
+        AuthRule::parse(rule).unwrap().matches(original, headers)
+    }
+
+    #[test]
+    fn parses_actions() {
+        assert!(matches!(AuthRule::parse("allow").unwrap().action(), Action::Allow));
+        assert!(matches!(AuthRule::parse("deny").unwrap().action(), Action::Deny(s) if *s == StatusCode::FORBIDDEN));
+        assert!(matches!(AuthRule::parse("deny:401 *").unwrap().action(), Action::Deny(s) if s.as_u16() == 401));
+        assert_eq!(AuthRule::parse("  deny path:/admin ").unwrap().text(), "deny path:/admin");
+        assert_eq!(AuthRule::parse("deny:302").unwrap_err(), "'302' is not a 4xx or 5xx status");
+        assert_eq!(AuthRule::parse("deny:abc").unwrap_err(), "'abc' is not a 4xx or 5xx status");
+        assert!(AuthRule::parse("permit").unwrap_err().starts_with("unknown action 'permit'"));
+    }
+
+    #[test]
+    fn parses_conditions() {
+        for ok in ["allow *", "deny path:/admin", "deny method:delete", "deny header:x-user", "deny header:x-user=bob", "deny cookie:session"] {
+            assert!(AuthRule::parse(ok).is_ok(), "{ok}");
+        }
+        for bad in ["deny path:admin", "deny header:", "deny cookie:", "deny host:example.com", "deny method:GE(T"] {
+            assert!(AuthRule::parse(bad).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn matches_conditions() {
+        let get = original("/admin/users?page=2", "GET");
+        let none = HeaderMap::new();
+        assert!(matches("deny path:/admin", &get, &none));
+        assert!(!matches("deny path:/api", &get, &none));
+        assert!(matches("deny method:get", &get, &none));
+        assert!(!matches("deny method:POST", &get, &none));
+
+        let map = headers(&[("x-user", "bob"), ("cookie", "theme=dark; session=abc")]);
+        assert!(matches("deny header:X-User", &get, &map));
+        assert!(matches("deny header:x-user=bob", &get, &map));
+        assert!(!matches("deny header:x-user=alice", &get, &map));
+        assert!(matches("deny cookie:session", &get, &map));
+        assert!(!matches("deny cookie:sess", &get, &map));
+
+        // An absolute X-Original-URL is reduced to its path.
+        let absolute = original("https://app.example/admin?x=1", "GET");
+        assert_eq!(absolute.path(), "/admin");
+        assert!(matches("deny path:/admin", &absolute, &none));
+    }
+
+    #[test]
+    fn first_matching_rule_decides() {
+        let rules: Vec<AuthRule> = ["allow header:x-user=admin", "deny:401 path:/admin", "deny method:DELETE"]
+            .iter()
+            .map(|r| AuthRule::parse(r).unwrap())
+            .collect();
+        let admin = headers(&[("x-user", "admin")]);
+        let none = HeaderMap::new();
+        let decided = |uri, method, h| decide(&rules, &original(uri, method), h).map(AuthRule::text);
+        assert_eq!(decided("/admin", "GET", &admin), Some("allow header:x-user=admin"));
+        assert_eq!(decided("/admin", "GET", &none), Some("deny:401 path:/admin"));
+        assert_eq!(decided("/x", "DELETE", &none), Some("deny method:DELETE"));
+        assert_eq!(decided("/x", "GET", &none), None);
+    }
+
+    #[test]
+    fn original_request_per_proxy() {
+        let traefik = headers(&[
+            ("x-forwarded-method", "POST"),
+            ("x-forwarded-uri", "/api?x=1"),
+            ("x-forwarded-host", "app.example"),
+            ("x-forwarded-proto", "https"),
+        ]);
+        let o = Original::from_request(&Method::GET, &traefik, None);
+        assert_eq!(o.source, "Traefik ForwardAuth (X-Forwarded-Uri)");
+        assert_eq!((o.method.as_str(), o.uri.as_deref()), ("POST", Some("/api?x=1")));
+        assert_eq!((o.host.as_deref(), o.proto.as_deref()), (Some("app.example"), Some("https")));
+
+        let nginx = headers(&[("x-original-uri", "/a"), ("x-original-method", "PUT")]);
+        let o = Original::from_request(&Method::GET, &nginx, None);
+        assert_eq!(o.source, "nginx auth_request (X-Original-URI)");
+        assert_eq!(o.method, "PUT");
+
+        let ingress = headers(&[("x-original-url", "https://app.example/b")]);
+        assert_eq!(Original::from_request(&Method::GET, &ingress, None).path(), "/b");
+
+        let envoy = headers(&[("host", "app.example")]);
+        let o = Original::from_request(&Method::DELETE, &envoy, Some("/c?d=1"));
+        assert_eq!(o.source, "Envoy ext_authz (path suffix)");
+        assert_eq!((o.method.as_str(), o.uri.as_deref(), o.host.as_deref()), ("DELETE", Some("/c?d=1"), Some("app.example")));
+
+        let o = Original::from_request(&Method::GET, &envoy, None);
+        assert!(o.uri.is_none() && o.host.is_none());
+    }
+
+    #[test]
+    fn auth_headers() {
+        assert_eq!(
+            parse_auth_header("X-Auth-Request-User:  alice ").unwrap(),
+            ("X-Auth-Request-User".to_string(), "alice".to_string())
+        );
+        assert_eq!(parse_auth_header("no colon").unwrap_err(), "'no colon' is not of the form 'Name: Value'");
+        assert!(parse_auth_header("Bad Name: x").is_err());
+    }
+
+    #[test]
+    fn log_keeps_the_newest() {
+        let log = AuthLog::default();
+        for i in 0..MAX_ENTRIES + 5 {
+            log.record(json!({"n": i}));
+        }
+        let recent = log.recent();
+        assert_eq!(recent.len(), MAX_ENTRIES);
+        assert_eq!(recent[0]["n"], MAX_ENTRIES + 4);
+        assert!(recent[0]["at_ms"].is_u64());
+    }
+}
@@ -0,0 +1,622 @@
+use hyper::HeaderMap;
+use serde_json::{json, Value};
+use std::net::{IpAddr, SocketAddr};
+
+/// An IP network in CIDR notation, e.g. `10.0.0.0/8` or `fd00::/8`.
+/// A bare address is accepted as a single-host network.
+#[derive(Clone, Debug)]
+pub struct Cidr {
+    addr: IpAddr,
+    prefix_len: u8,
+}
+
+impl Cidr {
+    pub fn parse(s: &str) -> Result<Cidr, String> {
+        let (addr_str, len_str) = match s.split_once('/') {
+            Some((a, l)) => (a, Some(l)),
+            None => (s, None),
+        };
+        let addr: IpAddr = addr_str
+            .parse()
+            .map_err(|_| format!("'{addr_str}' is not a valid IP address"))?;
+        let max_len = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix_len = match len_str {
+            Some(l) => l
+                .parse::<u8>()
+                .ok()
+                .filter(|&n| n <= max_len)
+                .ok_or_else(|| format!("'{l}' is not a valid prefix length for {addr_str}"))?,
+            None => max_len,
+        };
+        // An IPv4-mapped network (`::ffff:10.0.0.0/104`) is stored as the
+        // IPv4 network it covers, so it matches however the peer is written.
+        if let IpAddr::V6(v6) = addr {
+            if let Some(v4) = v6.to_ipv4_mapped().filter(|_| prefix_len >= 96) {
+                return Ok(Cidr { addr: IpAddr::V4(v4), prefix_len: prefix_len - 96 });
+            }
+        }
+        Ok(Cidr { addr, prefix_len })
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.addr, ip.to_canonical()) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix_len as u32).unwrap_or(0);
+                u32::from(net) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(net), ip) => {
+                // A wider IPv6 network may still cover IPv4 peers in mapped form.
+                let ip = match ip {
+                    IpAddr::V4(v4) => v4.to_ipv6_mapped(),
+                    IpAddr::V6(v6) => v6,
+                };
+                let mask = u128::MAX.checked_shl(128 - self.prefix_len as u32).unwrap_or(0);
+                u128::from(net) & mask == u128::from(ip) & mask
+            }
+            (IpAddr::V4(_), IpAddr::V6(_)) => false,
+        }
+    }
+}
+
+impl std::fmt::Display for Cidr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix_len)
+    }
+}
+
+/// A node identifier from `for=` / `by=` (RFC 7239 §6) or an `X-Forwarded-For` entry.
+#[derive(Clone, Debug)]
+pub enum Node {
+    Ip(IpAddr),
+    Unknown,
+    Obfuscated(String),
+    Invalid(String),
+}
+
+impl std::fmt::Display for Node {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Node::Ip(ip) => write!(f, "{ip}"),
+            Node::Unknown => f.write_str("unknown"),
+            Node::Obfuscated(s) | Node::Invalid(s) => f.write_str(s),
+        }
+    }
+}
+
+/// One comma-separated element of a `Forwarded` header, with its parameter
+/// names lower-cased and quoted values unescaped.
+#[derive(Clone, Debug, Default)]
+pub struct ForwardedElement {
+    pub pairs: Vec<(String, String)>,
+}
+
+impl ForwardedElement {
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.pairs
+            .iter()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// Split `s` on `sep`, ignoring separators inside double-quoted strings.
+fn split_unquoted(s: &str, sep: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut in_quotes = false;
+    let mut escaped = false;
+    for (i, c) in s.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if in_quotes && c == '\\' {
+            escaped = true;
+        } else if c == '"' {
+            in_quotes = !in_quotes;
+        } else if c == sep && !in_quotes {
+            parts.push(&s[start..i]);
+            start = i + c.len_utf8();
+        }
+    }
+    parts.push(&s[start..]);
+    parts
+}
+
+/// RFC 9110 `tchar`.
+pub fn is_tchar(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c)
+}
+
+/// Parse a token or quoted-string value, returning the unescaped contents.
+fn parse_value(raw: &str) -> Result<String, String> {
+    if let Some(inner) = raw.strip_prefix('"') {
+        let inner = inner
+            .strip_suffix('"')
+            .ok_or_else(|| format!("unterminated quoted string {raw}"))?;
+        let mut out = String::with_capacity(inner.len());
+        let mut chars = inner.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => out.push(chars.next().ok_or("dangling escape in quoted string")?),
+                '"' => return Err(format!("unescaped quote in {raw}")),
+                _ => out.push(c),
+            }
+        }
+        Ok(out)
+    } else if !raw.is_empty() && raw.chars().all(is_tchar) {
+        Ok(raw.to_string())
+    } else {
+        Err(format!("'{raw}' must be a token or a quoted string"))
+    }
+}
+
+/// Parse one `Forwarded` header value into its elements (RFC 7239 §4).
+/// Each element is parsed independently so that one malformed element
+/// does not hide the others.
+pub fn parse_forwarded(value: &str) -> Vec<Result<ForwardedElement, String>> {
+    split_unquoted(value, ',')
+        .into_iter()
+        .map(|element| {
+            let mut parsed = ForwardedElement::default();
+            for pair in split_unquoted(element, ';') {
+                let pair = pair.trim();
+                if pair.is_empty() {
+                    continue;
+                }
+                let (name, raw) = pair
+                    .split_once('=')
+                    .ok_or_else(|| format!("'{pair}' is not a name=value pair"))?;
+                if name.is_empty() || !name.chars().all(is_tchar) {
+                    return Err(format!("'{name}' is not a valid parameter name"));
+                }
+                let name = name.to_ascii_lowercase();
+                if parsed.get(&name).is_some() {
+                    return Err(format!("parameter '{name}' occurs more than once"));
+                }
+                parsed.pairs.push((name, parse_value(raw)?));
+            }
+            if parsed.pairs.is_empty() {
+                return Err("empty element".to_string());
+            }
+            Ok(parsed)
+        })
+        .collect()
+}
+
+/// Parse a node identifier (RFC 7239 §6), ignoring any port.
+/// `X-Forwarded-For` entries go through the same function since proxies
+/// in the wild also append ports and bracketed IPv6 there.
+pub fn parse_node(raw: &str) -> Node {
+    let raw = raw.trim();
+    if raw.eq_ignore_ascii_case("unknown") {
+        return Node::Unknown;
+    }
+    if let Some(obf) = raw.strip_prefix('_') {
+        let valid = !obf.is_empty()
+            && obf.chars().all(|c| c.is_ascii_alphanumeric() || "._-".contains(c));
+        return if valid {
+            Node::Obfuscated(raw.to_string())
+        } else {
+            Node::Invalid(raw.to_string())
+        };
+    }
+    if let Ok(ip) = raw.parse::<IpAddr>() {
+        return Node::Ip(ip);
+    }
+    if let Ok(sa) = raw.parse::<SocketAddr>() {
+        return Node::Ip(sa.ip());
+    }
+    // Bracketed IPv6 with an obfuscated port, or an IPv4 with one.
+    let host = match raw.strip_prefix('[') {
+        Some(rest) => rest.split_once(']').map(|(h, _)| h),
+        None => raw.split_once(':').map(|(h, _)| h),
+    };
+    match host.and_then(|h| h.parse::<IpAddr>().ok()) {
+        Some(ip) => Node::Ip(ip),
+        None => Node::Invalid(raw.to_string()),
+    }
+}
+
+/// True for addresses that cannot be a genuine public client:
+/// RFC 1918, loopback, link-local, CGNAT, unique-local and unspecified.
+pub fn is_private(ip: IpAddr) -> bool {
+    match ip.to_canonical() {
+        IpAddr::V4(v4) => {
+            let [a, b, ..] = v4.octets();
+            v4.is_private()
+                || v4.is_loopback()
+                || v4.is_link_local()
+                || v4.is_unspecified()
+                || (a == 100 && (64..128).contains(&b))
+        }
+        IpAddr::V6(v6) => {
+            let first = v6.segments()[0];
+            v6.is_loopback()
+                || v6.is_unspecified()
+                || (first & 0xfe00) == 0xfc00
+                || (first & 0xffc0) == 0xfe80
+        }
+    }
+}
+
+/// Comma-separated values across every occurrence of `name`.
+pub(crate) fn header_list(headers: &HeaderMap, name: &str) -> Vec<String> {
+    headers
+        .get_all(name)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+struct Hop {
+    node: Node,
+    proto: Option<String>,
+    host: Option<String>,
+}
+
+/// The hop a correctly configured application takes as the client.
+pub struct Resolution {
+    pub client: Node,
+    /// `proto=` / `X-Forwarded-Proto` recorded for the client's hop, if any.
+    pub proto: Option<String>,
+    /// `host=` / `X-Forwarded-Host` recorded for the client's hop, if any.
+    pub host: Option<String>,
+    /// `forwarded`, `x-forwarded-for`, or `socket` when the peer is the client.
+    pub source: &'static str,
+    /// The socket peer is a trusted proxy, so its other forwarding headers count.
+    pub peer_trusted: bool,
+    chain: Vec<Value>,
+    warnings: Vec<Value>,
+}
+
+/// Work out the client IP, scheme and host that a correctly configured
+/// application would conclude, given the trusted proxy list.
+pub fn resolve_client(headers: &HeaderMap, client_addr: SocketAddr, trusted: &[Cidr]) -> Value {
+    let r = resolve(headers, client_addr, trusted);
+    let host = r
+        .host
+        .or_else(|| headers.get("host").and_then(|v| v.to_str().ok()).map(str::to_string));
+    json!({
+        "effective_ip": match r.client { Node::Ip(ip) => Some(ip.to_string()), _ => None },
+        "effective_client": r.client.to_string(),
+        "scheme": r.proto.unwrap_or_else(|| "http".to_string()),
+        "host": host,
+        "source": r.source,
+        "chain": r.chain,
+        "warnings": r.warnings,
+    })
+}
+
+/// Find the client's hop.
+///
+/// The chain is the forwarding header's list of nodes followed by the socket
+/// peer.  Starting at the peer, we step left while the current hop is a
+/// trusted proxy; the first untrusted hop is the client.  Anything further
+/// left was supplied by that client and is reported as spoofable.
+pub fn resolve(headers: &HeaderMap, client_addr: SocketAddr, trusted: &[Cidr]) -> Resolution {
+    let mut warnings: Vec<Value> = Vec::new();
+    let mut warn = |kind: &str, message: String| {
+        warnings.push(json!({"kind": kind, "message": message}));
+    };
+
+    let forwarded_values: Vec<&str> = headers
+        .get_all("forwarded")
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .collect();
+    let xff = header_list(headers, "x-forwarded-for");
+
+    let mut hops: Vec<Hop> = Vec::new();
+    let source = if !forwarded_values.is_empty() {
+        for value in &forwarded_values {
+            for element in parse_forwarded(value) {
+                match element {
+                    Ok(e) => {
+                        let node = e.get("for").map(parse_node).unwrap_or(Node::Unknown);
+                        if let Node::Invalid(ref raw) = node {
+                            warn("malformed", format!("Forwarded for= value '{raw}' is not a valid node"));
+                        }
+                        hops.push(Hop {
+                            node,
+                            proto: e.get("proto").map(str::to_string),
+                            host: e.get("host").map(str::to_string),
+                        });
+                    }
+                    Err(msg) => {
+                        warn("malformed", format!("Forwarded: {msg}"));
+                        hops.push(Hop {
+                            node: Node::Invalid("(malformed)".to_string()),
+                            proto: None,
+                            host: None,
+                        });
+                    }
+                }
+            }
+        }
+        if !xff.is_empty() {
+            let fwd_ips: Vec<String> = hops.iter().map(|h| h.node.to_string()).collect();
+            let xff_ips: Vec<String> = xff.iter().map(|s| parse_node(s).to_string()).collect();
+            if fwd_ips != xff_ips {
+                warn(
+                    "mismatch",
+                    format!(
+                        "Forwarded and X-Forwarded-For disagree ({} vs {}); Forwarded was used",
+                        fwd_ips.join(", "),
+                        xff_ips.join(", ")
+                    ),
+                );
+            }
+        }
+        "forwarded"
+    } else if !xff.is_empty() {
+        let protos = header_list(headers, "x-forwarded-proto");
+        let hosts = header_list(headers, "x-forwarded-host");
+        // Proxies usually overwrite X-Forwarded-Proto/-Host rather than append to
+        // them, so only align by position when the lists are the same length.
+        let pick = |list: &[String], i: usize| {
+            if list.len() == xff.len() {
+                list.get(i).cloned()
+            } else {
+                list.last().cloned()
+            }
+        };
+        for (i, entry) in xff.iter().enumerate() {
+            let node = parse_node(entry);
+            if let Node::Invalid(ref raw) = node {
+                warn("malformed", format!("X-Forwarded-For entry '{raw}' is not an IP address"));
+            }
+            hops.push(Hop {
+                node,
+                proto: pick(&protos, i),
+                host: pick(&hosts, i),
+            });
+        }
+        "x-forwarded-for"
+    } else {
+        "socket"
+    };
+
+    let is_trusted = |node: &Node| match node {
+        Node::Ip(ip) => trusted.iter().any(|c| c.contains(*ip)),
+        _ => false,
+    };
+
+    // Walk from the socket peer leftwards through trusted proxies.
+    let peer = Node::Ip(client_addr.ip().to_canonical());
+    let peer_trusted = is_trusted(&peer);
+    let mut client_index = hops.len(); // hops.len() == the socket peer itself
+    if peer_trusted {
+        while client_index > 0 {
+            client_index -= 1;
+            if !is_trusted(&hops[client_index].node) {
+                break;
+            }
+        }
+    } else if !hops.is_empty() {
+        warn(
+            "spoofable",
+            format!(
+                "{} header received from untrusted peer {}; anyone can set it, so it was ignored{}",
+                if source == "forwarded" { "Forwarded" } else { "X-Forwarded-For" },
+                client_addr.ip(),
+                if trusted.is_empty() { " (no trusted proxies are configured)" } else { "" }
+            ),
+        );
+    }
+
+    if client_index > 0 && client_index < hops.len() {
+        let spoofable: Vec<String> = hops[..client_index].iter().map(|h| h.node.to_string()).collect();
+        warn(
+            "spoofable",
+            format!(
+                "{} left of the client ({}) came from the client itself and cannot be trusted",
+                if spoofable.len() == 1 { "1 entry" } else { "entries" },
+                spoofable.join(", ")
+            ),
+        );
+    }
+
+    let (client_node, proto, host) = if client_index == hops.len() {
+        (peer.clone(), None, None)
+    } else {
+        let hop = &hops[client_index];
+        (hop.node.clone(), hop.proto.clone(), hop.host.clone())
+    };
+
+    match client_node {
+        Node::Ip(ip) if is_private(ip) => warn(
+            "private",
+            format!(
+                "effective client {ip} is a private address; if it is one of your proxies, add it to the trusted list"
+            ),
+        ),
+        Node::Ip(_) => {}
+        ref other => warn(
+            "malformed",
+            format!("client could not be identified: the nearest untrusted hop is '{other}'"),
+        ),
+    }
+
+    let mut chain: Vec<Value> = hops
+        .iter()
+        .enumerate()
+        .map(|(i, hop)| {
+            json!({
+                "node": hop.node.to_string(),
+                "trusted": is_trusted(&hop.node),
+                "client": i == client_index,
+            })
+        })
+        .collect();
+    chain.push(json!({
+        "node": peer.to_string(),
+        "trusted": peer_trusted,
+        "client": client_index == hops.len(),
+        "socket": true,
+    }));
+
+    Resolution {
+        client: client_node,
+        proto,
+        host,
+        source: if client_index == hops.len() { "socket" } else { source },
+        peer_trusted,
+        chain,
+        warnings,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::headers;
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    fn cidrs(list: &[&str]) -> Vec<Cidr> {
+        list.iter().map(|c| Cidr::parse(c).unwrap()).collect()
+    }
+
+    #[test]
+    fn cidr_parse_and_contains() {
+        let net = Cidr::parse("10.0.0.0/8").unwrap();
+        assert!(net.contains(ip("10.1.2.3")));
+        assert!(!net.contains(ip("11.0.0.1")));
+        assert!(net.contains(ip("::ffff:10.1.2.3")));
+
+        let host = Cidr::parse("192.0.2.1").unwrap();
+        assert_eq!(host.to_string(), "192.0.2.1/32");
+        assert!(host.contains(ip("192.0.2.1")));
+        assert!(!host.contains(ip("192.0.2.2")));
+
+        let v6 = Cidr::parse("fd00::/8").unwrap();
+        assert!(v6.contains(ip("fd12::1")));
+        assert!(!v6.contains(ip("10.0.0.1")));
+
+        assert!(Cidr::parse("0.0.0.0/0").unwrap().contains(ip("203.0.113.9")));
+        assert!(Cidr::parse("10.0.0.0/33").is_err());
+        assert!(Cidr::parse("10.0.0/8").is_err());
+        assert!(Cidr::parse("10.0.0.0/x").is_err());
+    }
+
+    #[test]
+    fn cidr_ipv4_mapped_network() {
+        let mapped = Cidr::parse("::ffff:10.0.0.0/104").unwrap();
+        assert_eq!(mapped.to_string(), "10.0.0.0/8");
+        assert!(mapped.contains(ip("10.9.8.7")));
+        assert!(mapped.contains(ip("::ffff:10.9.8.7")));
+        assert!(!mapped.contains(ip("11.0.0.1")));
+
+        // Wider than the mapped range: stays IPv6 but still covers IPv4 peers.
+        let wide = Cidr::parse("::ffff:0:0/95").unwrap();
+        assert!(wide.contains(ip("10.0.0.1")));
+    }
+
+    #[test]
+    fn forwarded_elements() {
+        let parsed = parse_forwarded(r#"for=192.0.2.60;proto=http;by=203.0.113.43, for="[2001:db8::1]:4711";host="a\"b""#);
+        assert_eq!(parsed.len(), 2);
+        let first = parsed[0].as_ref().unwrap();
+        assert_eq!(first.get("for"), Some("192.0.2.60"));
+        assert_eq!(first.get("proto"), Some("http"));
+        let second = parsed[1].as_ref().unwrap();
+        assert_eq!(second.get("for"), Some("[2001:db8::1]:4711"));
+        assert_eq!(second.get("host"), Some("a\"b"));
+
+        // Parameter names are case-insensitive and may not repeat.
+        assert_eq!(parse_forwarded("For=1.2.3.4")[0].as_ref().unwrap().get("for"), Some("1.2.3.4"));
+        assert!(parse_forwarded("for=1.2.3.4;for=5.6.7.8")[0].is_err());
+
+        // One bad element does not hide the others.
+        let mixed = parse_forwarded("for=1.2.3.4, garbage, for=\"unterminated");
+        assert!(mixed[0].is_ok());
+        assert!(mixed[1].is_err());
+        assert!(mixed[2].is_err());
+        assert!(parse_forwarded("for=a b")[0].is_err());
+    }
+
+    #[test]
+    fn node_forms() {
+        assert!(matches!(parse_node("192.0.2.1"), Node::Ip(a) if a == ip("192.0.2.1")));
+        assert!(matches!(parse_node("192.0.2.1:8080"), Node::Ip(a) if a == ip("192.0.2.1")));
+        assert!(matches!(parse_node("[2001:db8::1]:443"), Node::Ip(a) if a == ip("2001:db8::1")));
+        assert!(matches!(parse_node("[2001:db8::1]:_port"), Node::Ip(a) if a == ip("2001:db8::1")));
+        assert!(matches!(parse_node("2001:db8::1"), Node::Ip(_)));
+        assert!(matches!(parse_node("unknown"), Node::Unknown));
+        assert!(matches!(parse_node("_hidden-1"), Node::Obfuscated(_)));
+        assert!(matches!(parse_node("_bad!"), Node::Invalid(_)));
+        assert!(matches!(parse_node("example.com"), Node::Invalid(_)));
+    }
+
+    #[test]
+    fn private_ranges() {
+        for a in ["10.0.0.1", "172.16.0.1", "192.168.1.1", "127.0.0.1", "169.254.1.1", "100.64.0.1", "::1", "fd00::1", "fe80::1", "::ffff:10.0.0.1"] {
+            assert!(is_private(ip(a)), "{a}");
+        }
+        for a in ["8.8.8.8", "100.128.0.1", "2001:db8::1"] {
+            assert!(!is_private(ip(a)), "{a}");
+        }
+    }
+
+    fn peer(s: &str) -> SocketAddr {
+        SocketAddr::new(ip(s), 40000)
+    }
+
+    #[test]
+    fn untrusted_peer_ignores_forwarding_headers() {
+        let h = headers(&[("x-forwarded-for", "1.2.3.4")]);
+        let r = resolve_client(&h, peer("203.0.113.5"), &[]);
+        assert_eq!(r["effective_ip"], "203.0.113.5");
+        assert_eq!(r["source"], "socket");
+        assert_eq!(r["warnings"][0]["kind"], "spoofable");
+    }
+
+    #[test]
+    fn walks_trusted_proxies_from_the_right() {
+        let h = headers(&[
+            ("x-forwarded-for", "6.6.6.6, 198.51.100.7, 10.0.0.2"),
+            ("x-forwarded-proto", "https"),
+            ("host", "example.com"),
+        ]);
+        let r = resolve_client(&h, peer("10.0.0.1"), &cidrs(&["10.0.0.0/8"]));
+        assert_eq!(r["effective_ip"], "198.51.100.7");
+        assert_eq!(r["source"], "x-forwarded-for");
+        assert_eq!(r["scheme"], "https");
+        assert_eq!(r["host"], "example.com");
+        let chain = r["chain"].as_array().unwrap();
+        assert_eq!(chain.len(), 4);
+        assert_eq!(chain[1]["client"], true);
+        assert_eq!(chain[3]["socket"], true);
+        assert!(r["warnings"].as_array().unwrap().iter().any(|w| w["kind"] == "spoofable"));
+    }
+
+    #[test]
+    fn forwarded_wins_over_x_forwarded_for() {
+        let h = headers(&[
+            ("forwarded", "for=198.51.100.7;proto=https;host=app.example"),
+            ("x-forwarded-for", "192.0.2.1"),
+        ]);
+        let r = resolve_client(&h, peer("::ffff:10.0.0.1"), &cidrs(&["::ffff:10.0.0.0/104"]));
+        assert_eq!(r["effective_ip"], "198.51.100.7");
+        assert_eq!(r["source"], "forwarded");
+        assert_eq!(r["scheme"], "https");
+        assert_eq!(r["host"], "app.example");
+        assert!(r["warnings"].as_array().unwrap().iter().any(|w| w["kind"] == "mismatch"));
+    }
+
+    #[test]
+    fn private_client_and_malformed_hops_are_flagged() {
+        let trusted = cidrs(&["127.0.0.1"]);
+        let r = resolve_client(&headers(&[("x-forwarded-for", "192.168.0.9")]), peer("127.0.0.1"), &trusted);
+        assert_eq!(r["effective_ip"], "192.168.0.9");
+        assert!(r["warnings"].as_array().unwrap().iter().any(|w| w["kind"] == "private"));
+
+        let r = resolve_client(&headers(&[("x-forwarded-for", "not-an-ip")]), peer("127.0.0.1"), &trusted);
+        assert_eq!(r["effective_ip"], Value::Null);
+        assert!(r["warnings"].as_array().unwrap().iter().all(|w| w["kind"] == "malformed"));
+    }
+}
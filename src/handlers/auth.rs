@@ -0,0 +1,204 @@
+use hyper::body::HttpBody;
+use hyper::{Body, Request, Response, StatusCode};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use super::common::{headers_json, node_label, parse_query, OrInternalError};
+use crate::config::ServerConfig;
+use crate::forward_auth::{self, Action, AuthLog, AuthRule, Original};
+
+/// Set on every `/auth` answer.  An allowed answer's copy reaching the
+/// backend shows the proxy injects auth response headers.
+const AUTH_ID_HEADER: &str = "X-Sensillum-Auth-Id";
+
+/// Redacted in the log on top of the configured prefixes and JWT headers:
+/// unlike the echo, the log shows other people's requests.
+const CREDENTIAL_HEADERS: &[&str] = &["authorization", "proxy-authorization", "cookie"];
+
+/// The `X-*-Access-Token` family that auth proxies pass upstream
+/// (`X-Forwarded-Access-Token`, `X-Amzn-Oidc-Accesstoken`…).
+fn is_access_token_header(name: &str) -> bool {
+    name.starts_with("x-") && (name.ends_with("-access-token") || name.ends_with("-accesstoken"))
+}
+
+fn json_response(status: StatusCode, body: Value) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .header("Cache-Control", "no-store")
+        .body(Body::from(body.to_string()))
+        .or_500()
+}
+
+/// A short ID that is unique across nodes.
+fn new_auth_id() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    let digest = Sha256::digest(format!("{}:{}:{}", nanos, std::process::id(), n));
+    digest.iter().take(6).map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Any method to /auth or /auth/{path}[?rule=R&set=Name:Value]
+///
+/// A forward-auth target (Traefik ForwardAuth, nginx `auth_request`, Envoy
+/// ext_authz).  Records the sub-request, works out the original request from
+/// the forwarding headers (or, for Envoy, the path after `/auth`), and
+/// answers according to the first matching rule: 200 with the configured
+/// headers for the proxy to inject upstream, or the rule's deny status.
+/// `rule` and `set` parameters replace the configured rules and headers, for
+/// proxies whose auth URL can carry a query; with a path suffix the query
+/// belongs to the original request instead.
+pub async fn handle_auth(
+    req: Request<Body>,
+    suffix: &str,
+    client_addr: SocketAddr,
+    config: Arc<ServerConfig>,
+    log: Arc<AuthLog>,
+) -> Response<Body> {
+    let suffix = (!suffix.is_empty()).then(|| match req.uri().query() {
+        Some(q) => format!("{suffix}?{q}"),
+        None => suffix.to_string(),
+    });
+
+    let mut rules = config.auth_rules.clone();
+    let mut inject = config.auth_headers.clone();
+    if suffix.is_none() {
+        let query = parse_query(req.uri().query().unwrap_or(""));
+        let values = |key: &str| -> Vec<String> {
+            query.pairs.iter().filter(|p| p.key == key).map(|p| p.value.clone()).collect()
+        };
+        let (rule_params, set_params) = (values("rule"), values("set"));
+        if !rule_params.is_empty() {
+            match rule_params.iter().map(|r| AuthRule::parse(r)).collect::<Result<Vec<_>, _>>() {
+                Ok(parsed) => rules = parsed,
+                Err(e) => return json_response(StatusCode::BAD_REQUEST, json!({"error": e})),
+            }
+        }
+        if !set_params.is_empty() {
+            match set_params.iter().map(|h| forward_auth::parse_auth_header(h)).collect::<Result<Vec<_>, _>>() {
+                Ok(parsed) => inject = parsed,
+                Err(e) => return json_response(StatusCode::BAD_REQUEST, json!({"error": e})),
+            }
+        }
+    }
+
+    let original = Original::from_request(req.method(), req.headers(), suffix.as_deref());
+    let rule = forward_auth::decide(&rules, &original, req.headers());
+    let status = match rule.map(AuthRule::action) {
+        Some(Action::Deny(status)) => *status,
+        Some(Action::Allow) | None => StatusCode::OK,
+    };
+    let allowed = status == StatusCode::OK;
+    if !allowed {
+        inject.clear();
+    }
+
+    let id = new_auth_id();
+    let redact: Vec<String> = config
+        .redact_prefixes
+        .iter()
+        .cloned()
+        .chain(CREDENTIAL_HEADERS.iter().map(|h| h.to_string()))
+        .chain(config.jwt_headers.iter().cloned())
+        .chain(
+            req.headers()
+                .keys()
+                .map(|name| name.as_str())
+                .filter(|name| is_access_token_header(name))
+                .map(str::to_string),
+        )
+        .collect();
+    let headers = headers_json(req.headers(), &redact);
+    let mut entry = json!({
+        "id": id,
+        "node": node_label(&config),
+        "client_addr": client_addr.to_string(),
+        "method": req.method().as_str(),
+        "uri": req.uri().to_string(),
+        "protocol": format!("{:?}", req.version()),
+        "original": original.to_json(),
+        "forwarded": forward_auth::forwarded_headers(&headers),
+        "headers": headers,
+        "allowed": allowed,
+        "status": status.as_u16(),
+        "rule": rule.map(AuthRule::text),
+        "injected": inject.iter().map(|(name, value)| json!({"name": name, "value": value})).collect::<Vec<_>>(),
+    });
+    // ext_authz can be configured to send the request body along.  Only its
+    // size is logged, so count it without holding it in memory.
+    let mut body = req.into_body();
+    let mut body_bytes = 0;
+    while let Some(Ok(chunk)) = body.data().await {
+        body_bytes += chunk.len();
+    }
+    entry["body_bytes"] = json!(body_bytes);
+
+    println!(
+        "Auth sub-request {}: {} {} — {} {}{}",
+        id,
+        original.method,
+        original.uri.as_deref().unwrap_or("(unknown URI)"),
+        if allowed { "allowed" } else { "denied" },
+        status.as_u16(),
+        rule.map(|r| format!(" by rule '{}'", r.text())).unwrap_or_default()
+    );
+    log.record(entry);
+
+    let mut builder = Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .header("Cache-Control", "no-store")
+        .header(AUTH_ID_HEADER, id.as_str());
+    if status == StatusCode::UNAUTHORIZED {
+        builder = builder.header("WWW-Authenticate", "Bearer realm=\"sensillum\"");
+    }
+    for (name, value) in &inject {
+        builder = builder.header(name.as_str(), value.as_str());
+    }
+    builder
+        .body(Body::from(
+            json!({
+                "id": id,
+                "allowed": allowed,
+                "status": status.as_u16(),
+                "rule": rule.map(AuthRule::text),
+            })
+            .to_string(),
+        ))
+        .or_500()
+}
+
+/// GET /auth-log
+///
+/// The auth sub-requests this node answered, newest first.  Ask repeatedly
+/// through the load balancer to collect the other nodes' logs.
+pub async fn handle_log(config: Arc<ServerConfig>, log: Arc<AuthLog>) -> Response<Body> {
+    json_response(
+        StatusCode::OK,
+        json!({
+            "node": node_label(&config),
+            "requests": log.recent(),
+        }),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn access_token_family() {
+        assert!(is_access_token_header("x-forwarded-access-token"));
+        assert!(is_access_token_header("x-auth-request-access-token"));
+        assert!(is_access_token_header("x-amzn-oidc-accesstoken"));
+        assert!(!is_access_token_header("x-amzn-oidc-identity"));
+        assert!(!is_access_token_header("access-token"));
+    }
+}
@@ -1,5 +1,11 @@
 use crate::build_info;
+use crate::client_cert;
 use crate::config::ServerConfig;
+use crate::fingerprint;
+use crate::forwarded;
+use crate::jwt;
+use crate::trace;
+use crate::via;
 use hyper::HeaderMap;
 use hyper::{Body, Response, StatusCode};
 use serde_json::{json, Value};
@@ -25,6 +31,21 @@ impl OrInternalError for Result<Response<Body>, hyper::http::Error> {
     }
 }
 
+/// The URL prefix the current request was reached under, as matched by
+/// `handle_request`.  Stored in the request extensions.
+#[derive(Clone, Default)]
+pub struct UrlPrefix(pub String);
+
+impl UrlPrefix {
+    /// Read the matched prefix from a request, defaulting to the root.
+    pub fn of<B>(req: &hyper::Request<B>) -> String {
+        req.extensions()
+            .get::<UrlPrefix>()
+            .map(|p| p.0.clone())
+            .unwrap_or_default()
+    }
+}
+
 /// Compute SHA-256 hash and return first 16 bytes as array for insignia generation
 fn compute_hash_bytes(input: &str) -> Vec<u8> {
     let mut hasher = Sha256::new();
@@ -34,57 +55,166 @@ fn compute_hash_bytes(input: &str) -> Vec<u8> {
     hash[..16].to_vec()
 }
 
-/// Decode a percent-encoded query value ('+' → space, %XX → byte).
-pub fn decode_query_value(s: &str) -> String {
-    let mut out = String::with_capacity(s.len());
-    let mut bytes = s.bytes();
-    while let Some(b) = bytes.next() {
-        match b {
-            b'+' => out.push(' '),
+/// Percent-decode `s` to raw bytes, optionally treating `+` as a space.
+/// Invalid escapes (a `%` not followed by two hex digits) are kept literally
+/// and reported through the returned flag.
+fn percent_decode(s: &str, plus_as_space: bool) -> (Vec<u8>, bool) {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut invalid_escape = false;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
             b'%' => {
-                let h1 = bytes.next().and_then(|c| (c as char).to_digit(16));
-                let h2 = bytes.next().and_then(|c| (c as char).to_digit(16));
-                if let (Some(h1), Some(h2)) = (h1, h2) {
-                    out.push(char::from(((h1 << 4) | h2) as u8));
+                let hex = |j: usize| bytes.get(j).and_then(|&b| (b as char).to_digit(16));
+                if let (Some(h1), Some(h2)) = (hex(i + 1), hex(i + 2)) {
+                    out.push(((h1 << 4) | h2) as u8);
+                    i += 3;
+                    continue;
                 }
+                invalid_escape = true;
+                out.push(b'%');
             }
-            _ => out.push(b as char),
+            b'+' if plus_as_space => out.push(b' '),
+            b => out.push(b),
+        }
+        i += 1;
+    }
+    (out, invalid_escape)
+}
+
+/// Percent-decode a path to raw bytes.  Invalid escapes are kept literally.
+pub fn percent_decode_bytes(s: &str) -> Vec<u8> {
+    percent_decode(s, false).0
+}
+
+/// Decode standard or URL-safe base64, with or without padding.  Whitespace
+/// is skipped, so PEM bodies decode as they are.
+pub fn base64_decode(text: &str) -> Result<Vec<u8>, String> {
+    let mut out = Vec::new();
+    let mut buf: u32 = 0;
+    let mut bits = 0;
+    for c in text.bytes() {
+        let v = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            b'=' => break,
+            c if c.is_ascii_whitespace() => continue,
+            _ => return Err(format!("invalid base64 character '{}'", c as char)),
+        };
+        buf = (buf << 6) | v as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buf >> bits) as u8);
         }
     }
-    out
+    Ok(out)
 }
 
-/// Parse a query string into a simple key→value map (last value wins).
-pub fn parse_query(query: &str) -> std::collections::HashMap<String, String> {
-    query
+/// One `key=value` pair of a query string, in both raw and decoded form.
+pub struct QueryPair {
+    pub raw_key: String,
+    pub raw_value: String,
+    pub key: String,
+    pub value: String,
+    /// A `%` not followed by two hex digits appeared in the key or value.
+    pub invalid_escape: bool,
+    /// The decoded bytes were not valid UTF-8, so U+FFFD was substituted.
+    pub invalid_utf8: bool,
+}
+
+/// A parsed query string: every pair in order, with duplicates kept.
+pub struct Query {
+    pub raw: String,
+    pub pairs: Vec<QueryPair>,
+}
+
+impl Query {
+    /// Value for `key`, if any; the last one wins when it is repeated.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.pairs
+            .iter()
+            .rev()
+            .find(|p| p.key == key)
+            .map(|p| p.value.as_str())
+    }
+
+    /// Structured echo of the query for JSON responses.
+    pub fn to_json(&self) -> Value {
+        let pairs: Vec<Value> = self
+            .pairs
+            .iter()
+            .map(|p| {
+                json!({
+                    "key": p.key,
+                    "value": p.value,
+                    "raw_key": p.raw_key,
+                    "raw_value": p.raw_value,
+                    "invalid_escape": p.invalid_escape,
+                    "invalid_utf8": p.invalid_utf8,
+                })
+            })
+            .collect();
+        json!({"raw": self.raw, "pairs": pairs})
+    }
+}
+
+/// Parse an `application/x-www-form-urlencoded` query string.
+/// Segments with an empty key (`a=1&&b=2`, `=v`) are skipped; everything else
+/// is kept in order.
+pub fn parse_query(query: &str) -> Query {
+    let pairs = query
         .split('&')
-        .filter_map(|pair| {
-            let (k, v) = pair.split_once('=').unwrap_or((pair, ""));
-            if k.is_empty() {
-                None
-            } else {
-                Some((decode_query_value(k), decode_query_value(v)))
+        .map(|pair| pair.split_once('=').unwrap_or((pair, "")))
+        .filter(|(raw_key, _)| !raw_key.is_empty())
+        .map(|(raw_key, raw_value)| {
+            let (key, key_bad_escape) = percent_decode(raw_key, true);
+            let (value, value_bad_escape) = percent_decode(raw_value, true);
+            let (key, key_utf8) = match String::from_utf8(key) {
+                Ok(k) => (k, true),
+                Err(e) => (String::from_utf8_lossy(e.as_bytes()).into_owned(), false),
+            };
+            let (value, value_utf8) = match String::from_utf8(value) {
+                Ok(v) => (v, true),
+                Err(e) => (String::from_utf8_lossy(e.as_bytes()).into_owned(), false),
+            };
+            QueryPair {
+                raw_key: raw_key.to_string(),
+                raw_value: raw_value.to_string(),
+                key,
+                value,
+                invalid_escape: key_bad_escape || value_bad_escape,
+                invalid_utf8: !(key_utf8 && value_utf8),
             }
         })
-        .collect()
+        .collect();
+    Query {
+        raw: query.to_string(),
+        pairs,
+    }
 }
 
-/// Build a JSON object containing server information for client diagnostics
-pub fn build_server_info(
-    headers: &HeaderMap,
-    client_addr: SocketAddr,
-    server_addr: SocketAddr,
-    config: Arc<ServerConfig>,
-    protocol: String,
-) -> Value {
-    // Split headers into echoed and redacted based on config.redact_prefixes.
-    // Redacted headers appear in the map as {"redacted": true} rather than their value.
+/// How this node identifies itself in per-node logs (retry attempts, auth
+/// sub-requests): the node name, or the hostname unless privacy mode hides it.
+pub fn node_label(config: &ServerConfig) -> Option<String> {
+    config
+        .node_name
+        .clone()
+        .or_else(|| (!config.privacy_mode).then(|| config.hostname.clone()))
+}
+
+/// The request headers as a JSON object.  Headers matching `redact_prefixes`
+/// appear as {"redacted": true} rather than their value.
+pub fn headers_json(headers: &HeaderMap, redact_prefixes: &[String]) -> Value {
     let mut headers_map = serde_json::Map::new();
 
     for (name, value) in headers.iter() {
         let name_str = name.to_string(); // HeaderName is already lowercase
-        let json_value = if config
-            .redact_prefixes
+        let json_value = if redact_prefixes
             .iter()
             .any(|p| name_str.starts_with(p.as_str()))
         {
@@ -105,12 +235,29 @@ pub fn build_server_info(
         };
         headers_map.insert(name_str, json_value);
     }
+    Value::Object(headers_map)
+}
 
+/// Build a JSON object containing server information for client diagnostics
+pub fn build_server_info(
+    headers: &HeaderMap,
+    client_addr: SocketAddr,
+    server_addr: SocketAddr,
+    config: Arc<ServerConfig>,
+    protocol: String,
+    url_prefix: &str,
+) -> Value {
     let mut server_info = json!({
         "client_addr": client_addr.to_string(),
         "protocol": protocol,
         "version": build_info::version(),
-        "headers": Value::Object(headers_map),
+        "headers": headers_json(headers, &config.redact_prefixes),
+        "client_ip": forwarded::resolve_client(headers, client_addr, &config.trusted_proxies),
+        "fingerprint": fingerprint::fingerprint(headers, &config.redact_prefixes),
+        "via": via::via_json(headers),
+        "trace": trace::trace_json(headers, &config.redact_prefixes),
+        "client_cert": client_cert::client_cert_json(headers, &config.redact_prefixes),
+        "tokens": jwt::tokens_json(headers, &config.jwt_headers, &config.redact_prefixes),
     });
 
     if !config.privacy_mode {
@@ -118,7 +265,7 @@ pub fn build_server_info(
         server_info["hostname"] = json!(config.hostname);
         server_info["hostname_hash"] = json!(compute_hash_bytes(&config.hostname));
         server_info["build_time"] = json!(build_info::build_time());
-        server_info["url_prefix"] = json!(config.url_prefix.as_deref().unwrap_or(""));
+        server_info["url_prefix"] = json!(url_prefix);
     }
 
     if let Some(ref node_name) = config.node_name {
@@ -128,3 +275,51 @@ pub fn build_server_info(
 
     server_info
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repeated_parameter_last_value_wins() {
+        let query = parse_query("status=200&status=503");
+        assert_eq!(query.get("status"), Some("503"));
+        assert_eq!(query.pairs.len(), 2);
+        assert_eq!(query.pairs[0].value, "200");
+    }
+
+    #[test]
+    fn query_decoding() {
+        let query = parse_query("a+b=c%20d&&flag&=v&bad=%zz&bytes=%ff");
+        let keys: Vec<&str> = query.pairs.iter().map(|p| p.key.as_str()).collect();
+        assert_eq!(keys, ["a b", "flag", "bad", "bytes"]);
+        assert_eq!(query.get("a b"), Some("c d"));
+        assert_eq!(query.get("flag"), Some(""));
+        assert_eq!(query.get("missing"), None);
+
+        let bad = &query.pairs[2];
+        assert_eq!(bad.value, "%zz");
+        assert!(bad.invalid_escape && !bad.invalid_utf8);
+        let bytes = &query.pairs[3];
+        assert_eq!(bytes.value, "\u{fffd}");
+        assert_eq!(bytes.raw_value, "%ff");
+        assert!(bytes.invalid_utf8 && !bytes.invalid_escape);
+    }
+
+    #[test]
+    fn path_decoding_keeps_plus_and_bad_escapes() {
+        assert_eq!(percent_decode_bytes("a+b%2Fc"), b"a+b/c");
+        assert_eq!(percent_decode_bytes("100%"), b"100%");
+        assert_eq!(percent_decode_bytes("%4"), b"%4");
+    }
+
+    #[test]
+    fn base64_variants() {
+        assert_eq!(base64_decode("aGk/Pz8+").unwrap(), b"hi???>");
+        assert_eq!(base64_decode("aGk_Pz8-").unwrap(), b"hi???>");
+        assert_eq!(base64_decode("aGk=").unwrap(), b"hi");
+        assert_eq!(base64_decode("aGk").unwrap(), b"hi");
+        assert_eq!(base64_decode("aG\nk=\n").unwrap(), b"hi");
+        assert_eq!(base64_decode("aG*k").unwrap_err(), "invalid base64 character '*'");
+    }
+}
@@ -3,7 +3,7 @@ use super::common::{parse_query, OrInternalError};
 
 pub async fn handle_delete_cookie(req: Request<Body>) -> Response<Body> {
     let name = req.uri().query()
-        .and_then(|q| parse_query(q).remove("name"))
+        .and_then(|q| parse_query(q).get("name").map(str::to_string))
         .unwrap_or_default();
 
     if name.is_empty() {
@@ -0,0 +1,66 @@
+use hyper::{Body, Request, Response, StatusCode};
+use serde_json::json;
+use std::sync::Arc;
+
+use super::common::{parse_query, OrInternalError};
+use crate::conn::ConnMeta;
+use crate::desync::DesyncMonitor;
+
+fn json_response(body: serde_json::Value) -> Response<Body> {
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("Content-Type", "application/json")
+        .header("Cache-Control", "no-store")
+        .body(Body::from(body.to_string()))
+        .or_500()
+}
+
+/// GET /desync[?canary=T]
+///
+/// What the backend has seen of smuggling probes: the probes themselves,
+/// requests that arrived on the smuggled path, connections that ended in a
+/// parse error, bodies shorter than their `Content-Length`, requests that
+/// followed a probe without its canary, and how many requests each connection
+/// carried.  With `canary`, only the events for that
+/// probe and its connections.
+pub async fn handle_report(req: Request<Body>, monitor: Arc<DesyncMonitor>) -> Response<Body> {
+    let query = parse_query(req.uri().query().unwrap_or(""));
+    json_response(monitor.report(query.get("canary")))
+}
+
+/// POST /desync/probe?canary=T&variant=V
+///
+/// The visible half of a smuggling probe.  Records which backend connection
+/// it arrived on and whether its body carried the smuggled request, which
+/// means the proxy and Sensillum agreed on the framing.
+pub async fn handle_probe(req: Request<Body>, monitor: Arc<DesyncMonitor>) -> Response<Body> {
+    let query = parse_query(req.uri().query().unwrap_or(""));
+    let canary = query.get("canary").unwrap_or("").to_string();
+    let variant = query.get("variant").unwrap_or("").to_string();
+    let conn = ConnMeta::of(&req);
+    let body = hyper::body::to_bytes(req.into_body()).await.unwrap_or_default();
+    monitor.probe(&canary, &variant, conn, &body);
+    json_response(json!({
+        "probe": canary,
+        "connection": conn.id,
+        "request_index": conn.request_index,
+        "body_bytes": body.len(),
+    }))
+}
+
+/// Any method to <anything>/desync/smuggled?canary=T&variant=V
+///
+/// Only reachable by a request the proxy never saw as one.  The response
+/// carries `X-Desync-Smuggled`, so whichever client receives it can tell.
+pub async fn handle_smuggled(req: Request<Body>, monitor: Arc<DesyncMonitor>) -> Response<Body> {
+    let query = parse_query(req.uri().query().unwrap_or(""));
+    let canary = query.get("canary");
+    monitor.smuggled(&req, canary, query.get("variant"));
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("Content-Type", "application/json")
+        .header("Cache-Control", "no-store")
+        .header("X-Desync-Smuggled", canary.unwrap_or("unknown"))
+        .body(Body::from(json!({"smuggled": true, "canary": canary}).to_string()))
+        .or_500()
+}
@@ -1,35 +1,97 @@
-use hyper::{Body, Request, Response, StatusCode, HeaderMap};
+use hyper::{Body, Method, Request, Response, StatusCode, HeaderMap};
 use std::sync::Arc;
 use std::net::SocketAddr;
 use crate::config::ServerConfig;
-use super::common::{build_server_info, OrInternalError};
+use super::common::{build_server_info, parse_query, OrInternalError, UrlPrefix};
+use crate::external_url;
 
+/// Request headers that frameworks honour to override the real method.
+const METHOD_OVERRIDE_HEADERS: &[&str] = &[
+    "x-http-method-override",
+    "x-http-method",
+    "x-method-override",
+    "x-original-method",
+];
+
+/// Methods defined by RFC 9110 and RFC 5789 (PATCH); anything else is an extension method.
+const STANDARD_METHODS: &[Method] = &[
+    Method::GET,
+    Method::HEAD,
+    Method::POST,
+    Method::PUT,
+    Method::DELETE,
+    Method::CONNECT,
+    Method::OPTIONS,
+    Method::TRACE,
+    Method::PATCH,
+];
+
+/// Any method, including extension methods such as PROPFIND or PURGE, to /echo[/...]
+///
+/// Echoes the method alongside any method-override headers or `_method` query
+/// parameter, so the client can tell whether the proxy rewrote the method or
+/// converted an override into a real one.  HEAD gets the same headers as GET
+/// (including `Content-Length`) but no body; the method is also reported in
+/// `X-Sensillum-Method` so HEAD callers can read it.
 pub async fn handle_echo(
-    _req: Request<Body>,
+    req: Request<Body>,
     headers: HeaderMap,
     config: Arc<ServerConfig>,
     client_addr: SocketAddr,
     server_addr: SocketAddr,
     protocol: String,
-    path: String,
-    query: Option<String>,
 ) -> Response<Body> {
+    let url_prefix = UrlPrefix::of(&req);
     let mut response_data = build_server_info(
         &headers,
         client_addr,
         server_addr,
-        config,
+        config.clone(),
         protocol,
+        &url_prefix,
     );
 
-    response_data["path"] = serde_json::json!(path);
-    response_data["query"] = serde_json::json!(query);
+    let method = req.method();
+    let mut overrides = serde_json::Map::new();
+    for name in METHOD_OVERRIDE_HEADERS {
+        if let Some(v) = headers.get(*name) {
+            overrides.insert(
+                name.to_string(),
+                serde_json::json!(String::from_utf8_lossy(v.as_bytes())),
+            );
+        }
+    }
+    let query = parse_query(req.uri().query().unwrap_or(""));
+    if let Some(m) = query.get("_method") {
+        overrides.insert("_method".to_string(), serde_json::json!(m));
+    }
+
+    response_data["method"] = serde_json::json!(method.as_str());
+    response_data["method_standard"] = serde_json::json!(STANDARD_METHODS.contains(method));
+    response_data["method_overrides"] = serde_json::Value::Object(overrides);
+    // Counts repeated fields separately, as proxies' header-count limits do.
+    response_data["header_count"] = serde_json::json!(headers.len());
+    response_data["path"] = serde_json::json!(req.uri().path());
+    response_data["query"] = serde_json::json!(req.uri().query());
+    response_data["query_parsed"] = query.to_json();
+    response_data["external_url"] = external_url::reconstruct(
+        &headers,
+        req.uri(),
+        &url_prefix,
+        client_addr,
+        &config.trusted_proxies,
+    );
 
-    let response = Response::builder()
+    let body = response_data.to_string();
+    let builder = Response::builder()
         .status(StatusCode::OK)
         .header("Content-Type", "application/json")
-        .body(Body::from(response_data.to_string()))
-        .or_500();
+        .header("Content-Length", body.len())
+        .header("X-Sensillum-Method", method.as_str());
 
-    response
+    if method == Method::HEAD {
+        builder.body(Body::empty()).or_500()
+    } else {
+        builder.body(Body::from(body)).or_500()
+    }
 }
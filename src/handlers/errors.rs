@@ -0,0 +1,69 @@
+use hyper::{Body, Request, Response, StatusCode};
+use serde_json::json;
+
+use super::common::{parse_query, OrInternalError};
+use super::hops::ALLOWED_METHODS;
+
+/// Error statuses the sweep covers.
+const ERROR_STATUSES: &[u16] = &[
+    400, 401, 403, 404, 405, 408, 409, 413, 422, 429, 500, 501, 502, 503, 504,
+];
+
+/// GET /error
+///
+/// Returns the list of error statuses the UI sweeps.
+///
+/// GET /error?status=N[&marker=M]
+///
+/// Answers with status N, a JSON body containing `marker` and an
+/// `X-Sensillum-Error-Marker` header with the same value, plus the headers the
+/// status normally comes with (`WWW-Authenticate`, `Allow`, `Retry-After`).
+/// If the marker is missing from what the client receives, the proxy replaced
+/// the backend's error page; if the status differs, it rewrote the status.
+pub async fn handle_error(req: Request<Body>) -> Response<Body> {
+    let query = parse_query(req.uri().query().unwrap_or(""));
+    let Some(status) = query.get("status") else {
+        return Response::builder()
+            .status(StatusCode::OK)
+            .header("Content-Type", "application/json")
+            .body(Body::from(json!({"statuses": ERROR_STATUSES}).to_string()))
+            .or_500();
+    };
+    let Some(status) = status
+        .parse::<u16>()
+        .ok()
+        .filter(|s| (400..600).contains(s))
+        .and_then(|s| StatusCode::from_u16(s).ok())
+    else {
+        return Response::builder()
+            .status(StatusCode::BAD_REQUEST)
+            .header("Content-Type", "application/json")
+            .body(Body::from(r#"{"error":"status must be from 400 to 599"}"#))
+            .or_500();
+    };
+    let marker = query
+        .get("marker")
+        .filter(|m| !m.is_empty() && m.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-'))
+        .unwrap_or("sensillum-error-marker")
+        .to_string();
+
+    let mut builder = Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .header("Cache-Control", "no-store")
+        .header("X-Sensillum-Error-Marker", marker.as_str());
+    builder = match status {
+        StatusCode::UNAUTHORIZED => builder.header("WWW-Authenticate", r#"Bearer realm="sensillum""#),
+        StatusCode::METHOD_NOT_ALLOWED => builder.header("Allow", ALLOWED_METHODS),
+        StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE => builder.header("Retry-After", "1"),
+        _ => builder,
+    };
+
+    let body = json!({
+        "error": status.canonical_reason().unwrap_or("Error"),
+        "status": status.as_u16(),
+        "marker": marker,
+        "detail": "Backend error body from Sensillum. If you can read this, the proxy passed it through.",
+    });
+    builder.body(Body::from(body.to_string())).or_500()
+}
@@ -5,6 +5,9 @@ use hyper::{Body, Request, Response, StatusCode};
 /// Safety cap — no point generating more than 2 MiB of response headers.
 const MAX_RESPONSE_HEADER_BYTES: usize = 2 * 1024 * 1024;
 
+/// Safety cap for `count=N`.
+const MAX_RESPONSE_HEADER_COUNT: usize = 10_000;
+
 /// GET /hdr?byte=<hexbyte>
 ///
 /// Response-header character test: sends back a single `x-charset-test` header whose
@@ -15,6 +18,13 @@ const MAX_RESPONSE_HEADER_BYTES: usize = 2 * 1024 * 1024;
 /// control characters and 0x7F, in which case the response body contains
 /// `{"ok":false,"reason":"byte rejected by HTTP library"}`.
 ///
+/// GET /hdr?sweep
+///
+/// The same test for every byte in one response: one `x-charset-XX` header (lower-case
+/// hex) per byte that `HeaderValue::from_bytes` accepts, each carrying `probe<byte>probe`.
+/// The body is a manifest listing which bytes were sent and which hyper rejected, so the
+/// client can tell a stripped header from one that was never sent.
+///
 /// GET /hdr?size=N&mode=single|multi
 ///
 /// Returns a response whose headers consume approximately `size` bytes:
@@ -22,9 +32,46 @@ const MAX_RESPONSE_HEADER_BYTES: usize = 2 * 1024 * 1024;
 ///   - mode=multi   → ten headers (X-Response-Test-0 … -9) each of size/10 bytes
 ///
 /// The client uses this to binary-search the proxy's *response* header size limit.
+///
+/// GET /hdr?count=N
+///
+/// Returns N small headers (`x-response-count-0: x` … `x-response-count-<N-1>: x`), so the
+/// client can binary-search a header-*count* limit such as Envoy's `max_headers_count` or
+/// HAProxy's `tune.http.maxhdr`.  A proxy may reject the response or silently drop the
+/// excess, so the client counts the headers that arrived.
 pub async fn handle_response_headers_test(req: Request<Body>) -> Response<Body> {
     let params = parse_query(req.uri().query().unwrap_or(""));
 
+    // --- charset sweep branch ---
+    if params.get("sweep").is_some() {
+        let mut builder = Response::builder()
+            .status(StatusCode::OK)
+            .header("Content-Type", "application/json")
+            .header("Cache-Control", "no-store")
+            .header("Access-Control-Expose-Headers", "*");
+        let mut sent = Vec::new();
+        let mut rejected = Vec::new();
+        for byte_val in 0..=255u8 {
+            let hex = format!("{:02x}", byte_val);
+            match HeaderValue::from_bytes(&[b"probe".as_slice(), &[byte_val], b"probe"].concat()) {
+                Ok(hv) => {
+                    builder = builder.header(format!("x-charset-{}", hex), hv);
+                    sent.push(hex);
+                }
+                Err(_) => rejected.push(hex),
+            }
+        }
+        let manifest = serde_json::json!({
+            "ok": true,
+            "header_prefix": "x-charset-",
+            "value_format": "probe<byte>probe",
+            "sent": sent,
+            "rejected": rejected,
+            "reason": "byte rejected by HTTP library",
+        });
+        return builder.body(Body::from(manifest.to_string())).or_500();
+    }
+
     // --- character-test branch ---
     if let Some(hex) = params.get("byte") {
         if let Ok(byte_val) = u8::from_str_radix(hex.trim(), 16) {
@@ -58,13 +105,27 @@ pub async fn handle_response_headers_test(req: Request<Body>) -> Response<Body>
         }
     }
 
+    if let Some(count) = params.get("count").and_then(|c| c.parse::<usize>().ok()) {
+        let count = count.min(MAX_RESPONSE_HEADER_COUNT);
+        let mut builder = Response::builder()
+            .status(StatusCode::OK)
+            .header("Content-Type", "application/json")
+            .header("Access-Control-Expose-Headers", "*");
+        for i in 0..count {
+            builder = builder.header(format!("x-response-count-{}", i), "x");
+        }
+        return builder
+            .body(Body::from(format!(r#"{{"ok":true,"count":{}}}"#, count)))
+            .or_500();
+    }
+
     let size: usize = params
         .get("size")
         .and_then(|s| s.parse().ok())
         .unwrap_or(0)
         .min(MAX_RESPONSE_HEADER_BYTES);
 
-    let mode = params.get("mode").cloned().unwrap_or_default();
+    let mode = params.get("mode").unwrap_or_default();
 
     let mut builder = Response::builder()
         .status(StatusCode::OK)
@@ -0,0 +1,235 @@
+use hyper::{Body, HeaderMap, Method, Request, Response, StatusCode};
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use super::common::{build_server_info, parse_query, OrInternalError, UrlPrefix};
+use crate::config::ServerConfig;
+use crate::via;
+
+/// Methods every Sensillum route accepts.
+pub const ALLOWED_METHODS: &str = "GET, HEAD, POST, PUT, PATCH, DELETE, OPTIONS, TRACE";
+
+/// The client's own `Max-Forwards` value, passed as `?max_forwards=N` so that
+/// the decrement can be worked out server-side.
+fn sent_max_forwards(req: &Request<Body>) -> Option<u32> {
+    req.uri()
+        .query()
+        .and_then(|q| parse_query(q).get("max_forwards")?.parse().ok())
+}
+
+/// OPTIONS|TRACE /max-forwards[?max_forwards=N]
+///
+/// The hop analysis endpoint: dispatches to [`handle_options`] or
+/// [`handle_trace`], and answers other methods with 405.
+pub async fn handle_max_forwards(
+    req: Request<Body>,
+    headers: HeaderMap,
+    config: Arc<ServerConfig>,
+    client_addr: SocketAddr,
+    server_addr: SocketAddr,
+    protocol: String,
+) -> Response<Body> {
+    match *req.method() {
+        Method::OPTIONS => handle_options(req, headers, config, client_addr, server_addr, protocol).await,
+        Method::TRACE => handle_trace(req, config, client_addr).await,
+        _ => Response::builder()
+            .status(StatusCode::METHOD_NOT_ALLOWED)
+            .header("Allow", "OPTIONS, TRACE")
+            .body(Body::from("Use OPTIONS or TRACE"))
+            .or_500(),
+    }
+}
+
+/// OPTIONS * or OPTIONS /max-forwards[?max_forwards=N]
+///
+/// Answers as the final recipient (RFC 9110 §9.3.7) with an `Allow` header and
+/// a JSON body containing the usual server info plus the parsed `Via` chain
+/// and a `Max-Forwards` decrement check.
+pub async fn handle_options(
+    req: Request<Body>,
+    headers: HeaderMap,
+    config: Arc<ServerConfig>,
+    client_addr: SocketAddr,
+    server_addr: SocketAddr,
+    protocol: String,
+) -> Response<Body> {
+    let mut response_data = build_server_info(
+        &headers,
+        client_addr,
+        server_addr,
+        config,
+        protocol,
+        &UrlPrefix::of(&req),
+    );
+    response_data["method"] = serde_json::json!("OPTIONS");
+    response_data["max_forwards"] = via::max_forwards_report(&headers, sent_max_forwards(&req));
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("Allow", ALLOWED_METHODS)
+        .header("Content-Type", "application/json")
+        .header("Cache-Control", "no-store")
+        .body(Body::from(response_data.to_string()))
+        .or_500()
+}
+
+/// TRACE /max-forwards[?max_forwards=N]
+///
+/// Reflects the request as received, as `message/http` (RFC 9110 §9.3.8).
+/// Redacted headers are masked.  Reaching this handler at all means every
+/// proxy in front let TRACE through, which enables cross-site tracing, so the
+/// response carries `X-Sensillum-Trace: reached-backend` and the verdict of
+/// the `Max-Forwards` check.
+pub async fn handle_trace(
+    req: Request<Body>,
+    config: Arc<ServerConfig>,
+    client_addr: SocketAddr,
+) -> Response<Body> {
+    println!(
+        "TRACE request from {} reached the backend — any proxy in front lets TRACE through (cross-site tracing risk)",
+        client_addr
+    );
+
+    let report = via::max_forwards_report(req.headers(), sent_max_forwards(&req));
+
+    let mut body = format!("{} {} {:?}\r\n", req.method(), req.uri(), req.version());
+    for (name, value) in req.headers() {
+        body.push_str(name.as_str());
+        body.push_str(": ");
+        if config.redact_prefixes.iter().any(|p| name.as_str().starts_with(p.as_str())) {
+            body.push_str("(redacted)");
+        } else {
+            body.push_str(&String::from_utf8_lossy(value.as_bytes()));
+        }
+        body.push_str("\r\n");
+    }
+    body.push_str("\r\n");
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("Content-Type", "message/http")
+        .header("Cache-Control", "no-store")
+        .header("X-Sensillum-Trace", "reached-backend")
+        .header(
+            "X-Sensillum-Max-Forwards",
+            report["verdict"].as_str().unwrap_or("unknown"),
+        )
+        .body(Body::from(body))
+        .or_500()
+}
+
+/// Connection-specific request and response fields (RFC 9110 §7.6.1), plus
+/// `Trailer`, which proxies commonly treat the same way.
+const HOP_BY_HOP: &[&str] = &[
+    "connection",
+    "keep-alive",
+    "proxy-connection",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+];
+
+/// Response headers that `/hop` lists in its own `Connection` header; a proxy
+/// must remove them before the response reaches the client.
+const LISTED_RESPONSE_HEADERS: &[&str] = &["x-hop-listed-a", "x-hop-listed-b"];
+
+/// Names from a comma-separated list, lower-cased, keeping only valid tokens.
+fn header_names(list: &str) -> Vec<String> {
+    list.split(',')
+        .map(|s| s.trim().to_ascii_lowercase())
+        .filter(|s| !s.is_empty() && s.chars().all(crate::forwarded::is_tchar))
+        .collect()
+}
+
+/// GET /hop[?expect=x-hop-a,x-hop-b]
+///
+/// Request direction: reports which hop-by-hop headers reached the backend,
+/// and which of the headers named in `Connection` survived.  Because a proxy
+/// that strips `Connection` takes the list with it, the client repeats the
+/// names it listed in `?expect=` so they can still be checked.
+///
+/// Response direction: lists `x-hop-listed-a`/`-b` in its own `Connection`
+/// header and also sends `Keep-Alive`, `Proxy-Connection` and an unlisted
+/// control header, so the client can see which ones the proxy stripped.
+/// Over HTTP/2 hyper strips these itself, which the body notes.
+pub async fn handle_hop_by_hop(req: Request<Body>) -> Response<Body> {
+    let headers = req.headers();
+    let value_of = |name: &str| -> Option<String> {
+        let values: Vec<String> = headers
+            .get_all(name)
+            .iter()
+            .map(|v| String::from_utf8_lossy(v.as_bytes()).into_owned())
+            .collect();
+        (!values.is_empty()).then(|| values.join(", "))
+    };
+
+    let received: serde_json::Map<String, serde_json::Value> = HOP_BY_HOP
+        .iter()
+        .map(|&name| (name.to_string(), serde_json::json!(value_of(name))))
+        .collect();
+
+    // `Connection: keep-alive` / `close` are connection options, not header names.
+    let listed: Vec<String> = value_of("connection")
+        .map(|v| header_names(&v))
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|n| n != "keep-alive" && n != "close" && n != "upgrade")
+        .collect();
+    let expected: Vec<String> = req
+        .uri()
+        .query()
+        .map(|q| header_names(parse_query(q).get("expect").unwrap_or("")))
+        .unwrap_or_default();
+
+    let mut names: Vec<&String> = expected.iter().chain(listed.iter()).collect();
+    names.sort();
+    names.dedup();
+    let connection_listed: Vec<serde_json::Value> = names
+        .into_iter()
+        .map(|name| {
+            serde_json::json!({
+                "name": name,
+                "listed_on_arrival": listed.contains(name),
+                "arrived": headers.contains_key(name.as_str()),
+            })
+        })
+        .collect();
+
+    let forwarded_hop_by_hop: Vec<&str> = HOP_BY_HOP
+        .iter()
+        .copied()
+        .filter(|&n| headers.contains_key(n))
+        .collect();
+
+    let is_h2 = req.version() == hyper::Version::HTTP_2;
+    let body = serde_json::json!({
+        "protocol": format!("{:?}", req.version()),
+        "received": received,
+        "forwarded_hop_by_hop": forwarded_hop_by_hop,
+        "connection_listed": connection_listed,
+        "response": {
+            "connection": format!("keep-alive, {}", LISTED_RESPONSE_HEADERS.join(", ")),
+            "listed": LISTED_RESPONSE_HEADERS,
+            "also_sent": ["keep-alive", "proxy-connection"],
+            "control": "x-hop-control",
+            "stripped_by_sensillum": is_h2,
+        },
+    });
+
+    let mut builder = Response::builder()
+        .status(StatusCode::OK)
+        .header("Content-Type", "application/json")
+        .header("Cache-Control", "no-store")
+        .header("X-Hop-Control", "should-arrive");
+    if !is_h2 {
+        builder = builder
+            .header("Connection", format!("keep-alive, {}", LISTED_RESPONSE_HEADERS.join(", ")))
+            .header("Keep-Alive", "timeout=30")
+            .header("Proxy-Connection", "keep-alive");
+        for name in LISTED_RESPONSE_HEADERS {
+            builder = builder.header(*name, "should-be-stripped");
+        }
+    }
+    builder.body(Body::from(body.to_string())).or_500()
+}
@@ -3,7 +3,8 @@ use rust_embed::RustEmbed;
 use std::net::SocketAddr;
 use std::sync::Arc;
 
-use super::common::{build_server_info, OrInternalError};
+use super::common::{build_server_info, OrInternalError, UrlPrefix};
+use crate::external_url;
 use crate::build_info;
 use crate::config::ServerConfig;
 
@@ -33,7 +34,10 @@ pub async fn handle_index(
             build_info::full_version()
         )
     };
-    let server_info = build_server_info(req.headers(), client_addr, server_addr, config, protocol);
+    let url_prefix = UrlPrefix::of(&req);
+    let mut server_info = build_server_info(req.headers(), client_addr, server_addr, config.clone(), protocol, &url_prefix);
+    server_info["external_url"] =
+        external_url::reconstruct(req.headers(), req.uri(), &url_prefix, client_addr, &config.trusted_proxies);
 
     let body = [
         header.data.as_ref(),
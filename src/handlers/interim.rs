@@ -0,0 +1,189 @@
+use hyper::body::HttpBody;
+use hyper::{Body, Request, Response, StatusCode, Version};
+use serde_json::json;
+use std::time::{Duration, Instant};
+
+use super::common::{parse_query, OrInternalError, UrlPrefix};
+use crate::conn::RawConn;
+
+/// Upper bound for `?delay_ms=`, so a request cannot park a connection forever.
+const MAX_DELAY_MS: u64 = 30_000;
+
+fn delay_param(req: &Request<Body>, default: u64) -> u64 {
+    parse_query(req.uri().query().unwrap_or(""))
+        .get("delay_ms")
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(default)
+        .min(MAX_DELAY_MS)
+}
+
+fn elapsed_ms(start: Instant) -> f64 {
+    start.elapsed().as_secs_f64() * 1000.0
+}
+
+/// POST|PUT /continue[?delay_ms=N][&refuse]
+///
+/// Handles `Expect: 100-continue` explicitly.  hyper writes `100 Continue`
+/// when the body is first read, so the handler waits `delay_ms` before
+/// reading it, or with `refuse` answers `417 Expectation Failed` without
+/// reading it at all.  The report says whether the expectation arrived and
+/// when the body arrived relative to the 100: together with the request head,
+/// during the delay (the client or a proxy did not wait), or after it.
+pub async fn handle_continue(mut req: Request<Body>) -> Response<Body> {
+    let start = Instant::now();
+    let delay_ms = delay_param(&req, 0);
+    let refuse = parse_query(req.uri().query().unwrap_or("")).get("refuse").is_some();
+    let expect = req
+        .headers()
+        .get("expect")
+        .map(|v| String::from_utf8_lossy(v.as_bytes()).into_owned());
+    let expects_continue = expect
+        .as_deref()
+        .is_some_and(|v| v.trim().eq_ignore_ascii_case("100-continue"));
+    let content_length = req
+        .headers()
+        .get("content-length")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok());
+    let raw = RawConn::of(&req);
+    // hyper only sends 100 Continue on HTTP/1.1.
+    let continue_possible = expects_continue && req.version() == Version::HTTP_11;
+    let bytes_at_head = raw.as_ref().map(RawConn::bytes_read);
+
+    // Wait before reading the body, noting whether body bytes show up on the
+    // socket before the 100 has been sent.
+    let delay = Duration::from_millis(delay_ms);
+    let mut arrived_during_delay_ms = None;
+    if let Some(raw) = &raw {
+        if !delay.is_zero() && raw.wait_readable(delay).await {
+            arrived_during_delay_ms = Some(elapsed_ms(start));
+        }
+    }
+    tokio::time::sleep(delay.saturating_sub(start.elapsed())).await;
+
+    let mut report = json!({
+        "protocol": format!("{:?}", req.version()),
+        "expect": expect,
+        "content_length": content_length,
+        "delay_ms": delay_ms,
+        "refused": refuse,
+    });
+
+    if refuse {
+        report["continue_sent"] = json!(false);
+        report["verdict"] = json!("refused");
+        report["detail"] = json!("answered 417 without reading the body");
+        report["body_arrived_during_delay_ms"] = json!(arrived_during_delay_ms);
+        return Response::builder()
+            .status(StatusCode::EXPECTATION_FAILED)
+            .header("Content-Type", "application/json")
+            .header("Cache-Control", "no-store")
+            .header("Connection", "close")
+            .body(Body::from(report.to_string()))
+            .or_500();
+    }
+
+    // Reading the body is what makes hyper send 100 Continue.
+    let continue_at_ms = elapsed_ms(start);
+    let body = req.body_mut();
+    let mut first_chunk_ms = None;
+    let mut buffered_with_head = false;
+    let mut body_bytes: u64 = 0;
+    let mut body_error = None;
+    while let Some(chunk) = body.data().await {
+        match chunk {
+            Ok(chunk) => {
+                if first_chunk_ms.is_none() && !chunk.is_empty() {
+                    first_chunk_ms = Some(elapsed_ms(start));
+                    buffered_with_head = bytes_at_head.is_some()
+                        && bytes_at_head == raw.as_ref().map(RawConn::bytes_read);
+                }
+                body_bytes += chunk.len() as u64;
+            }
+            Err(e) => {
+                body_error = Some(e.to_string());
+                break;
+            }
+        }
+    }
+    let body_done_ms = elapsed_ms(start);
+
+    let (verdict, detail) = if !expects_continue {
+        ("no-expect", "Expect: 100-continue did not arrive; if the client sent it, a proxy removed it or answered it itself".to_string())
+    } else if first_chunk_ms.is_none() {
+        ("no-body", "the request had no body".to_string())
+    } else if buffered_with_head {
+        ("with-head", "the body arrived together with the request head, without waiting for 100 Continue".to_string())
+    } else if let Some(t) = arrived_during_delay_ms {
+        ("before-continue", format!("the body started arriving after {t:.1} ms, before 100 Continue was sent at {continue_at_ms:.1} ms"))
+    } else if !continue_possible {
+        ("no-continue", "100 Continue is only sent on HTTP/1.1".to_string())
+    } else {
+        ("after-continue", format!("the body started arriving {:.1} ms after 100 Continue was sent", first_chunk_ms.unwrap_or(0.0) - continue_at_ms))
+    };
+
+    report["continue_sent"] = json!(continue_possible);
+    report["continue_at_ms"] = json!(continue_at_ms);
+    report["body_arrived_during_delay_ms"] = json!(arrived_during_delay_ms);
+    report["body_buffered_with_head"] = json!(buffered_with_head);
+    report["first_body_chunk_ms"] = json!(first_chunk_ms);
+    report["body_complete_ms"] = json!(body_done_ms);
+    report["body_bytes"] = json!(body_bytes);
+    report["body_error"] = json!(body_error);
+    report["verdict"] = json!(verdict);
+    report["detail"] = json!(detail);
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("Content-Type", "application/json")
+        .header("Cache-Control", "no-store")
+        .body(Body::from(report.to_string()))
+        .or_500()
+}
+
+/// GET /early-hints[?delay_ms=N]
+///
+/// Writes `103 Early Hints` with a preload `Link` header straight to the
+/// socket, waits `delay_ms` (default 500), then sends the final response with
+/// the same `Link`.  hyper cannot send 1xx responses itself, so this only
+/// works on HTTP/1.1; over HTTP/2 the body says the hint was not sent.
+pub async fn handle_early_hints(req: Request<Body>) -> Response<Body> {
+    let delay_ms = delay_param(&req, 500);
+    let link = format!(
+        "<{}/early-hints/hint.css>; rel=preload; as=style",
+        UrlPrefix::of(&req)
+    );
+
+    let mut sent = false;
+    if req.version() == Version::HTTP_11 {
+        if let Some(raw) = RawConn::of(&req) {
+            let hint = format!("HTTP/1.1 103 Early Hints\r\nLink: {}\r\n\r\n", link);
+            sent = raw.write_all(hint.as_bytes()).await.is_ok();
+        }
+    }
+    tokio::time::sleep(Duration::from_millis(delay_ms)).await;
+
+    let body = json!({
+        "protocol": format!("{:?}", req.version()),
+        "early_hints_sent": sent,
+        "link": link,
+        "delay_ms": delay_ms,
+    });
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("Content-Type", "application/json")
+        .header("Cache-Control", "no-store")
+        .header("Link", link)
+        .body(Body::from(body.to_string()))
+        .or_500()
+}
+
+/// GET /early-hints/hint.css — the resource named in the early hint.
+pub async fn handle_hint_css() -> Response<Body> {
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("Content-Type", "text/css")
+        .header("Cache-Control", "no-store")
+        .body(Body::from("/* early hint preload target */\n"))
+        .or_500()
+}
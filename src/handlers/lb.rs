@@ -2,10 +2,10 @@ use hyper::{Body, Request, Response, StatusCode, HeaderMap};
 use std::sync::Arc;
 use std::net::SocketAddr;
 use crate::config::ServerConfig;
-use super::common::{build_server_info, OrInternalError};
+use super::common::{build_server_info, OrInternalError, UrlPrefix};
 
 pub async fn handle_lb(
-    _req: Request<Body>,
+    req: Request<Body>,
     headers: HeaderMap,
     config: Arc<ServerConfig>,
     client_addr: SocketAddr,
@@ -13,22 +13,26 @@ pub async fn handle_lb(
     protocol: String,
 ) -> Response<Body> {
     // Build server info using shared function
-    let response_data = build_server_info(
+    let mut response_data = build_server_info(
         &headers,
         client_addr,
         server_addr,
-        config,
+        config.clone(),
         protocol,
+        &UrlPrefix::of(&req),
     );
 
-    let response = Response::builder()
+    // Pod and container identity, so the grid shows the real topology.
+    if !config.privacy_mode && !config.identity.is_empty() {
+        response_data["identity"] = config.identity.to_json();
+    }
+
+    Response::builder()
         .status(StatusCode::OK)
         .header("Content-Type", "application/json")
         .header("Cache-Control", "no-cache, no-store, must-revalidate")
         .header("Pragma", "no-cache")
         .header("Expires", "0")
         .body(Body::from(response_data.to_string()))
-        .or_500();
-
-    response
+        .or_500()
 }
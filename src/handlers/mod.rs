@@ -7,3 +7,15 @@ pub mod common;
 pub mod waf;
 pub mod cookie;
 pub mod hdr;
+pub mod hops;
+pub mod norm;
+pub mod interim;
+pub mod raw;
+pub mod desync;
+pub mod reflect;
+pub mod security;
+pub mod errors;
+pub mod slow;
+pub mod retry;
+pub mod oidc;
+pub mod auth;
@@ -0,0 +1,160 @@
+use hyper::{Body, Request, Response, StatusCode};
+use serde_json::json;
+
+use super::common::{percent_decode_bytes, OrInternalError, UrlPrefix};
+
+/// A path that proxies and backends are known to disagree about.
+struct NormCase {
+    id: &'static str,
+    description: &'static str,
+    /// Path to request, relative to the URL prefix.  Every case stays under
+    /// `/norm/` even after normalisation, so it still reaches this handler.
+    path: &'static str,
+}
+
+static NORM_CASES: &[NormCase] = &[
+    NormCase { id: "dot-dot", description: "Dot-dot segment", path: "/norm/t/a/../b" },
+    NormCase { id: "dot", description: "Single-dot segment", path: "/norm/t/./b" },
+    NormCase { id: "enc-dot-dot", description: "Percent-encoded dot-dot segment", path: "/norm/t/a/%2e%2e/b" },
+    NormCase { id: "enc-dot", description: "Percent-encoded dot inside a segment", path: "/norm/t/a%2eb" },
+    NormCase { id: "double-slash", description: "Empty segment (//)", path: "/norm/t//b" },
+    NormCase { id: "enc-slash", description: "Percent-encoded slash (%2F)", path: "/norm/t/a%2Fb" },
+    NormCase { id: "enc-slash-lower", description: "Lower-case percent-encoded slash (%2f)", path: "/norm/t/a%2fb" },
+    NormCase { id: "double-enc-slash", description: "Double-encoded slash (%252F)", path: "/norm/t/a%252Fb" },
+    NormCase { id: "backslash", description: "Backslash", path: "/norm/t/a\\b" },
+    NormCase { id: "enc-backslash", description: "Percent-encoded backslash (%5C)", path: "/norm/t/a%5Cb" },
+    NormCase { id: "overlong-dot", description: "Overlong UTF-8 dots (%C0%AE)", path: "/norm/t/a/%C0%AE%C0%AE/b" },
+    NormCase { id: "overlong-slash", description: "Overlong UTF-8 slash (%C0%AF)", path: "/norm/t/a%C0%AFb" },
+    NormCase { id: "semicolon", description: "Path parameter (;jsessionid=)", path: "/norm/t/a;jsessionid=x/b" },
+    NormCase { id: "dot-dot-semicolon", description: "Dot-dot with path parameter (..;)", path: "/norm/t/a/..;/b" },
+    NormCase { id: "case", description: "Mixed-case segment", path: "/norm/t/MiXeD" },
+    NormCase { id: "enc-unreserved", description: "Encoded unreserved character (%7E)", path: "/norm/t/%7Euser" },
+    NormCase { id: "enc-hex-case", description: "Lower-case hex in escape (%c3%a9)", path: "/norm/t/caf%c3%a9" },
+    NormCase { id: "nfc", description: "Unicode NFC (é as U+00E9)", path: "/norm/t/caf%C3%A9" },
+    NormCase { id: "nfd", description: "Unicode NFD (e + U+0301)", path: "/norm/t/cafe%CC%81" },
+    NormCase { id: "fullwidth-slash", description: "Fullwidth solidus (U+FF0F, NFKC → /)", path: "/norm/t/a%EF%BC%8Fb" },
+    NormCase { id: "null", description: "Encoded NUL (%00)", path: "/norm/t/a%00b" },
+    NormCase { id: "enc-question", description: "Encoded question mark (%3F)", path: "/norm/t/a%3Fb" },
+    NormCase { id: "enc-hash", description: "Encoded hash (%23)", path: "/norm/t/a%23b" },
+    NormCase { id: "trailing-space", description: "Encoded trailing space (%20)", path: "/norm/t/a%20" },
+];
+
+/// Lossy rendering of a percent-decoded path, for display.
+fn decoded(path: &str) -> String {
+    String::from_utf8_lossy(&percent_decode_bytes(path)).into_owned()
+}
+
+/// `path` relative to the URL prefix, whether or not the proxy stripped it.
+fn relative<'a>(path: &'a str, prefix: &str) -> &'a str {
+    match path.strip_prefix(prefix) {
+        Some(rest) if !prefix.is_empty() && rest.starts_with('/') => rest,
+        _ => path,
+    }
+}
+
+/// How the path that arrived relates to the one sent.  A fully decoded path
+/// also equals the sent one after decoding both sides, so `decoded` must be
+/// tested before `re-encoded`.
+fn verdict(sent: &str, arrived: &str) -> &'static str {
+    if arrived == sent {
+        "preserved"
+    } else if arrived.as_bytes() == percent_decode_bytes(sent).as_slice() {
+        "decoded"
+    } else if percent_decode_bytes(arrived) == percent_decode_bytes(sent) {
+        "re-encoded"
+    } else {
+        "modified"
+    }
+}
+
+/// GET /norm
+///
+/// Returns the manifest of path-normalisation cases as JSON.
+///
+/// GET /norm/...  with `X-Norm-Case: <id>` and optionally `X-Norm-Sent: <path>`
+///
+/// Reports exactly which path arrived for the case and compares it with what
+/// the client says it sent (browsers normalise some paths themselves, so the
+/// UI declares the path after its own URL parsing).  Without `X-Norm-Sent`,
+/// the case's own path under the current URL prefix is assumed, which is what
+/// `curl --path-as-is` sends.  Both paths are compared below the prefix.  Path confusion between proxy and backend is how
+/// path-based ACLs get bypassed.
+pub async fn handle_norm(req: Request<Body>) -> Response<Body> {
+    let headers = req.headers();
+    let case_id = headers.get("x-norm-case").and_then(|v| v.to_str().ok());
+
+    let Some(case_id) = case_id else {
+        let cases: Vec<_> = NORM_CASES
+            .iter()
+            .map(|c| json!({"id": c.id, "description": c.description, "path": c.path}))
+            .collect();
+        return Response::builder()
+            .status(StatusCode::OK)
+            .header("Content-Type", "application/json")
+            .body(Body::from(json!({"cases": cases}).to_string()))
+            .or_500();
+    };
+
+    let Some(case) = NORM_CASES.iter().find(|c| c.id == case_id) else {
+        return Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .header("Content-Type", "application/json")
+            .body(Body::from(r#"{"error":"unknown case"}"#))
+            .or_500();
+    };
+
+    let prefix = UrlPrefix::of(&req);
+    let intended = format!("{prefix}{}", case.path);
+    let sent = headers
+        .get("x-norm-sent")
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string)
+        .unwrap_or_else(|| intended.clone());
+    let arrived = req.uri().path();
+
+    // Compare below the prefix, so a proxy that strips it is not "modified".
+    let verdict = verdict(relative(&sent, &prefix), relative(arrived, &prefix));
+
+    let body = json!({
+        "case": case.id,
+        "description": case.description,
+        "intended": intended,
+        "sent": sent,
+        "arrived": arrived,
+        "arrived_decoded": decoded(arrived),
+        "query": req.uri().query(),
+        "verdict": verdict,
+    });
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("Content-Type", "application/json")
+        .header("Cache-Control", "no-store")
+        .body(Body::from(body.to_string()))
+        .or_500()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{relative, verdict};
+
+    #[test]
+    fn verdicts() {
+        assert_eq!(verdict("/norm/t/a%2Fb", "/norm/t/a%2Fb"), "preserved");
+        assert_eq!(verdict("/norm/t/a%2Fb", "/norm/t/a/b"), "decoded");
+        assert_eq!(verdict("/norm/t/%41", "/norm/t/A"), "decoded");
+        assert_eq!(verdict("/norm/t/a%2fb", "/norm/t/a%2Fb"), "re-encoded");
+        assert_eq!(verdict("/norm/t/%7Euser", "/norm/t/~user"), "decoded");
+        assert_eq!(verdict("/norm/t/a%252Fb", "/norm/t/a%2Fb"), "decoded");
+        assert_eq!(verdict("/norm/t/a/../b", "/norm/t/b"), "modified");
+    }
+
+    #[test]
+    fn stripped_prefix_is_not_a_modification() {
+        let sent = relative("/api/norm/t/a%2Fb", "/api");
+        assert_eq!(verdict(sent, relative("/norm/t/a%2Fb", "/api")), "preserved");
+        assert_eq!(verdict(sent, relative("/api/norm/t/a/b", "/api")), "decoded");
+        assert_eq!(relative("/apiary/norm", "/api"), "/apiary/norm");
+        assert_eq!(relative("/norm/t", ""), "/norm/t");
+    }
+}
@@ -0,0 +1,371 @@
+use hyper::{Body, Request, Response, StatusCode};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::common::{parse_query, OrInternalError, Query, UrlPrefix};
+
+/// Upper bound for `state_size`, `code_size` and `cookie_size`.
+const MAX_SIZE: usize = 65536;
+
+/// Session cookie chunk sizes.  Browsers refuse cookies whose name and value
+/// exceed 4096 bytes, which leaves room for the chunk name.
+const MIN_CHUNK: usize = 1024;
+const MAX_CHUNK: usize = 4000;
+const MAX_CHUNKS: usize = MAX_SIZE / MIN_CHUNK;
+
+/// Chunks are named `sensillum_oidc_0`, `sensillum_oidc_1`, … as
+/// oauth2-proxy and most OIDC middlewares do.
+const SESSION_COOKIE: &str = "sensillum_oidc";
+const CSRF_COOKIE: &str = "sensillum_oidc_csrf";
+const CSRF_BYTES: usize = 64;
+const NONCE_BYTES: usize = 32;
+
+/// The sizes of one simulated login, carried from step to step in the query
+/// string.  Every value the flow sends is derived from `run`, so any node
+/// behind the load balancer can verify what arrived without shared state.
+struct Flow {
+    run: String,
+    state_size: usize,
+    code_size: usize,
+    cookie_size: usize,
+    chunk_size: usize,
+}
+
+impl Flow {
+    fn from_query(query: &Query) -> Flow {
+        let size = |key: &str, default: usize, min: usize, max: usize| {
+            query
+                .get(key)
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default)
+                .clamp(min, max)
+        };
+        let run = query
+            .get("run")
+            .filter(|r| !r.is_empty() && r.len() <= 32)
+            .filter(|r| r.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_'))
+            .map(str::to_string)
+            .unwrap_or_else(new_run);
+        Flow {
+            run,
+            state_size: size("state_size", 1024, 16, MAX_SIZE),
+            code_size: size("code_size", 512, 16, MAX_SIZE),
+            cookie_size: size("cookie_size", 6144, 0, MAX_SIZE),
+            chunk_size: size("chunk_size", 3800, MIN_CHUNK, MAX_CHUNK),
+        }
+    }
+
+    /// The flow parameters, placed before the large values so a proxy that
+    /// truncates the URL cuts the value being measured, not the sizes.
+    fn query(&self) -> String {
+        format!(
+            "run={}&state_size={}&code_size={}&cookie_size={}&chunk_size={}",
+            self.run, self.state_size, self.code_size, self.cookie_size, self.chunk_size
+        )
+    }
+
+    fn state(&self) -> String {
+        filler(&self.run, "state", self.state_size)
+    }
+
+    fn code(&self) -> String {
+        filler(&self.run, "code", self.code_size)
+    }
+
+    fn csrf(&self) -> String {
+        filler(&self.run, "csrf", CSRF_BYTES)
+    }
+
+    /// The session cookie value split into chunks of at most `chunk_size`.
+    fn chunks(&self) -> Vec<String> {
+        let session = filler(&self.run, "session", self.cookie_size);
+        session
+            .as_bytes()
+            .chunks(self.chunk_size)
+            .map(|c| String::from_utf8_lossy(c).into_owned())
+            .collect()
+    }
+
+    fn to_json(&self) -> Value {
+        json!({
+            "run": self.run,
+            "state_size": self.state_size,
+            "code_size": self.code_size,
+            "cookie_size": self.cookie_size,
+            "chunk_size": self.chunk_size,
+            "chunks": self.cookie_size.div_ceil(self.chunk_size),
+        })
+    }
+}
+
+fn new_run() -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    let digest = Sha256::digest(format!("{}:{}", nanos, std::process::id()));
+    digest.iter().take(6).map(|b| format!("{:02x}", b)).collect()
+}
+
+/// `len` hex characters derived from `run` and `label`: safe in URLs and
+/// cookies unencoded, and reproducible by every node.
+fn filler(run: &str, label: &str, len: usize) -> String {
+    let mut out = String::with_capacity(len + 64);
+    let mut block = 0u64;
+    while out.len() < len {
+        let digest = Sha256::digest(format!("{run}:{label}:{block}"));
+        out.extend(digest.iter().map(|b| format!("{:02x}", b)));
+        block += 1;
+    }
+    out.truncate(len);
+    out
+}
+
+/// Compare a received value with the expected one.
+fn check(name: &str, expected: &str, received: Option<&str>) -> Value {
+    let (ok, detail) = match received {
+        None => (false, "missing".to_string()),
+        Some(r) if r == expected => (true, format!("intact ({} bytes)", r.len())),
+        Some(r) if r.len() < expected.len() && expected.starts_with(r) => (
+            false,
+            format!("truncated to {} of {} bytes", r.len(), expected.len()),
+        ),
+        Some(r) => {
+            let at = r
+                .bytes()
+                .zip(expected.bytes())
+                .position(|(a, b)| a != b)
+                .unwrap_or(r.len().min(expected.len()));
+            (
+                false,
+                format!("{} bytes, expected {}; differs from byte {}", r.len(), expected.len(), at),
+            )
+        }
+    };
+    json!({"check": name, "ok": ok, "detail": detail})
+}
+
+/// Every cookie the request carries, from all `Cookie` headers (HTTP/2
+/// clients may send one per cookie).
+fn request_cookies(req: &Request<Body>) -> HashMap<String, String> {
+    req.headers()
+        .get_all("cookie")
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect()
+}
+
+/// How large the request that reached this step was.
+fn received_json(req: &Request<Body>) -> Value {
+    let cookie_bytes: usize = req.headers().get_all("cookie").iter().map(|v| v.len()).sum();
+    json!({
+        "url_bytes": req.uri().to_string().len(),
+        "cookie_bytes": cookie_bytes,
+    })
+}
+
+fn set_cookie(name: &str, value: &str, path: &str) -> String {
+    format!("{name}={value}; Path={path}; HttpOnly; SameSite=Lax")
+}
+
+fn expire_cookie(name: &str, path: &str) -> String {
+    format!("{name}=; Path={path}; Max-Age=0")
+}
+
+/// Session chunks are only sent to the final step, and the CSRF cookie only
+/// to the callback, so leftovers from a failed run cannot inflate the other
+/// steps' requests.
+fn session_path(prefix: &str) -> String {
+    format!("{prefix}/oidc/session")
+}
+
+fn csrf_path(prefix: &str) -> String {
+    format!("{prefix}/oidc/callback")
+}
+
+fn redirect(location: String, cookies: Vec<String>) -> Response<Body> {
+    let mut builder = Response::builder()
+        .status(StatusCode::FOUND)
+        .header("Location", location)
+        .header("Cache-Control", "no-store");
+    for cookie in cookies {
+        builder = builder.header("Set-Cookie", cookie);
+    }
+    builder.body(Body::empty()).or_500()
+}
+
+/// The JSON report of `step`: 200 when every check passed, else 400.
+fn step_report(
+    step: &str,
+    flow: &Flow,
+    checks: Vec<Value>,
+    received: Value,
+    cookies: Vec<String>,
+) -> Response<Body> {
+    let ok = checks.iter().all(|c| c["ok"] == true);
+    let mut builder = Response::builder()
+        .status(if ok { StatusCode::OK } else { StatusCode::BAD_REQUEST })
+        .header("Content-Type", "application/json")
+        .header("Cache-Control", "no-store");
+    for cookie in cookies {
+        builder = builder.header("Set-Cookie", cookie);
+    }
+    builder
+        .body(Body::from(
+            json!({
+                "step": step,
+                "ok": ok,
+                "flow": flow.to_json(),
+                "checks": checks,
+                "received": received,
+            })
+            .to_string(),
+        ))
+        .or_500()
+}
+
+fn log_step(step: &str, flow: &Flow, checks: &[Value]) {
+    let failed: Vec<String> = checks
+        .iter()
+        .filter(|c| c["ok"] != true)
+        .map(|c| format!("{} {}", c["check"].as_str().unwrap_or(""), c["detail"].as_str().unwrap_or("")))
+        .collect();
+    if failed.is_empty() {
+        println!("OIDC flow {}: {} ok", flow.run, step);
+    } else {
+        println!("OIDC flow {}: {} failed — {}", flow.run, step, failed.join(", "));
+    }
+}
+
+/// GET /oidc/login?run=R&state_size=N&code_size=N&cookie_size=N&chunk_size=N
+///
+/// Starts a simulated OIDC login: sets a CSRF cookie and redirects to the
+/// simulated identity provider with a `state` of `state_size` bytes.  The
+/// flow continues through /oidc/authorize and /oidc/callback, which sets the
+/// session cookie (`cookie_size` bytes in chunks of `chunk_size`), and ends
+/// at /oidc/session.  A step that finds something damaged answers 400 with
+/// its report instead of redirecting, so the URL a client ends up at names
+/// the step that broke.
+pub async fn handle_login(req: Request<Body>) -> Response<Body> {
+    let flow = Flow::from_query(&parse_query(req.uri().query().unwrap_or("")));
+    let prefix = UrlPrefix::of(&req);
+    let redirect_uri = format!("{prefix}/oidc/callback").replace('/', "%2F");
+    let location = format!(
+        "{prefix}/oidc/authorize?{}&client_id=sensillum&response_type=code&scope=openid&redirect_uri={}&nonce={}&state={}",
+        flow.query(),
+        redirect_uri,
+        filler(&flow.run, "nonce", NONCE_BYTES),
+        flow.state()
+    );
+    println!("OIDC flow {}: login", flow.run);
+    redirect(location, vec![set_cookie(CSRF_COOKIE, &flow.csrf(), &csrf_path(&prefix))])
+}
+
+/// GET /oidc/authorize — the identity provider.  Checks that `state`
+/// survived the redirect, then sends the browser to the callback with an
+/// authorization code of `code_size` bytes.
+pub async fn handle_authorize(req: Request<Body>) -> Response<Body> {
+    let query = parse_query(req.uri().query().unwrap_or(""));
+    let flow = Flow::from_query(&query);
+    let prefix = UrlPrefix::of(&req);
+    let checks = vec![check("state", &flow.state(), query.get("state"))];
+    log_step("authorize", &flow, &checks);
+    if checks.iter().any(|c| c["ok"] != true) {
+        return step_report("authorize", &flow, checks, received_json(&req), Vec::new());
+    }
+    let location = format!(
+        "{prefix}/oidc/callback?{}&code={}&state={}",
+        flow.query(),
+        flow.code(),
+        flow.state()
+    );
+    redirect(location, Vec::new())
+}
+
+/// GET /oidc/callback — checks the code, the state and the CSRF cookie,
+/// then sets the chunked session cookie and redirects to /oidc/session.
+pub async fn handle_callback(req: Request<Body>) -> Response<Body> {
+    let query = parse_query(req.uri().query().unwrap_or(""));
+    let flow = Flow::from_query(&query);
+    let prefix = UrlPrefix::of(&req);
+    let cookies = request_cookies(&req);
+    let checks = vec![
+        check("code", &flow.code(), query.get("code")),
+        check("state", &flow.state(), query.get("state")),
+        check("csrf cookie", &flow.csrf(), cookies.get(CSRF_COOKIE).map(String::as_str)),
+    ];
+    log_step("callback", &flow, &checks);
+    if checks.iter().any(|c| c["ok"] != true) {
+        return step_report("callback", &flow, checks, received_json(&req), Vec::new());
+    }
+
+    let path = session_path(&prefix);
+    let mut set_cookies: Vec<String> = flow
+        .chunks()
+        .iter()
+        .enumerate()
+        .map(|(i, chunk)| set_cookie(&format!("{SESSION_COOKIE}_{i}"), chunk, &path))
+        .collect();
+    set_cookies.push(expire_cookie(CSRF_COOKIE, &csrf_path(&prefix)));
+    redirect(format!("{prefix}/oidc/session?{}", flow.query()), set_cookies)
+}
+
+/// GET /oidc/session — the application page after login.  Checks every
+/// session cookie chunk and reports the whole flow; the chunks are expired
+/// either way so the next run starts clean.
+pub async fn handle_session(req: Request<Body>) -> Response<Body> {
+    let flow = Flow::from_query(&parse_query(req.uri().query().unwrap_or("")));
+    let prefix = UrlPrefix::of(&req);
+    let cookies = request_cookies(&req);
+    let path = session_path(&prefix);
+
+    let checks: Vec<Value> = flow
+        .chunks()
+        .iter()
+        .enumerate()
+        .map(|(i, chunk)| {
+            let name = format!("{SESSION_COOKIE}_{i}");
+            check(&name, chunk, cookies.get(&name).map(String::as_str))
+        })
+        .collect();
+    log_step("session", &flow, &checks);
+
+    let expired = cookies
+        .keys()
+        .filter(|name| name.strip_prefix(SESSION_COOKIE).and_then(|r| r.strip_prefix('_')).is_some_and(|i| i.parse::<usize>().is_ok()))
+        .map(|name| expire_cookie(name, &path))
+        .collect();
+    step_report("session", &flow, checks, received_json(&req), expired)
+}
+
+/// GET /oidc/logout?chunks=N
+///
+/// Expires the CSRF cookie and the first N session chunks (default 8), for
+/// clearing up after a run that never reached /oidc/session.
+pub async fn handle_logout(req: Request<Body>) -> Response<Body> {
+    let query = parse_query(req.uri().query().unwrap_or(""));
+    let chunks = query
+        .get("chunks")
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(8usize)
+        .min(MAX_CHUNKS);
+    let prefix = UrlPrefix::of(&req);
+    let path = session_path(&prefix);
+
+    let mut builder = Response::builder()
+        .status(StatusCode::OK)
+        .header("Content-Type", "application/json")
+        .header("Cache-Control", "no-store")
+        .header("Set-Cookie", expire_cookie(CSRF_COOKIE, &csrf_path(&prefix)));
+    for i in 0..chunks {
+        builder = builder.header("Set-Cookie", expire_cookie(&format!("{SESSION_COOKIE}_{i}"), &path));
+    }
+    builder
+        .body(Body::from(json!({"ok": true, "expired_chunks": chunks}).to_string()))
+        .or_500()
+}
@@ -0,0 +1,115 @@
+use hyper::{Body, Request, Response, StatusCode};
+use serde_json::json;
+use std::sync::Arc;
+
+use super::common::{parse_query, OrInternalError};
+use crate::config::ServerConfig;
+use crate::conn::RawConn;
+
+/// A response that hyper refuses to produce, written byte for byte.
+struct RawCase {
+    id: &'static str,
+    description: &'static str,
+    script: &'static [u8],
+}
+
+// Every well-formed body is `raw-ok`, so the client can tell whether the
+// framing survived.
+static RAW_CASES: &[RawCase] = &[
+    RawCase { id: "baseline", description: "Well-formed response (control)", script: b"HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: 6\r\nX-Raw-Case: baseline\r\n\r\nraw-ok" },
+    RawCase { id: "close-delimited", description: "No Content-Length, body ends at close", script: b"HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nConnection: close\r\nX-Raw-Case: close-delimited\r\n\r\nraw-ok" },
+    RawCase { id: "obs-fold", description: "Folded header (obs-fold)", script: b"HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: 6\r\nX-Raw-Case: obs-fold\r\nX-Raw-Value: first\r\n second\r\n\r\nraw-ok" },
+    RawCase { id: "bare-lf", description: "Bare LF line endings throughout", script: b"HTTP/1.1 200 OK\nContent-Type: text/plain\nContent-Length: 6\nX-Raw-Case: bare-lf\n\nraw-ok" },
+    RawCase { id: "bare-lf-header", description: "One header line ending in bare LF", script: b"HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: 6\r\nX-Raw-Case: bare-lf-header\nX-Raw-Value: after-lf\r\n\r\nraw-ok" },
+    RawCase { id: "bare-cr", description: "Bare CR inside a header value", script: b"HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: 6\r\nX-Raw-Case: bare-cr\r\nX-Raw-Value: a\rX-Raw-Injected: yes\r\n\r\nraw-ok" },
+    RawCase { id: "ctl-char", description: "Control character (0x01) in a header value", script: b"HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: 6\r\nX-Raw-Case: ctl-char\r\nX-Raw-Value: a\x01b\r\n\r\nraw-ok" },
+    RawCase { id: "nul", description: "NUL in a header value", script: b"HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: 6\r\nX-Raw-Case: nul\r\nX-Raw-Value: a\x00b\r\n\r\nraw-ok" },
+    RawCase { id: "del", description: "DEL (0x7F) in a header value", script: b"HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: 6\r\nX-Raw-Case: del\r\nX-Raw-Value: a\x7fb\r\n\r\nraw-ok" },
+    RawCase { id: "space-before-colon", description: "Whitespace between field name and colon", script: b"HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: 6\r\nX-Raw-Case: space-before-colon\r\nX-Raw-Value : spaced\r\n\r\nraw-ok" },
+    RawCase { id: "no-colon", description: "Header line without a colon", script: b"HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: 6\r\nX-Raw-Case: no-colon\r\nX-Raw-No-Colon\r\n\r\nraw-ok" },
+    RawCase { id: "dup-cl-same", description: "Duplicate Content-Length, same value", script: b"HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: 6\r\nContent-Length: 6\r\nX-Raw-Case: dup-cl-same\r\n\r\nraw-ok" },
+    RawCase { id: "dup-cl-diff", description: "Duplicate Content-Length, different values", script: b"HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: 6\r\nContent-Length: 3\r\nX-Raw-Case: dup-cl-diff\r\n\r\nraw-ok" },
+    RawCase { id: "cl-list", description: "Content-Length as a list (6, 6)", script: b"HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: 6, 6\r\nX-Raw-Case: cl-list\r\n\r\nraw-ok" },
+    RawCase { id: "cl-plus", description: "Content-Length with a sign (+6)", script: b"HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: +6\r\nX-Raw-Case: cl-plus\r\n\r\nraw-ok" },
+    RawCase { id: "te-cl", description: "Transfer-Encoding: chunked with Content-Length", script: b"HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nTransfer-Encoding: chunked\r\nContent-Length: 3\r\nX-Raw-Case: te-cl\r\n\r\n6\r\nraw-ok\r\n0\r\n\r\n" },
+    RawCase { id: "te-xchunked", description: "Unknown transfer coding (xchunked)", script: b"HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nTransfer-Encoding: xchunked\r\nX-Raw-Case: te-xchunked\r\n\r\n6\r\nraw-ok\r\n0\r\n\r\n" },
+    RawCase { id: "te-space", description: "Whitespace before the colon of Transfer-Encoding", script: b"HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nTransfer-Encoding : chunked\r\nX-Raw-Case: te-space\r\n\r\n6\r\nraw-ok\r\n0\r\n\r\n" },
+    RawCase { id: "te-not-last", description: "chunked is not the final transfer coding", script: b"HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nTransfer-Encoding: chunked, identity\r\nX-Raw-Case: te-not-last\r\n\r\n6\r\nraw-ok\r\n0\r\n\r\n" },
+    RawCase { id: "chunk-hex-prefix", description: "Chunk size with 0x prefix", script: b"HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nTransfer-Encoding: chunked\r\nX-Raw-Case: chunk-hex-prefix\r\n\r\n0x6\r\nraw-ok\r\n0\r\n\r\n" },
+    RawCase { id: "chunk-ext", description: "Chunk extension (valid)", script: b"HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nTransfer-Encoding: chunked\r\nX-Raw-Case: chunk-ext\r\n\r\n6;ext=1\r\nraw-ok\r\n0\r\n\r\n" },
+    RawCase { id: "status-999", description: "Unknown status code (999)", script: b"HTTP/1.1 999 Weird\r\nContent-Type: text/plain\r\nContent-Length: 6\r\nX-Raw-Case: status-999\r\n\r\nraw-ok" },
+    RawCase { id: "status-2digit", description: "Two-digit status code", script: b"HTTP/1.1 20 OK\r\nContent-Type: text/plain\r\nContent-Length: 6\r\nX-Raw-Case: status-2digit\r\n\r\nraw-ok" },
+    RawCase { id: "status-no-reason", description: "Status line without the space before the reason", script: b"HTTP/1.1 200\r\nContent-Type: text/plain\r\nContent-Length: 6\r\nX-Raw-Case: status-no-reason\r\n\r\nraw-ok" },
+    RawCase { id: "version-9-9", description: "Unknown HTTP version (HTTP/9.9)", script: b"HTTP/9.9 200 OK\r\nContent-Type: text/plain\r\nContent-Length: 6\r\nX-Raw-Case: version-9-9\r\n\r\nraw-ok" },
+    RawCase { id: "version-lowercase", description: "Lower-case protocol name (http/1.1)", script: b"http/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: 6\r\nX-Raw-Case: version-lowercase\r\n\r\nraw-ok" },
+    RawCase { id: "http09", description: "No status line at all (HTTP/0.9 style)", script: b"raw-ok" },
+    RawCase { id: "trailing-response", description: "A second response after the first (response queue poisoning)", script: b"HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: 6\r\nX-Raw-Case: trailing-response\r\n\r\nraw-okHTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: 6\r\nX-Raw-Smuggled: yes\r\n\r\nsmuggl" },
+];
+
+fn json_response(status: StatusCode, body: serde_json::Value) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .header("Cache-Control", "no-store")
+        .body(Body::from(body.to_string()))
+        .or_500()
+}
+
+/// GET /raw
+///
+/// Returns the manifest of raw response cases, and whether they are enabled.
+///
+/// GET /raw?case=<id>
+///
+/// Takes over the connection and writes the case's byte script exactly:
+/// folded headers, bare LF, control characters, conflicting framing, invalid
+/// status lines.  The write side is then shut down so hyper cannot add a
+/// response of its own.  Only available with `--raw-responses`, since these
+/// responses can desynchronise the proxy's connection to Sensillum, and only
+/// on HTTP/1.x.
+pub async fn handle_raw(req: Request<Body>, config: Arc<ServerConfig>) -> Response<Body> {
+    let case_id = parse_query(req.uri().query().unwrap_or(""))
+        .get("case")
+        .map(str::to_string);
+
+    let Some(case_id) = case_id else {
+        let cases: Vec<_> = RAW_CASES
+            .iter()
+            .map(|c| {
+                json!({
+                    "id": c.id,
+                    "description": c.description,
+                    "script": String::from_utf8_lossy(c.script).escape_debug().to_string(),
+                })
+            })
+            .collect();
+        return json_response(
+            StatusCode::OK,
+            json!({"enabled": config.raw_responses, "cases": cases}),
+        );
+    };
+
+    if !config.raw_responses {
+        return json_response(
+            StatusCode::FORBIDDEN,
+            json!({"error": "raw responses are disabled; start Sensillum with --raw-responses"}),
+        );
+    }
+    let Some(case) = RAW_CASES.iter().find(|c| c.id == case_id) else {
+        return json_response(StatusCode::NOT_FOUND, json!({"error": "unknown case"}));
+    };
+    let Some(raw) = RawConn::of(&req) else {
+        return json_response(
+            StatusCode::NOT_IMPLEMENTED,
+            json!({"error": "raw responses need an HTTP/1.x connection"}),
+        );
+    };
+
+    if let Err(e) = raw.write_all(case.script).await {
+        eprintln!("Raw response '{}' failed: {}", case.id, e);
+    }
+    let _ = raw.shutdown().await;
+
+    // Never reaches the client: the socket no longer accepts writes.
+    Response::new(Body::empty())
+}
@@ -0,0 +1,117 @@
+use hyper::header::{HeaderName, HeaderValue};
+use hyper::{Body, Request, Response, StatusCode};
+use serde_json::json;
+
+use super::common::{parse_query, OrInternalError};
+
+/// Headers that decide how the response is framed; hyper sets them, and
+/// letting the query override them would break the connection rather than
+/// test the proxy.
+const FRAMING_HEADERS: &[&str] = &["content-length", "transfer-encoding", "connection"];
+
+fn bad_request(message: &str) -> Response<Body> {
+    Response::builder()
+        .status(StatusCode::BAD_REQUEST)
+        .header("Content-Type", "application/json")
+        .body(Body::from(json!({"error": message}).to_string()))
+        .or_500()
+}
+
+/// Serialise `body` with `<`, `>` and `&` escaped as `\u003c`, `\u003e` and
+/// `\u0026`.  The JSON is unchanged, but a browser sniffing it or rendering it
+/// under a query-chosen `Content-Type: text/html` finds no markup to run.
+fn to_safe_json(body: &serde_json::Value) -> String {
+    let json = body.to_string();
+    let mut out = String::with_capacity(json.len());
+    for c in json.chars() {
+        match c {
+            '<' => out.push_str("\\u003c"),
+            '>' => out.push_str("\\u003e"),
+            '&' => out.push_str("\\u0026"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Any method to /response-headers?Name=Value[&Name=Value…][&status=N]
+///
+/// Sends back the query's pairs as response headers, in order and with
+/// repeats kept, and the status from `status` (default 200), like httpbin's
+/// `/response-headers`.  The body lists the headers emitted and any that
+/// were rejected, so the client can diff it against what arrived and see
+/// which ones the proxy stripped, rewrote or added (`Server`,
+/// `X-Powered-By`, HSTS, `Cache-Control`…).  `Content-Type: application/json`
+/// and `X-Content-Type-Options: nosniff` are only added if the query does not
+/// set them itself.  The body escapes `<`, `>` and `&`, so reflected values
+/// cannot become markup whatever `Content-Type` the query picks.
+pub async fn handle_response_headers(req: Request<Body>) -> Response<Body> {
+    let query = parse_query(req.uri().query().unwrap_or(""));
+
+    let status = match query.get("status") {
+        None => StatusCode::OK,
+        Some(s) => match s.parse::<u16>().ok().and_then(|n| StatusCode::from_u16(n).ok()) {
+            // hyper cannot send a 1xx as the final response.
+            Some(code) if !code.is_informational() => code,
+            _ => return bad_request("status must be a number from 200 to 999"),
+        },
+    };
+
+    let mut emitted = Vec::new();
+    let mut rejected = Vec::new();
+    let mut headers = Vec::new();
+    for pair in query.pairs.iter().filter(|p| p.key != "status") {
+        let name = HeaderName::from_bytes(pair.key.as_bytes());
+        let value = HeaderValue::from_str(&pair.value);
+        let reason = match (&name, &value) {
+            (Err(_), _) => Some("invalid header name"),
+            (_, Err(_)) => Some("invalid header value"),
+            (Ok(n), _) if FRAMING_HEADERS.contains(&n.as_str()) => {
+                Some("framing header is set by the server")
+            }
+            _ => None,
+        };
+        match (reason, name, value) {
+            (None, Ok(name), Ok(value)) => {
+                emitted.push(json!({"name": name.as_str(), "value": pair.value}));
+                headers.push((name, value));
+            }
+            (reason, _, _) => rejected.push(json!({
+                "name": pair.key,
+                "value": pair.value,
+                "reason": reason.unwrap_or("invalid header"),
+            })),
+        }
+    }
+
+    let mut builder = Response::builder().status(status);
+    if !headers.iter().any(|(n, _)| n == hyper::header::CONTENT_TYPE) {
+        builder = builder.header("Content-Type", "application/json");
+    }
+    if !headers.iter().any(|(n, _)| n == hyper::header::X_CONTENT_TYPE_OPTIONS) {
+        builder = builder.header("X-Content-Type-Options", "nosniff");
+    }
+    for (name, value) in headers {
+        builder = builder.header(name, value);
+    }
+    let body = json!({
+        "status": status.as_u16(),
+        "headers": emitted,
+        "rejected": rejected,
+    });
+    builder.body(Body::from(to_safe_json(&body))).or_500()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn markup_is_escaped_but_the_json_is_unchanged() {
+        let body = json!({"name": "content-type", "value": "<script>a&&b</script>"});
+        let safe = to_safe_json(&body);
+        assert!(!safe.contains(['<', '>', '&']));
+        assert!(safe.contains(r"\u003cscript\u003ea\u0026\u0026b"));
+        assert_eq!(serde_json::from_str::<serde_json::Value>(&safe).unwrap(), body);
+    }
+}
@@ -0,0 +1,127 @@
+use hyper::{Body, Request, Response, StatusCode};
+use serde_json::json;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use super::common::{node_label, parse_query, OrInternalError};
+use crate::config::ServerConfig;
+use crate::conn::{ConnMeta, RawConn};
+use crate::retry::RetryTracker;
+
+/// Upper bound for `?fail=`; no proxy retries more often than this.
+const MAX_FAILURES: usize = 10;
+
+fn json_response(status: StatusCode, body: serde_json::Value) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .header("Cache-Control", "no-store")
+        .body(Body::from(body.to_string()))
+        .or_500()
+}
+
+/// Any method to /retry?token=T[&fail=N][&mode=status|reset][&status=S]
+///
+/// Fails the first N attempts (default 1) this node sees for token T, then
+/// answers 200 with this node's attempt log.  A failure is status S (default
+/// 503), or with `mode=reset` the connection is aborted with a TCP RST and
+/// no response; resets need HTTP/1.x and fall back to the status on HTTP/2.
+/// A proxy that retries turns one client request into several attempts,
+/// possibly on different nodes, and may answer 200 where the client should
+/// have seen the failure.
+pub async fn handle_retry(
+    req: Request<Body>,
+    client_addr: SocketAddr,
+    config: Arc<ServerConfig>,
+    tracker: Arc<RetryTracker>,
+) -> Response<Body> {
+    let query = parse_query(req.uri().query().unwrap_or(""));
+    let Some(token) = query.get("token").filter(|t| !t.is_empty()).map(str::to_string) else {
+        return json_response(StatusCode::BAD_REQUEST, json!({"error": "token is required"}));
+    };
+    let fail = query
+        .get("fail")
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(1usize)
+        .min(MAX_FAILURES);
+    let status = match query.get("status") {
+        None => StatusCode::SERVICE_UNAVAILABLE,
+        Some(s) => match s.parse::<u16>().ok().and_then(|n| StatusCode::from_u16(n).ok()) {
+            Some(code) if code.is_client_error() || code.is_server_error() => code,
+            _ => {
+                return json_response(
+                    StatusCode::BAD_REQUEST,
+                    json!({"error": "status must be a 4xx or 5xx code"}),
+                )
+            }
+        },
+    };
+    let raw = match query.get("mode") {
+        Some("reset") => RawConn::of(&req),
+        _ => None,
+    };
+
+    let conn = ConnMeta::of(&req);
+    let method = req.method().to_string();
+    let node = node_label(&config);
+    // Read the whole body, so a POST counts as delivered before it fails.
+    let _ = hyper::body::to_bytes(req.into_body()).await;
+
+    let (attempt, outcome) = tracker.attempt(&token, conn, &method, client_addr.to_string(), |n| {
+        match (n <= fail, &raw) {
+            (false, _) => "ok",
+            (true, Some(_)) => "reset",
+            (true, None) => "failed",
+        }
+    });
+    println!(
+        "Retry test {}: {} attempt {} on connection {} — {}",
+        token, method, attempt, conn.id, outcome
+    );
+
+    match (outcome, raw) {
+        ("reset", Some(raw)) => {
+            let _ = raw.reset();
+            // Never reaches the client: hyper's write fails and the socket
+            // is closed with an RST.
+            Response::new(Body::empty())
+        }
+        ("failed", _) => json_response(
+            status,
+            json!({"token": token, "node": node, "attempt": attempt, "failed": true}),
+        ),
+        _ => json_response(
+            StatusCode::OK,
+            json!({
+                "token": token,
+                "node": node,
+                "attempt": attempt,
+                "attempts": tracker.log(&token, node.as_deref()),
+            }),
+        ),
+    }
+}
+
+/// GET /retry/log?token=T
+///
+/// This node's attempts for token T.  Ask repeatedly through the load
+/// balancer to collect the logs of the other nodes.
+pub async fn handle_log(
+    req: Request<Body>,
+    config: Arc<ServerConfig>,
+    tracker: Arc<RetryTracker>,
+) -> Response<Body> {
+    let query = parse_query(req.uri().query().unwrap_or(""));
+    let Some(token) = query.get("token") else {
+        return json_response(StatusCode::BAD_REQUEST, json!({"error": "token is required"}));
+    };
+    let node = node_label(&config);
+    json_response(
+        StatusCode::OK,
+        json!({
+            "token": token,
+            "node": node,
+            "attempts": tracker.log(token, node.as_deref()),
+        }),
+    )
+}
@@ -0,0 +1,58 @@
+use hyper::{Body, Request, Response, StatusCode};
+use serde_json::json;
+
+use super::common::OrInternalError;
+
+/// A security response header and what Sensillum itself sends for it.
+struct BaselineHeader {
+    name: &'static str,
+    description: &'static str,
+    /// `None` means Sensillum deliberately leaves it out, so anything that
+    /// arrives was added by a proxy.
+    value: Option<&'static str>,
+}
+
+static BASELINE: &[BaselineHeader] = &[
+    BaselineHeader { name: "strict-transport-security", description: "HSTS", value: None },
+    BaselineHeader { name: "content-security-policy", description: "Content Security Policy", value: Some("default-src 'none'; frame-ancestors 'none'") },
+    BaselineHeader { name: "x-frame-options", description: "Clickjacking protection", value: Some("SAMEORIGIN") },
+    BaselineHeader { name: "x-content-type-options", description: "MIME sniffing protection", value: None },
+    BaselineHeader { name: "referrer-policy", description: "Referrer Policy", value: Some("strict-origin-when-cross-origin") },
+    BaselineHeader { name: "permissions-policy", description: "Permissions Policy", value: None },
+    BaselineHeader { name: "cross-origin-opener-policy", description: "COOP", value: Some("same-origin") },
+    BaselineHeader { name: "cross-origin-embedder-policy", description: "COEP", value: None },
+    BaselineHeader { name: "cross-origin-resource-policy", description: "CORP", value: None },
+];
+
+/// GET /security-headers
+///
+/// Sends a fixed baseline of security headers, some present and some
+/// deliberately absent, and returns the same baseline in the body.  The client
+/// compares it with what arrived to see which headers the proxy injects,
+/// removes or rewrites.
+pub async fn handle_security_headers(_req: Request<Body>) -> Response<Body> {
+    let baseline: Vec<_> = BASELINE
+        .iter()
+        .map(|h| {
+            json!({
+                "name": h.name,
+                "description": h.description,
+                "sent": h.value.is_some(),
+                "value": h.value,
+            })
+        })
+        .collect();
+
+    let mut builder = Response::builder()
+        .status(StatusCode::OK)
+        .header("Content-Type", "application/json")
+        .header("Cache-Control", "no-store");
+    for h in BASELINE {
+        if let Some(value) = h.value {
+            builder = builder.header(h.name, value);
+        }
+    }
+    builder
+        .body(Body::from(json!({"baseline": baseline}).to_string()))
+        .or_500()
+}
@@ -0,0 +1,85 @@
+use hyper::{Body, Request, Response, StatusCode};
+use serde_json::json;
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+
+use super::common::{parse_query, OrInternalError};
+use crate::conn::RawConn;
+
+/// Upper bound for `?delay_ms=`; proxies rarely wait longer than a few minutes.
+const MAX_SLOW_DELAY_MS: u64 = 600_000;
+
+/// Logs the disconnect if hyper notices the client went away and drops the
+/// handler before it answers.
+struct DisconnectLog {
+    delay_ms: u64,
+    client_addr: SocketAddr,
+    start: Instant,
+    answered: bool,
+}
+
+impl DisconnectLog {
+    fn log(&self) {
+        println!(
+            "Slow response ({} ms) to {}: client disconnected after {} ms",
+            self.delay_ms,
+            self.client_addr,
+            self.start.elapsed().as_millis()
+        );
+    }
+}
+
+impl Drop for DisconnectLog {
+    fn drop(&mut self) {
+        if !self.answered {
+            self.log();
+        }
+    }
+}
+
+/// GET /slow?delay_ms=N
+///
+/// Waits N ms before sending anything, then answers 200.  While waiting it
+/// watches the connection, and logs whether the client (usually the proxy)
+/// was still connected when the answer was ready: a proxy that gave up first
+/// has a read timeout shorter than N.  On HTTP/2 connections this cannot be
+/// observed, and `client_connected` is null.
+pub async fn handle_slow(req: Request<Body>, client_addr: SocketAddr) -> Response<Body> {
+    let delay_ms = parse_query(req.uri().query().unwrap_or(""))
+        .get("delay_ms")
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(0u64)
+        .min(MAX_SLOW_DELAY_MS);
+    let start = Instant::now();
+    let delay = Duration::from_millis(delay_ms);
+    let mut log = DisconnectLog { delay_ms, client_addr, start, answered: false };
+
+    let client_connected = match RawConn::of(&req) {
+        Some(raw) => {
+            let closed = raw.wait_closed(delay).await;
+            if closed {
+                log.log();
+            } else {
+                println!("Slow response ({} ms) to {}: client still connected", delay_ms, client_addr);
+            }
+            Some(!closed)
+        }
+        None => {
+            tokio::time::sleep(delay).await;
+            None
+        }
+    };
+    log.answered = true;
+
+    let body = json!({
+        "delay_ms": delay_ms,
+        "client_connected": client_connected,
+        "elapsed_ms": start.elapsed().as_millis() as u64,
+    });
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("Content-Type", "application/json")
+        .header("Cache-Control", "no-store")
+        .body(Body::from(body.to_string()))
+        .or_500()
+}
@@ -5,7 +5,8 @@ use std::time::Duration;
 use tokio::time::interval;
 
 use crate::config::ServerConfig;
-use super::common::{build_server_info, OrInternalError};
+use crate::otlp::Span;
+use super::common::{build_server_info, OrInternalError, UrlPrefix};
 
 pub async fn handle_sse(
     req: Request<Body>,
@@ -25,14 +26,19 @@ pub async fn handle_sse(
         server_addr,
         config,
         protocol,
+        &UrlPrefix::of(&req),
     );
     
+    // Ends when the client goes away and hyper drops the stream.
+    let session_span = Span::session(&req, "sse");
+
     // Create the SSE stream
     let stream = async_stream::stream! {
+        let _session_span = session_span;
         // Send initial connection info
         let info_event = format!(
             "event: headers\ndata: {}\n\n",
-            server_info.to_string()
+            server_info
         );
         yield Ok::<_, hyper::Error>(info_event);
         
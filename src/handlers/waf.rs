@@ -12,7 +12,7 @@ pub async fn handle_waf(req: Request<Body>) -> Response<Body> {
     let params = parse_query(query);
 
     let name = match params.get("name") {
-        Some(n) => n.clone(),
+        Some(n) => n.to_string(),
         None => {
             return Response::builder()
                 .status(StatusCode::BAD_REQUEST)
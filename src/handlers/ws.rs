@@ -8,7 +8,8 @@ use std::time::Duration;
 use std::sync::Arc;
 
 use crate::config::ServerConfig;
-use super::common::{build_server_info, OrInternalError};
+use crate::otlp::Span;
+use super::common::{build_server_info, OrInternalError, UrlPrefix};
 
 /// Returns false only when an Origin header is present and its host[:port]
 /// does not match the Host header — i.e. an explicit cross-origin browser request.
@@ -72,6 +73,7 @@ pub async fn handle_ws_upgrade(
     
     // Get HTTP protocol version
     let protocol = format!("{:?}", req.version());
+    let url_prefix = UrlPrefix::of(&req);
 
     let headers_for_task = if origin_mismatch {
         hyper::HeaderMap::new()
@@ -79,8 +81,11 @@ pub async fn handle_ws_upgrade(
         headers.clone()
     };
 
+    let session_span = Span::session(&req, "websocket");
+
     // Spawn task to handle the WebSocket connection
     tokio::spawn(async move {
+        let _session_span = session_span;
         // Wait for the connection to be upgraded
         match hyper::upgrade::on(req).await {
             Ok(upgraded) => {
@@ -96,6 +101,7 @@ pub async fn handle_ws_upgrade(
                     server_addr,
                     config.clone(),
                     protocol.clone(),
+                    &url_prefix,
                 );
 
                 if origin_mismatch {
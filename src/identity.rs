@@ -0,0 +1,181 @@
+use serde_json::{json, Value};
+use std::path::Path;
+
+/// Where a downward-API volume is mounted by default; override with
+/// `SENSILLUM_PODINFO_DIR`.
+const DEFAULT_PODINFO_DIR: &str = "/etc/podinfo";
+
+/// Namespace file of the mounted service-account token.
+const SERVICE_ACCOUNT_NAMESPACE: &str = "/var/run/secrets/kubernetes.io/serviceaccount/namespace";
+
+/// Placeholders accepted in `--node-template`.
+const TEMPLATE_FIELDS: &[&str] = &["pod", "namespace", "node", "pod_ip", "container", "hostname"];
+
+/// Where Sensillum runs, as far as Kubernetes and the container runtime
+/// reveal it.  Every field is optional; outside a cluster usually only
+/// `container_id` (or nothing) is found.
+#[derive(Clone, Default)]
+pub struct Identity {
+    pub pod: Option<String>,
+    pub namespace: Option<String>,
+    /// The Kubernetes node, not Sensillum's `--node` name.
+    pub node: Option<String>,
+    pub pod_ip: Option<String>,
+    /// Short (12-character) container ID, as `docker ps` shows it.
+    pub container_id: Option<String>,
+}
+
+/// The first non-empty value among `vars`, then the downward-API file named
+/// after the first of them, lower-cased (`POD_NAME` → `pod_name`).
+fn downward(vars: &[&str], podinfo: &Path) -> Option<String> {
+    vars.iter()
+        .filter_map(|v| std::env::var(v).ok())
+        .chain(std::fs::read_to_string(podinfo.join(vars[0].to_lowercase())).ok())
+        .map(|v| v.trim().to_string())
+        .find(|v| !v.is_empty())
+}
+
+/// A 64-hex-digit container ID in a cgroup path or mount source, such as
+/// `/kubepods/…/<id>`, `docker-<id>.scope` or `<id>` itself.
+fn find_container_id(text: &str) -> Option<String> {
+    text.split(|c: char| !c.is_ascii_hexdigit())
+        .find(|word| word.len() == 64)
+        .map(|id| id[..12].to_lowercase())
+}
+
+impl Identity {
+    pub fn detect(hostname: &str) -> Identity {
+        let podinfo = std::env::var("SENSILLUM_PODINFO_DIR").unwrap_or_else(|_| DEFAULT_PODINFO_DIR.to_string());
+        let podinfo = Path::new(&podinfo);
+        let in_cluster = std::env::var("KUBERNETES_SERVICE_HOST").is_ok();
+
+        let pod = downward(&["POD_NAME", "MY_POD_NAME", "K8S_POD_NAME"], podinfo)
+            // A pod's hostname is its name unless the spec overrides it.
+            .or_else(|| in_cluster.then(|| hostname.to_string()));
+        let namespace = downward(&["POD_NAMESPACE", "MY_POD_NAMESPACE", "K8S_NAMESPACE"], podinfo).or_else(|| {
+            std::fs::read_to_string(SERVICE_ACCOUNT_NAMESPACE)
+                .ok()
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty())
+        });
+        let node = downward(&["NODE_NAME", "MY_NODE_NAME", "K8S_NODE_NAME"], podinfo);
+        let pod_ip = downward(&["POD_IP", "MY_POD_IP", "K8S_POD_IP"], podinfo);
+
+        // cgroup v1 paths carry the ID; under cgroup v2 the path is usually
+        // just `/`, but Docker's bind mounts of /etc/hostname and friends
+        // still name the container.  Overlay layer directories are 64 hex
+        // digits too, so mountinfo is only searched under `/containers/`.
+        let container_id = std::fs::read_to_string("/proc/self/cgroup")
+            .ok()
+            .and_then(|text| {
+                text.lines()
+                    .filter(|l| ["kubepods", "docker", "containerd", "crio", "libpod"].iter().any(|k| l.contains(k)))
+                    .find_map(find_container_id)
+            })
+            .or_else(|| {
+                let text = std::fs::read_to_string("/proc/self/mountinfo").ok()?;
+                text.lines()
+                    .filter_map(|l| l.split_once("/containers/").map(|(_, rest)| rest))
+                    .find_map(|rest| find_container_id(rest.split('/').next().unwrap_or("")))
+            });
+
+        Identity { pod, namespace, node, pod_ip, container_id }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pod.is_none()
+            && self.namespace.is_none()
+            && self.node.is_none()
+            && self.pod_ip.is_none()
+            && self.container_id.is_none()
+    }
+
+    fn field(&self, name: &str, hostname: &str) -> Option<String> {
+        match name {
+            "pod" => self.pod.clone(),
+            "namespace" => self.namespace.clone(),
+            "node" => self.node.clone(),
+            "pod_ip" => self.pod_ip.clone(),
+            "container" => self.container_id.clone(),
+            "hostname" => Some(hostname.to_string()),
+            _ => None,
+        }
+    }
+
+    /// Expand a node-name template such as `{namespace}/{pod}`.  Fields that
+    /// were not detected become `unknown`; unknown placeholders are an error.
+    pub fn expand(&self, template: &str, hostname: &str) -> Result<String, String> {
+        let mut out = String::new();
+        let mut rest = template;
+        while let Some(open) = rest.find('{') {
+            out.push_str(&rest[..open]);
+            let close = rest[open..]
+                .find('}')
+                .ok_or_else(|| format!("unclosed '{{' in node template '{template}'"))?;
+            let name = &rest[open + 1..open + close];
+            if !TEMPLATE_FIELDS.contains(&name) {
+                return Err(format!(
+                    "unknown placeholder '{{{name}}}' in node template; use {}",
+                    TEMPLATE_FIELDS.iter().map(|f| format!("{{{f}}}")).collect::<Vec<_>>().join(", ")
+                ));
+            }
+            out.push_str(&self.field(name, hostname).unwrap_or_else(|| "unknown".to_string()));
+            rest = &rest[open + close + 1..];
+        }
+        out.push_str(rest);
+        Ok(out)
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "pod": self.pod,
+            "namespace": self.namespace,
+            "node": self.node,
+            "pod_ip": self.pod_ip,
+            "container_id": self.container_id,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn identity() -> Identity {
+        Identity {
+            pod: Some("web-7d9f".to_string()),
+            namespace: Some("shop".to_string()),
+            container_id: Some("0123456789ab".to_string()),
+            ..Identity::default()
+        }
+    }
+
+    #[test]
+    fn expands_placeholders() {
+        let id = identity();
+        assert_eq!(id.expand("{namespace}/{pod}", "host").unwrap(), "shop/web-7d9f");
+        assert_eq!(id.expand("{hostname}-{container}", "host").unwrap(), "host-0123456789ab");
+        assert_eq!(id.expand("node {node} ip {pod_ip}", "host").unwrap(), "node unknown ip unknown");
+        assert_eq!(id.expand("plain", "host").unwrap(), "plain");
+    }
+
+    #[test]
+    fn rejects_bad_templates() {
+        let id = identity();
+        assert_eq!(id.expand("{pod", "host").unwrap_err(), "unclosed '{' in node template '{pod'");
+        let err = id.expand("{cluster}", "host").unwrap_err();
+        assert!(err.starts_with("unknown placeholder '{cluster}'"), "{err}");
+        assert!(id.expand("{}", "host").is_err());
+    }
+
+    #[test]
+    fn container_id_from_cgroup_or_mount() {
+        let id = "a".repeat(12) + &"b".repeat(52);
+        assert_eq!(
+            find_container_id(&format!("0::/system.slice/docker-{id}.scope")).as_deref(),
+            Some("aaaaaaaaaaaa")
+        );
+        assert_eq!(find_container_id(&format!("/kubepods/burstable/pod1/{id}")).as_deref(), Some("aaaaaaaaaaaa"));
+        assert_eq!(find_container_id("/kubepods/burstable/pod1/abc123"), None);
+    }
+}
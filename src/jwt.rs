@@ -0,0 +1,266 @@
+use hyper::HeaderMap;
+use serde_json::{json, Value};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::handlers::common::base64_decode;
+
+/// Tokens larger than this are flagged: a browser cannot store a bigger
+/// cookie, and a few of them fill the 8 KB header limit common in proxies.
+const OVERSIZED_TOKEN: usize = 4096;
+
+/// Unix seconds as ISO 8601 (UTC).
+fn iso_time(secs: i64) -> String {
+    let days = secs.div_euclid(86400);
+    let rem = secs.rem_euclid(86400);
+    // Civil date from days since 1970-01-01 (proleptic Gregorian).
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    let y = yoe + era * 400 + if m <= 2 { 1 } else { 0 };
+    format!(
+        "{y:04}-{m:02}-{d:02}T{:02}:{:02}:{:02}Z",
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
+
+fn decode_segment(segment: &str) -> Option<Value> {
+    let bytes = base64_decode(segment).ok()?;
+    serde_json::from_slice::<Value>(&bytes).ok().filter(Value::is_object)
+}
+
+/// Decode `token` if it is a JWS (three segments) or JWE (five) whose header
+/// is a JSON object with `alg`.  Nothing is verified.
+fn decode(token: &str, now: i64) -> Option<Value> {
+    let segments: Vec<&str> = token.split('.').collect();
+    if segments.len() != 3 && segments.len() != 5 {
+        return None;
+    }
+    let header = decode_segment(segments[0]).filter(|h| h["alg"].is_string())?;
+    let mut warnings = Vec::new();
+    if token.len() > OVERSIZED_TOKEN {
+        warnings.push(format!("{} bytes — larger than a browser cookie can hold ({} bytes)", token.len(), OVERSIZED_TOKEN));
+    }
+
+    // Encrypted tokens (JWE) only reveal their header.
+    if segments.len() == 5 {
+        return Some(json!({
+            "size": token.len(),
+            "encrypted": true,
+            "header": header,
+            "warnings": warnings,
+        }));
+    }
+
+    let claims = decode_segment(segments[1]);
+    if claims.is_none() {
+        warnings.push("the payload is not a JSON object".to_string());
+    }
+    let claims = claims.unwrap_or_else(|| json!({}));
+    if header["alg"].as_str().is_some_and(|a| a.eq_ignore_ascii_case("none")) {
+        warnings.push("unsigned (alg: none)".to_string());
+    }
+    let time = |name: &str| claims[name].as_f64().map(|t| t as i64);
+    let (exp, nbf, iat) = (time("exp"), time("nbf"), time("iat"));
+    if let Some(exp) = exp.filter(|&e| e <= now) {
+        warnings.push(format!("expired {} s ago", now - exp));
+    }
+    if let Some(nbf) = nbf.filter(|&n| n > now) {
+        warnings.push(format!("not valid for another {} s", nbf - now));
+    }
+
+    Some(json!({
+        "size": token.len(),
+        "encrypted": false,
+        "header": header,
+        "claims": claims,
+        "alg": header["alg"],
+        "issuer": claims["iss"],
+        "subject": claims["sub"],
+        "audience": claims["aud"],
+        "expires_at": exp.map(iso_time),
+        "expires_in": exp.map(|e| e - now),
+        "issued_at": iat.map(iso_time),
+        "not_before": nbf.map(iso_time),
+        "warnings": warnings,
+    }))
+}
+
+/// Where a header value may carry a token: `Bearer <token>` or the bare token.
+fn token_in(value: &str) -> &str {
+    let value = value.trim();
+    match value.split_once(' ') {
+        Some((scheme, rest)) if scheme.eq_ignore_ascii_case("bearer") => rest.trim(),
+        _ => value,
+    }
+}
+
+/// JWTs found in `Authorization`, in cookies and in `jwt_headers`, with
+/// their header and claims decoded (not verified).  For redacted headers
+/// only the size is reported.
+pub fn tokens_json(headers: &HeaderMap, jwt_headers: &[String], redact_prefixes: &[String]) -> Value {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+    let redacted = |name: &str| redact_prefixes.iter().any(|p| name.starts_with(p.as_str()));
+
+    // (source label, header name, candidate token)
+    let mut candidates: Vec<(String, &str, String)> = Vec::new();
+    let names = std::iter::once("authorization").chain(jwt_headers.iter().map(String::as_str));
+    for name in names {
+        for value in headers.get_all(name).iter() {
+            let value = String::from_utf8_lossy(value.as_bytes());
+            candidates.push((name.to_string(), name, token_in(&value).to_string()));
+        }
+    }
+    for value in headers.get_all("cookie").iter() {
+        let value = String::from_utf8_lossy(value.as_bytes());
+        for pair in value.split(';') {
+            if let Some((cookie, token)) = pair.trim().split_once('=') {
+                candidates.push((format!("cookie {cookie}"), "cookie", token.trim_matches('"').to_string()));
+            }
+        }
+    }
+
+    let tokens: Vec<Value> = candidates
+        .iter()
+        .filter_map(|(source, header, token)| {
+            let mut decoded = decode(token, now)?;
+            let mut source = source.as_str();
+            if redacted(header) {
+                // Only what the size alone reveals; not even the cookie name.
+                let warnings: Vec<String> = (token.len() > OVERSIZED_TOKEN)
+                    .then(|| format!("{} bytes — larger than a browser cookie can hold", token.len()))
+                    .into_iter()
+                    .collect();
+                decoded = json!({"size": token.len(), "redacted": true, "warnings": warnings});
+                source = header;
+            }
+            decoded["source"] = json!(source);
+            Some(decoded)
+        })
+        .collect();
+    Value::Array(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::headers;
+
+    const NOW: i64 = 1_700_000_000;
+
+    fn b64url(bytes: &[u8]) -> String {
+        const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+        let mut out = String::new();
+        for chunk in bytes.chunks(3) {
+            let n = chunk.iter().fold(0u32, |acc, b| (acc << 8) | *b as u32) << (8 * (3 - chunk.len()));
+            for i in 0..=chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            }
+        }
+        out
+    }
+
+    fn jwt(header: Value, claims: Value) -> String {
+        format!("{}.{}.c2ln", b64url(header.to_string().as_bytes()), b64url(claims.to_string().as_bytes()))
+    }
+
+    #[test]
+    fn redacted_cookie_hides_its_name() {
+        let token = jwt(json!({"alg": "HS256"}), json!({"sub": "alice"}));
+        let cookie = format!("session_alice={token}; theme=dark");
+        let map = headers(&[("cookie", &cookie)]);
+
+        let tokens = tokens_json(&map, &[], &[]);
+        assert_eq!(tokens[0]["source"], "cookie session_alice");
+        assert_eq!(tokens[0]["subject"], "alice");
+
+        let tokens = tokens_json(&map, &[], &["cookie".to_string()]);
+        assert_eq!(tokens.as_array().unwrap().len(), 1);
+        assert_eq!(tokens[0]["source"], "cookie");
+        assert_eq!(tokens[0]["redacted"], true);
+        assert!(tokens[0]["claims"].is_null());
+    }
+
+    #[test]
+    fn decodes_claims_and_times() {
+        let token = jwt(
+            json!({"alg": "RS256", "kid": "k1"}),
+            json!({"iss": "https://idp.example", "sub": "alice", "aud": ["web"], "iat": NOW - 60, "exp": NOW + 3600}),
+        );
+        let decoded = decode(&token, NOW).unwrap();
+        assert_eq!(decoded["encrypted"], false);
+        assert_eq!(decoded["alg"], "RS256");
+        assert_eq!(decoded["header"]["kid"], "k1");
+        assert_eq!(decoded["issuer"], "https://idp.example");
+        assert_eq!(decoded["audience"], json!(["web"]));
+        assert_eq!(decoded["expires_in"], 3600);
+        assert_eq!(decoded["expires_at"], "2023-11-14T23:13:20Z");
+        assert_eq!(decoded["issued_at"], "2023-11-14T22:12:20Z");
+        assert_eq!(decoded["warnings"], json!([]));
+    }
+
+    #[test]
+    fn warnings() {
+        let warnings = |header: Value, claims: Value| decode(&jwt(header, claims), NOW).unwrap()["warnings"].clone();
+        assert_eq!(warnings(json!({"alg": "HS256"}), json!({"exp": NOW - 5})), json!(["expired 5 s ago"]));
+        assert_eq!(warnings(json!({"alg": "HS256"}), json!({"nbf": NOW + 10})), json!(["not valid for another 10 s"]));
+        assert_eq!(warnings(json!({"alg": "none"}), json!({})), json!(["unsigned (alg: none)"]));
+
+        let header = b64url(br#"{"alg":"HS256"}"#);
+        let decoded = decode(&format!("{header}.{}.sig", b64url(b"[1]")), NOW).unwrap();
+        assert_eq!(decoded["warnings"], json!(["the payload is not a JSON object"]));
+
+        let big = jwt(json!({"alg": "HS256"}), json!({"pad": "x".repeat(OVERSIZED_TOKEN)}));
+        let decoded = decode(&big, NOW).unwrap();
+        assert!(decoded["warnings"][0].as_str().unwrap().contains("larger than a browser cookie"));
+    }
+
+    #[test]
+    fn encrypted_tokens_only_show_the_header() {
+        let header = b64url(br#"{"alg":"RSA-OAEP","enc":"A256GCM"}"#);
+        let decoded = decode(&format!("{header}.a.b.c.d"), NOW).unwrap();
+        assert_eq!(decoded["encrypted"], true);
+        assert_eq!(decoded["header"]["enc"], "A256GCM");
+        assert!(decoded["claims"].is_null());
+    }
+
+    #[test]
+    fn ignores_what_is_not_a_jwt() {
+        assert!(decode("opaque-session-id", NOW).is_none());
+        assert!(decode("a.b.c", NOW).is_none());
+        assert!(decode("a.b.c.d", NOW).is_none());
+        // A JSON header without `alg`.
+        let header = b64url(br#"{"typ":"JWT"}"#);
+        assert!(decode(&format!("{header}.e30.sig"), NOW).is_none());
+    }
+
+    #[test]
+    fn finds_tokens_in_headers() {
+        let token = jwt(json!({"alg": "HS256"}), json!({"sub": "bob"}));
+        let bearer = format!("Bearer {token}");
+        let map = headers(&[("authorization", &bearer), ("x-id-token", &token), ("x-other", &token)]);
+        let tokens = tokens_json(&map, &["x-id-token".to_string()], &[]);
+        let sources: Vec<&str> = tokens.as_array().unwrap().iter().map(|t| t["source"].as_str().unwrap()).collect();
+        assert_eq!(sources, ["authorization", "x-id-token"]);
+
+        let tokens = tokens_json(&map, &[], &["authorization".to_string()]);
+        assert_eq!(tokens[0]["source"], "authorization");
+        assert_eq!(tokens[0]["redacted"], true);
+    }
+
+    #[test]
+    fn iso_times() {
+        assert_eq!(iso_time(0), "1970-01-01T00:00:00Z");
+        assert_eq!(iso_time(951_782_400), "2000-02-29T00:00:00Z");
+        assert_eq!(iso_time(-1), "1969-12-31T23:59:59Z");
+    }
+}
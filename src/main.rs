@@ -1,7 +1,23 @@
 mod build_info;
+mod client_cert;
 mod config;
+mod conn;
+mod desync;
+mod desync_probe;
+mod external_url;
+mod fingerprint;
+mod forward_auth;
+mod forwarded;
 mod handlers;
+mod identity;
+mod jwt;
+mod otlp;
+mod retry;
 mod server;
+#[cfg(test)]
+mod test_support;
+mod trace;
+mod via;
 
 use config::parse_config;
 use server::run_server;
@@ -28,10 +44,44 @@ Sensillum {}
 
     let config = Arc::new(parse_config());
 
+    if let Some(url) = &config.desync_probe {
+        match desync_probe::run_probe(url).await {
+            Ok(false) => std::process::exit(0),
+            Ok(true) => std::process::exit(2),
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    if !config.identity.is_empty() {
+        let id = &config.identity;
+        let fields = [("pod", &id.pod), ("namespace", &id.namespace), ("node", &id.node), ("pod IP", &id.pod_ip), ("container", &id.container_id)];
+        let found: Vec<String> = fields
+            .iter()
+            .filter_map(|(label, v)| v.as_ref().map(|v| format!("{label} {v}")))
+            .collect();
+        println!("Running in {}", found.join(", "));
+    }
+
     if config.privacy_mode {
         println!("Privacy mode enabled: server_addr, hostname, build_time and url_prefix will not be sent to clients.");
     }
 
+    if config.raw_responses {
+        println!("Raw responses enabled: /raw will send malformed responses that may desynchronise proxy connections.");
+    }
+
+    if let Some(url) = &config.otlp_endpoint {
+        println!("OTLP export enabled: request spans are sent to {}", url);
+    }
+
+    if !config.trusted_proxies.is_empty() {
+        let list: Vec<String> = config.trusted_proxies.iter().map(|c| c.to_string()).collect();
+        println!("Trusted proxies: {}", list.join(", "));
+    }
+
     if let Err(e) = run_server(config).await {
         eprintln!("Server error: {}", e);
         std::process::exit(1);
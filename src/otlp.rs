@@ -0,0 +1,453 @@
+use hyper::client::HttpConnector;
+use hyper::{Body, Client, Method, Request, Uri};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::build_info;
+use crate::config::ServerConfig;
+use crate::trace;
+
+/// How often buffered spans are sent to the collector.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How long one export may take before it counts as failed.
+const EXPORT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Spans held for the next flush, including those kept back while the
+/// collector is unreachable; newer ones are dropped.
+const MAX_PENDING: usize = 4096;
+
+/// OTLP span kinds (opentelemetry-proto `Span.SpanKind`).
+const SPAN_KIND_INTERNAL: u8 = 1;
+const SPAN_KIND_SERVER: u8 = 2;
+
+/// Sends spans to an OTLP/HTTP collector as JSON (`POST {endpoint}/v1/traces`).
+pub struct Exporter {
+    endpoint: Uri,
+    resource: Value,
+    node: String,
+    client: Client<HttpConnector>,
+    pending: Mutex<Vec<Value>>,
+    dropped: AtomicU64,
+    failing: AtomicBool,
+}
+
+/// The route a request matched, set by the router on the response so the
+/// request span can report it as `http.route`.
+#[derive(Clone)]
+pub struct Route(pub String);
+
+/// The request span's trace, kept in the request extensions so handlers
+/// can start child spans for WebSocket and SSE sessions.
+#[derive(Clone)]
+pub struct TraceCtx {
+    exporter: Arc<Exporter>,
+    trace_id: String,
+    span_id: String,
+    trace_state: Option<String>,
+    /// The caller's sampling decision; unsampled traces are not exported.
+    sampled: bool,
+}
+
+fn now_ns() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0)
+}
+
+/// `bytes` random-looking bytes as hex.  Trace and span IDs only need to be
+/// unique, so a hash of the time, the process and a counter will do.
+fn new_id(bytes: usize) -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+    let digest = Sha256::digest(format!("{}:{}:{}", now_ns(), std::process::id(), n));
+    digest.iter().take(bytes).map(|b| format!("{:02x}", b)).collect()
+}
+
+fn attribute(key: &str, value: &Value) -> Value {
+    let value = match value {
+        Value::Bool(b) => json!({"boolValue": b}),
+        // OTLP/JSON encodes 64-bit integers as strings.
+        Value::Number(n) if n.is_i64() || n.is_u64() => json!({"intValue": n.to_string()}),
+        Value::Number(n) => json!({"doubleValue": n}),
+        Value::String(s) => json!({"stringValue": s}),
+        other => json!({"stringValue": other.to_string()}),
+    };
+    json!({"key": key, "value": value})
+}
+
+impl Exporter {
+    /// Create the exporter and start its flush task.  `endpoint` is the
+    /// collector's base URL; `/v1/traces` is appended unless already there.
+    pub fn start(endpoint: &str, config: &ServerConfig) -> Arc<Exporter> {
+        let endpoint = endpoint.trim_end_matches('/');
+        let endpoint = if endpoint.ends_with("/v1/traces") {
+            endpoint.to_string()
+        } else {
+            format!("{endpoint}/v1/traces")
+        };
+        let node = config.node_name.clone().unwrap_or_else(|| config.hostname.clone());
+        let resource = json!({
+            "attributes": [
+                attribute("service.name", &json!("sensillum")),
+                attribute("service.version", &json!(build_info::version())),
+                attribute("service.instance.id", &json!(node)),
+                attribute("host.name", &json!(config.hostname)),
+            ],
+        });
+        let exporter = Arc::new(Exporter {
+            // Validated by parse_config.
+            endpoint: endpoint.parse().expect("valid OTLP endpoint"),
+            resource,
+            node,
+            client: Client::new(),
+            pending: Mutex::new(Vec::new()),
+            dropped: AtomicU64::new(0),
+            failing: AtomicBool::new(false),
+        });
+
+        let flusher = exporter.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(FLUSH_INTERVAL);
+            loop {
+                interval.tick().await;
+                flusher.flush().await;
+            }
+        });
+        exporter
+    }
+
+    fn record(&self, span: Value) {
+        let mut pending = self.pending.lock().unwrap();
+        if pending.len() < MAX_PENDING {
+            pending.push(span);
+        } else {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Put a batch the collector did not take back in front of the spans
+    /// recorded since, dropping the newest beyond `MAX_PENDING`.
+    fn requeue(&self, mut spans: Vec<Value>) {
+        let mut pending = self.pending.lock().unwrap();
+        spans.append(&mut pending);
+        if spans.len() > MAX_PENDING {
+            let excess = spans.len() - MAX_PENDING;
+            spans.truncate(MAX_PENDING);
+            self.dropped.fetch_add(excess as u64, Ordering::Relaxed);
+        }
+        *pending = spans;
+    }
+
+    async fn flush(&self) {
+        let spans = std::mem::take(&mut *self.pending.lock().unwrap());
+        let dropped = self.dropped.swap(0, Ordering::Relaxed);
+        if dropped > 0 {
+            eprintln!("OTLP export: dropped {} spans, more than {} were waiting to be sent", dropped, MAX_PENDING);
+        }
+        if spans.is_empty() {
+            return;
+        }
+        let count = spans.len();
+        let body = json!({
+            "resourceSpans": [{
+                "resource": self.resource,
+                "scopeSpans": [{
+                    "scope": {"name": "sensillum", "version": build_info::version()},
+                    "spans": &spans,
+                }],
+            }],
+        });
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri(self.endpoint.clone())
+            .header("Content-Type", "application/json")
+            .body(Body::from(body.to_string()));
+        // Unreachable, timed out or temporarily failing collectors get the
+        // batch again next time; one that rejects it would reject it again.
+        let result = match request {
+            Ok(request) => match tokio::time::timeout(EXPORT_TIMEOUT, self.client.request(request)).await {
+                Ok(Ok(response)) if response.status().is_success() => Ok(()),
+                Ok(Ok(response)) => {
+                    let status = response.status();
+                    let retry = status.is_server_error() || status == hyper::StatusCode::TOO_MANY_REQUESTS;
+                    Err((format!("collector answered {}", status), retry))
+                }
+                Ok(Err(e)) => Err((e.to_string(), true)),
+                Err(_) => Err((format!("no answer within {}s", EXPORT_TIMEOUT.as_secs()), true)),
+            },
+            Err(e) => Err((e.to_string(), false)),
+        };
+        // Log when the collector becomes unreachable and when it recovers,
+        // not on every flush.
+        match result {
+            Ok(()) => {
+                if self.failing.swap(false, Ordering::Relaxed) {
+                    println!("OTLP export to {} recovered", self.endpoint);
+                }
+            }
+            Err((e, retry)) => {
+                let outcome = if retry { "kept for the next attempt" } else { "discarded" };
+                if !self.failing.swap(true, Ordering::Relaxed) {
+                    eprintln!("OTLP export to {} failed ({} spans {}): {}", self.endpoint, count, outcome, e);
+                }
+                if retry {
+                    self.requeue(spans);
+                }
+            }
+        }
+    }
+}
+
+/// A span in progress.  It is exported when dropped, so a request whose
+/// handler hyper abandons, or an SSE stream the client closes, still ends
+/// its span.
+pub struct Span {
+    ctx: TraceCtx,
+    parent_span_id: Option<String>,
+    name: String,
+    kind: u8,
+    start_ns: u64,
+    start: Instant,
+    attributes: Vec<(&'static str, Value)>,
+    error: bool,
+}
+
+impl Span {
+    /// The server span for `req`, joining the caller's trace if it sent a
+    /// valid `traceparent`.  Records the trace in the request extensions.
+    pub fn request(exporter: &Arc<Exporter>, req: &mut Request<Body>, client_addr: SocketAddr) -> Span {
+        let (trace_id, parent_span_id, sampled, trace_state) = match trace::incoming_parent(req.headers()) {
+            Some((trace_id, span_id, sampled)) => {
+                let state = req
+                    .headers()
+                    .get("tracestate")
+                    .and_then(|v| v.to_str().ok())
+                    .map(str::to_string);
+                (trace_id, Some(span_id), sampled, state)
+            }
+            None => (new_id(16), None, true, None),
+        };
+        let ctx = TraceCtx {
+            exporter: exporter.clone(),
+            trace_id,
+            span_id: new_id(8),
+            trace_state,
+            sampled,
+        };
+        req.extensions_mut().insert(ctx.clone());
+
+        let version = match req.version() {
+            hyper::Version::HTTP_09 => "0.9",
+            hyper::Version::HTTP_10 => "1.0",
+            hyper::Version::HTTP_2 => "2",
+            hyper::Version::HTTP_3 => "3",
+            _ => "1.1",
+        };
+        let attributes = vec![
+            ("http.request.method", json!(req.method().as_str())),
+            ("url.path", json!(req.uri().path())),
+            ("network.protocol.name", json!("http")),
+            ("network.protocol.version", json!(version)),
+            ("network.peer.address", json!(client_addr.ip().to_string())),
+            ("sensillum.node", json!(exporter.node)),
+        ];
+        Span {
+            ctx,
+            parent_span_id,
+            name: req.method().to_string(),
+            kind: SPAN_KIND_SERVER,
+            start_ns: now_ns(),
+            start: Instant::now(),
+            attributes,
+            error: false,
+        }
+    }
+
+    /// A child of the request span for a long-lived session on `req`
+    /// (`websocket`, `sse`), or None when export is off.
+    pub fn session(req: &Request<Body>, kind: &str) -> Option<Span> {
+        let parent = req.extensions().get::<TraceCtx>()?.clone();
+        let ctx = TraceCtx { span_id: new_id(8), ..parent.clone() };
+        Some(Span {
+            ctx,
+            parent_span_id: Some(parent.span_id),
+            name: format!("{kind} session"),
+            kind: SPAN_KIND_INTERNAL,
+            start_ns: now_ns(),
+            start: Instant::now(),
+            attributes: vec![
+                ("sensillum.session", json!(kind)),
+                ("url.path", json!(req.uri().path())),
+                ("sensillum.node", json!(parent.exporter.node)),
+            ],
+            error: false,
+        })
+    }
+
+    pub fn set(&mut self, key: &'static str, value: Value) {
+        self.attributes.push((key, value));
+    }
+
+    /// Name the span after the matched route and record the response status.
+    pub fn finish_response(&mut self, route: Option<&Route>, status: hyper::StatusCode) {
+        if let Some(Route(route)) = route {
+            self.name = format!("{} {}", self.name, route);
+            self.set("http.route", json!(route));
+        }
+        self.set("http.response.status_code", json!(status.as_u16()));
+        self.error = status.is_server_error();
+    }
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        if !self.ctx.sampled {
+            return;
+        }
+        let duration = self.start.elapsed();
+        let mut attributes: Vec<Value> = self.attributes.iter().map(|(k, v)| attribute(k, v)).collect();
+        attributes.push(attribute("sensillum.duration_ms", &json!(duration.as_millis() as u64)));
+        let mut span = json!({
+            "traceId": self.ctx.trace_id,
+            "spanId": self.ctx.span_id,
+            "name": self.name,
+            "kind": self.kind,
+            "startTimeUnixNano": self.start_ns.to_string(),
+            "endTimeUnixNano": (self.start_ns + duration.as_nanos() as u64).to_string(),
+            "attributes": attributes,
+            // STATUS_CODE_ERROR, or STATUS_CODE_UNSET
+            "status": {"code": if self.error { 2 } else { 0 }},
+        });
+        if let Some(parent) = &self.parent_span_id {
+            span["parentSpanId"] = json!(parent);
+        }
+        if let Some(state) = &self.ctx.trace_state {
+            span["traceState"] = json!(state);
+        }
+        self.ctx.exporter.record(span);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRACE_ID: &str = "4bf92f3577b34da6a3ce929d0e0e4736";
+    const SPAN_ID: &str = "00f067aa0ba902b7";
+
+    fn exporter() -> Arc<Exporter> {
+        Arc::new(Exporter {
+            endpoint: "http://collector:4318/v1/traces".parse().unwrap(),
+            resource: json!({}),
+            node: "node-a".to_string(),
+            client: Client::new(),
+            pending: Mutex::new(Vec::new()),
+            dropped: AtomicU64::new(0),
+            failing: AtomicBool::new(false),
+        })
+    }
+
+    fn peer() -> SocketAddr {
+        "192.0.2.1:40000".parse().unwrap()
+    }
+
+    #[test]
+    fn attribute_encoding() {
+        assert_eq!(attribute("n", &json!(42))["value"], json!({"intValue": "42"}));
+        assert_eq!(attribute("n", &json!(u64::MAX))["value"], json!({"intValue": "18446744073709551615"}));
+        assert_eq!(attribute("n", &json!(-1))["value"], json!({"intValue": "-1"}));
+        assert_eq!(attribute("f", &json!(1.5))["value"], json!({"doubleValue": 1.5}));
+        assert_eq!(attribute("b", &json!(true))["value"], json!({"boolValue": true}));
+        assert_eq!(attribute("s", &json!("GET"))["value"], json!({"stringValue": "GET"}));
+        assert_eq!(attribute("a", &json!([1, 2]))["value"], json!({"stringValue": "[1,2]"}));
+        assert_eq!(attribute("s", &json!("x"))["key"], "s");
+    }
+
+    #[test]
+    fn requeued_batch_goes_before_newer_spans() {
+        let e = exporter();
+        e.record(json!("c"));
+        e.requeue(vec![json!("a"), json!("b")]);
+        assert_eq!(*e.pending.lock().unwrap(), [json!("a"), json!("b"), json!("c")]);
+        assert_eq!(e.dropped.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn requeue_drops_the_newest_beyond_the_cap() {
+        let e = exporter();
+        for i in 0..MAX_PENDING - 1 {
+            e.record(json!(i));
+        }
+        e.requeue(vec![json!("old-1"), json!("old-2")]);
+        let pending = e.pending.lock().unwrap();
+        assert_eq!(pending.len(), MAX_PENDING);
+        assert_eq!(pending[0], "old-1");
+        assert_eq!(pending[MAX_PENDING - 1], json!(MAX_PENDING - 3));
+        assert_eq!(e.dropped.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn span_joins_the_incoming_trace() {
+        let e = exporter();
+        let mut req = Request::builder()
+            .uri("/echo")
+            .header("traceparent", format!("00-{TRACE_ID}-{SPAN_ID}-01"))
+            .header("tracestate", "vendor=abc")
+            .body(Body::empty())
+            .unwrap();
+        let mut span = Span::request(&e, &mut req, peer());
+        span.finish_response(Some(&Route("/echo".to_string())), hyper::StatusCode::BAD_GATEWAY);
+        drop(span);
+
+        let pending = e.pending.lock().unwrap();
+        let span = &pending[0];
+        assert_eq!(span["traceId"], TRACE_ID);
+        assert_eq!(span["parentSpanId"], SPAN_ID);
+        assert_eq!(span["traceState"], "vendor=abc");
+        assert_eq!(span["name"], "GET /echo");
+        assert_eq!(span["kind"], SPAN_KIND_SERVER);
+        assert_eq!(span["status"]["code"], 2);
+        assert!(span["startTimeUnixNano"].is_string());
+        let status = span["attributes"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|a| a["key"] == "http.response.status_code")
+            .unwrap();
+        assert_eq!(status["value"], json!({"intValue": "502"}));
+    }
+
+    #[test]
+    fn root_span_has_no_parent_or_state() {
+        let e = exporter();
+        let mut req = Request::builder()
+            .uri("/")
+            .header("tracestate", "vendor=abc")
+            .body(Body::empty())
+            .unwrap();
+        drop(Span::request(&e, &mut req, peer()));
+        let pending = e.pending.lock().unwrap();
+        assert_eq!(pending[0]["traceId"].as_str().unwrap().len(), 32);
+        assert!(pending[0].get("parentSpanId").is_none());
+        assert!(pending[0].get("traceState").is_none());
+        assert_eq!(pending[0]["status"]["code"], 0);
+    }
+
+    #[test]
+    fn unsampled_trace_is_not_exported() {
+        let e = exporter();
+        let mut req = Request::builder()
+            .uri("/")
+            .header("traceparent", format!("00-{TRACE_ID}-{SPAN_ID}-00"))
+            .body(Body::empty())
+            .unwrap();
+        drop(Span::request(&e, &mut req, peer()));
+        assert!(e.pending.lock().unwrap().is_empty());
+    }
+}
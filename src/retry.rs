@@ -0,0 +1,101 @@
+use serde_json::{json, Value};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::conn::ConnMeta;
+
+/// How many tokens are remembered; the oldest is forgotten first.
+const MAX_TOKENS: usize = 200;
+
+/// How many attempts are remembered per token.
+const MAX_ATTEMPTS: usize = 100;
+
+struct Attempt {
+    at_ms: u64,
+    conn: ConnMeta,
+    method: String,
+    client_addr: String,
+    outcome: &'static str,
+}
+
+#[derive(Default)]
+struct Tokens {
+    attempts: HashMap<String, Vec<Attempt>>,
+    order: VecDeque<String>,
+}
+
+/// Counts the attempts this node sees for each retry-test token.  Every node
+/// keeps its own log; the client merges them by asking through the load
+/// balancer until it has heard from each node.
+#[derive(Default)]
+pub struct RetryTracker {
+    tokens: Mutex<Tokens>,
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+impl RetryTracker {
+    /// Record an attempt for `token` and return its number on this node
+    /// (1 for the first).  `outcome` picks how to answer attempt `n`.
+    pub fn attempt(
+        &self,
+        token: &str,
+        conn: ConnMeta,
+        method: &str,
+        client_addr: String,
+        outcome: impl FnOnce(usize) -> &'static str,
+    ) -> (usize, &'static str) {
+        let mut tokens = self.tokens.lock().unwrap();
+        if !tokens.attempts.contains_key(token) {
+            if tokens.order.len() == MAX_TOKENS {
+                if let Some(oldest) = tokens.order.pop_front() {
+                    tokens.attempts.remove(&oldest);
+                }
+            }
+            tokens.order.push_back(token.to_string());
+        }
+        let attempts = tokens.attempts.entry(token.to_string()).or_default();
+        let n = attempts.len() + 1;
+        let outcome = outcome(n);
+        if attempts.len() < MAX_ATTEMPTS {
+            attempts.push(Attempt {
+                at_ms: now_ms(),
+                conn,
+                method: method.to_string(),
+                client_addr,
+                outcome,
+            });
+        }
+        (n, outcome)
+    }
+
+    /// This node's attempts for `token`, oldest first.
+    pub fn log(&self, token: &str, node: Option<&str>) -> Vec<Value> {
+        let tokens = self.tokens.lock().unwrap();
+        tokens
+            .attempts
+            .get(token)
+            .into_iter()
+            .flatten()
+            .enumerate()
+            .map(|(i, a)| {
+                json!({
+                    "attempt": i + 1,
+                    "at_ms": a.at_ms,
+                    "node": node,
+                    "connection": a.conn.id,
+                    "request_index": a.conn.request_index,
+                    "method": a.method,
+                    "client_addr": a.client_addr,
+                    "outcome": a.outcome,
+                })
+            })
+            .collect()
+    }
+}
@@ -1,15 +1,33 @@
-use hyper::{Body, Request, Response, Server, StatusCode};
-use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, HeaderMap, Method, Request, Response, StatusCode, Version};
+use hyper::server::conn::Http;
+use hyper::service::service_fn;
 use std::convert::Infallible;
 use std::net::SocketAddr;
 use std::sync::Arc;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use tokio::net::TcpListener;
 
 use crate::config::ServerConfig;
-use crate::handlers::{index, ws, sse, lb, echo, waf, cookie, hdr};
-use crate::handlers::common::OrInternalError;
+use crate::conn::{self, ConnMeta};
+use crate::desync::{DesyncMonitor, SMUGGLED_PATH};
+use crate::external_url::forwarded_prefix;
+use crate::forward_auth::AuthLog;
+use crate::otlp::{Exporter, Route, Span};
+use crate::retry::RetryTracker;
+use crate::handlers::{index, ws, sse, lb, echo, waf, cookie, hdr, hops, norm, interim, raw, desync, reflect, security, errors, slow, retry, oidc, auth};
+use crate::handlers::common::{OrInternalError, UrlPrefix};
 
-// Decrement the active-connection counter when the per-connection service is dropped.
+/// What every connection shares: the configuration and the per-node logs
+/// and monitors the handlers record into.
+struct ServerState {
+    config: Arc<ServerConfig>,
+    monitor: Arc<DesyncMonitor>,
+    tracker: Arc<RetryTracker>,
+    auth_log: Arc<AuthLog>,
+    exporter: Option<Arc<Exporter>>,
+}
+
+// Decrement the active-connection counter when the connection task ends.
 struct ConnectionGuard(Arc<AtomicUsize>);
 impl Drop for ConnectionGuard {
     fn drop(&mut self) { self.0.fetch_sub(1, Ordering::Relaxed); }
@@ -34,49 +52,134 @@ pub async fn run_server(config: Arc<ServerConfig>) -> Result<(), Box<dyn std::er
         }
     });
 
-    let make_svc = make_service_fn(move |conn: &hyper::server::conn::AddrStream| {
-        let client_addr = conn.remote_addr();
-        let server_addr = conn.local_addr();
-        let config = config.clone();
+    let mut http = Http::new();
+    http.http1_title_case_headers(true)
+        .max_buf_size(16 * 1024 * 1024)  // 16 MiB — raise URL and header limits
+        .http2_initial_stream_window_size(65535)
+        .http2_initial_connection_window_size(1048576);
+    let http = Arc::new(http);
+
+    let state = Arc::new(ServerState {
+        monitor: Arc::new(DesyncMonitor::default()),
+        tracker: Arc::new(RetryTracker::default()),
+        auth_log: Arc::new(AuthLog::default()),
+        exporter: config.otlp_endpoint.as_deref().map(|url| Exporter::start(url, &config)),
+        config,
+    });
+    let mut next_conn_id: u64 = 0;
+
+    let listener = TcpListener::bind(addr).await?;
+    println!("HTTP/1.1 and HTTP/2 (h2c) enabled");
+
+    loop {
+        let (stream, client_addr) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                // Usually EMFILE; back off rather than spin.
+                eprintln!("Accept error: {}", e);
+                tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+                continue;
+            }
+        };
+        let Ok(server_addr) = stream.local_addr() else { continue };
+        let (io, raw) = conn::wrap(stream);
+        let http = http.clone();
+        let state = state.clone();
+        next_conn_id += 1;
+        let conn_id = next_conn_id;
+        let requests = Arc::new(AtomicU64::new(0));
 
         // Track connection count; ConnectionGuard decrements on drop.
         let cur = active.fetch_add(1, Ordering::Relaxed) + 1;
         peak.fetch_max(cur, Ordering::Relaxed);
-        let _guard = ConnectionGuard(active.clone());
+        let guard = ConnectionGuard(active.clone());
 
-        async move {
-            Ok::<_, Infallible>(service_fn(move |req| {
-                let _guard = &_guard; // keep guard alive for the connection lifetime
-                handle_request(req, client_addr, server_addr, config.clone())
-            }))
+        let service_state = state.clone();
+        let service_requests = requests.clone();
+        let service = service_fn(move |mut req: Request<Body>| {
+            // Raw writes are only safe on HTTP/1, where hyper is idle while
+            // the handler runs.
+            if req.version() != Version::HTTP_2 {
+                req.extensions_mut().insert(raw.clone());
+            }
+            let request_index = service_requests.fetch_add(1, Ordering::Relaxed) + 1;
+            req.extensions_mut().insert(ConnMeta { id: conn_id, request_index });
+            service_state.monitor.watch(&mut req);
+            // Health checks arrive every few seconds and would drown out real traffic.
+            let span = match &service_state.exporter {
+                Some(e) if req.uri().path() != "/healthz" => Some(Span::request(e, &mut req, client_addr)),
+                _ => None,
+            };
+            let response = handle_request(req, client_addr, server_addr, service_state.clone());
+            async move {
+                let response = response.await;
+                if let (Some(mut span), Ok(response)) = (span, &response) {
+                    span.finish_response(response.extensions().get::<Route>(), response.status());
+                }
+                response
+            }
+        });
+        tokio::spawn(async move {
+            let _guard = guard; // keep guard alive for the connection lifetime
+            if let Err(e) = http.serve_connection(io, service).with_upgrades().await {
+                // Bytes left over after a request that do not parse as the
+                // next one are a sign of a framing disagreement upstream.
+                if e.is_parse() {
+                    state.monitor.parse_error(conn_id, requests.load(Ordering::Relaxed), &e);
+                }
+            }
+        });
+    }
+}
+
+/// Strip `prefix` from `path`, mapping an exact match to `/`.  The prefix
+/// must end at a segment boundary, so `/api` does not match `/apiary`.
+fn strip_path_prefix<'a>(path: &'a str, prefix: &str) -> Option<&'a str> {
+    path.strip_prefix(prefix)
+        .filter(|rest| rest.is_empty() || rest.starts_with('/'))
+        .map(|p| if p.is_empty() { "/" } else { p })
+}
+
+/// Work out the URL prefix this request was reached under, and the path
+/// relative to it.  The relative path is empty (so no route matches) when
+/// static prefixes are configured and none of them applies.
+fn match_prefix(
+    path: &str,
+    headers: &HeaderMap,
+    client_addr: SocketAddr,
+    config: &ServerConfig,
+) -> (String, String) {
+    // X-Forwarded-Prefix is only honoured from a trusted proxy; the config
+    // refuses --prefix-from-header without one.
+    let header_allowed = config.prefix_from_header && config.is_trusted_proxy(client_addr.ip());
+    if header_allowed {
+        if let Some(prefix) = forwarded_prefix(headers) {
+            // The proxy may or may not have stripped the prefix before forwarding.
+            let rel = strip_path_prefix(path, &prefix).unwrap_or(path);
+            return (prefix, rel.to_string());
         }
-    });
-    
-    let server = Server::bind(&addr)
-        .http1_title_case_headers(true)
-        .http1_max_buf_size(16 * 1024 * 1024)  // 16 MiB — raise URL and header limits
-        .http2_initial_stream_window_size(65535)
-        .http2_initial_connection_window_size(1048576)
-        .serve(make_svc);
-    println!("HTTP/1.1 and HTTP/2 (h2c) enabled");
-    
-    server.await?;
-    
-    Ok(())
+    }
+
+    if config.url_prefixes.is_empty() {
+        return (String::new(), path.to_string());
+    }
+    config.url_prefixes.iter()
+        .find_map(|prefix| strip_path_prefix(path, prefix).map(|rel| (prefix.clone(), rel.to_string())))
+        .unwrap_or_default() // Empty string won't match any route
 }
 
 async fn handle_request(
-    req: Request<Body>,
+    mut req: Request<Body>,
     client_addr: SocketAddr,
     server_addr: SocketAddr,
-    config: Arc<ServerConfig>,
+    state: Arc<ServerState>,
 ) -> Result<Response<Body>, Infallible> {
-    let mut path = req.uri().path();
+    let config = state.config.clone();
     let headers = req.headers().clone();
     let protocol = format!("{:?}", req.version());
 
     // Health check — always available, regardless of url_prefix.
-    if path == "/healthz" {
+    if req.uri().path() == "/healthz" {
         return Ok(Response::builder()
             .status(StatusCode::OK)
             .header("Content-Type", "text/plain")
@@ -84,14 +187,22 @@ async fn handle_request(
             .or_500());
     }
 
-    // Strip URL prefix if configured
-    if let Some(prefix) = &config.url_prefix {
-        path = path.strip_prefix(prefix)
-            .map(|p| if p.is_empty() { "/" } else { p })
-            .unwrap_or(""); // Empty string won't match any route
+    // Smuggled requests bypass the proxy, so they are matched whatever the prefix.
+    if req.uri().path().ends_with(SMUGGLED_PATH) {
+        return Ok(desync::handle_smuggled(req, state.monitor.clone()).await);
     }
-    
-    let response = match path {
+
+    // `OPTIONS *` asks about the server as a whole, so it has no prefix.
+    if req.method() == Method::OPTIONS && req.uri().path() == "*" {
+        return Ok(hops::handle_options(req, headers, config, client_addr, server_addr, protocol).await);
+    }
+
+    // Strip URL prefix if configured; handlers read the matched prefix back
+    // from the request extensions.
+    let (prefix, path) = match_prefix(req.uri().path(), &headers, client_addr, &config);
+    req.extensions_mut().insert(UrlPrefix(prefix));
+
+    let mut response = match path.as_str() {
         "/" => index::handle_index(req, client_addr, server_addr, config).await,
         "/ws" => ws::handle_ws_upgrade(req, client_addr, server_addr, config).await,
         "/sse" => sse::handle_sse(req, client_addr, server_addr, config).await,
@@ -99,17 +210,66 @@ async fn handle_request(
         "/waf" => waf::handle_waf(req).await,
         "/delete-cookie" => cookie::handle_delete_cookie(req).await,
         "/hdr" => hdr::handle_response_headers_test(req).await,
+        "/hop" => hops::handle_hop_by_hop(req).await,
+        "/max-forwards" => {
+            hops::handle_max_forwards(req, headers, config, client_addr, server_addr, protocol).await
+        }
+        "/response-headers" => reflect::handle_response_headers(req).await,
+        "/security-headers" => security::handle_security_headers(req).await,
+        "/error" => errors::handle_error(req).await,
+        "/slow" => slow::handle_slow(req, client_addr).await,
+        "/retry" => retry::handle_retry(req, client_addr, config, state.tracker.clone()).await,
+        "/retry/log" => retry::handle_log(req, config, state.tracker.clone()).await,
+        "/oidc/login" => oidc::handle_login(req).await,
+        "/oidc/authorize" => oidc::handle_authorize(req).await,
+        "/oidc/callback" => oidc::handle_callback(req).await,
+        "/oidc/session" => oidc::handle_session(req).await,
+        "/oidc/logout" => oidc::handle_logout(req).await,
+        "/auth-log" => auth::handle_log(config, state.auth_log.clone()).await,
+        "/continue" => interim::handle_continue(req).await,
+        "/early-hints" => interim::handle_early_hints(req).await,
+        "/early-hints/hint.css" => interim::handle_hint_css().await,
+        "/raw" => raw::handle_raw(req, config).await,
+        "/desync" => desync::handle_report(req, state.monitor.clone()).await,
+        "/desync/probe" => desync::handle_probe(req, state.monitor.clone()).await,
+        p if p == "/auth" || p.starts_with("/auth/") => {
+            auth::handle_auth(req, &p["/auth".len()..], client_addr, config, state.auth_log.clone()).await
+        }
+        p if p == "/norm" || p.starts_with("/norm/") => norm::handle_norm(req).await,
         p if p == "/echo" || p.starts_with("/echo/") => {
-            let uri = req.uri();
-            let echo_path = uri.path().to_string();
-            let echo_query = uri.query().map(str::to_string);
-            echo::handle_echo(req, headers, config, client_addr, server_addr, protocol, echo_path, echo_query).await
+            echo::handle_echo(req, headers, config, client_addr, server_addr, protocol).await
+        }
+        _ => {
+            return Ok(Response::builder()
+                .status(StatusCode::NOT_FOUND)
+                .body(Body::from("Not Found"))
+                .or_500())
         }
-        _ => Response::builder()
-            .status(StatusCode::NOT_FOUND)
-            .body(Body::from("Not Found"))
-            .or_500(),
     };
-    
+    response.extensions_mut().insert(Route(route_template(&path)));
+
     Ok(response)
 }
+
+/// The route a matched path belongs to, for span names: subpaths of the
+/// wildcard routes collapse to the route itself.
+fn route_template(path: &str) -> String {
+    ["/auth", "/norm", "/echo"]
+        .iter()
+        .find(|r| path.strip_prefix(**r).is_some_and(|rest| rest.starts_with('/')))
+        .map(|r| format!("{r}/{{path}}"))
+        .unwrap_or_else(|| path.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prefix_ends_at_a_segment_boundary() {
+        assert_eq!(strip_path_prefix("/api", "/api"), Some("/"));
+        assert_eq!(strip_path_prefix("/api/echo", "/api"), Some("/echo"));
+        assert_eq!(strip_path_prefix("/apiary", "/api"), None);
+        assert_eq!(strip_path_prefix("/echo", ""), Some("/echo"));
+    }
+}
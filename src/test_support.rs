@@ -0,0 +1,11 @@
+use hyper::header::HeaderValue;
+use hyper::HeaderMap;
+
+/// A header map with `pairs` appended in order, so repeated names are kept.
+pub(crate) fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+    let mut map = HeaderMap::new();
+    for (name, value) in pairs {
+        map.append(*name, HeaderValue::from_str(value).unwrap());
+    }
+    map
+}
@@ -0,0 +1,442 @@
+use hyper::HeaderMap;
+use serde_json::{json, Value};
+
+use crate::handlers::common::parse_query;
+
+/// Header in which a client lists the trace headers it sent, as a query
+/// string (`traceparent=…&x-request-id=…`).  Present but empty means it sent
+/// none, so any that arrive were created on the way.
+pub const TRACE_SENT_HEADER: &str = "x-sensillum-trace-sent";
+
+/// The fields of a trace header that matter for propagation.
+#[derive(Default)]
+struct Parsed {
+    trace_id: Option<String>,
+    span_id: Option<String>,
+    sampled: Option<bool>,
+    errors: Vec<String>,
+}
+
+/// One trace-context convention and the headers that carry it.
+struct TraceKind {
+    id: &'static str,
+    headers: &'static [&'static str],
+    parse: fn(&str) -> Parsed,
+}
+
+static KINDS: &[TraceKind] = &[
+    TraceKind { id: "traceparent", headers: &["traceparent"], parse: parse_traceparent },
+    TraceKind { id: "tracestate", headers: &["tracestate"], parse: parse_tracestate },
+    TraceKind { id: "b3", headers: &["b3"], parse: parse_b3_single },
+    TraceKind {
+        id: "x-b3",
+        headers: &["x-b3-traceid", "x-b3-spanid", "x-b3-parentspanid", "x-b3-sampled", "x-b3-flags"],
+        parse: parse_b3_multi,
+    },
+    TraceKind { id: "x-request-id", headers: &["x-request-id"], parse: parse_request_id },
+];
+
+fn is_lower_hex(s: &str, len: usize) -> bool {
+    s.len() == len && s.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+fn is_zero(s: &str) -> bool {
+    s.bytes().all(|b| b == b'0')
+}
+
+/// `version-traceid-parentid-flags` (W3C Trace Context §3.2).
+fn parse_traceparent(value: &str) -> Parsed {
+    let mut p = Parsed::default();
+    let fields: Vec<&str> = value.trim().split('-').collect();
+    if fields.len() < 4 {
+        p.errors.push("expected version-traceid-parentid-flags".to_string());
+        return p;
+    }
+    let (version, trace_id, parent_id, flags) = (fields[0], fields[1], fields[2], fields[3]);
+    if !is_lower_hex(version, 2) || version == "ff" {
+        p.errors.push(format!("invalid version '{version}'"));
+    } else if version == "00" && fields.len() != 4 {
+        p.errors.push("version 00 has exactly four fields".to_string());
+    }
+    if !is_lower_hex(trace_id, 32) {
+        p.errors.push("trace-id must be 32 lower-case hex digits".to_string());
+    } else if is_zero(trace_id) {
+        p.errors.push("trace-id is all zeros".to_string());
+    } else {
+        p.trace_id = Some(trace_id.to_string());
+    }
+    if !is_lower_hex(parent_id, 16) {
+        p.errors.push("parent-id must be 16 lower-case hex digits".to_string());
+    } else if is_zero(parent_id) {
+        p.errors.push("parent-id is all zeros".to_string());
+    } else {
+        p.span_id = Some(parent_id.to_string());
+    }
+    match u8::from_str_radix(flags, 16) {
+        Ok(f) if is_lower_hex(flags, 2) => p.sampled = Some(f & 1 == 1),
+        _ => p.errors.push(format!("invalid trace-flags '{flags}'")),
+    }
+    p
+}
+
+/// The trace ID, parent span ID and sampled flag of a single valid
+/// `traceparent`, for spans that join the caller's trace.
+pub fn incoming_parent(headers: &HeaderMap) -> Option<(String, String, bool)> {
+    let mut values = headers.get_all("traceparent").iter();
+    let value = values.next()?.to_str().ok()?;
+    if values.next().is_some() {
+        return None;
+    }
+    let p = parse_traceparent(value);
+    if !p.errors.is_empty() {
+        return None;
+    }
+    Some((p.trace_id?, p.span_id?, p.sampled?))
+}
+
+/// Comma-separated `key=value` members, at most 32 (W3C Trace Context §3.3).
+fn parse_tracestate(value: &str) -> Parsed {
+    let mut p = Parsed::default();
+    let members: Vec<&str> = value.split(',').map(str::trim).filter(|m| !m.is_empty()).collect();
+    if members.len() > 32 {
+        p.errors.push(format!("{} list members; at most 32 are allowed", members.len()));
+    }
+    let mut keys = Vec::new();
+    for member in members {
+        let Some((key, val)) = member.split_once('=') else {
+            p.errors.push(format!("'{member}' is not key=value"));
+            continue;
+        };
+        let key_ok = !key.is_empty()
+            && key.len() <= 256
+            && key.bytes().next().is_some_and(|b| b.is_ascii_lowercase() || b.is_ascii_digit())
+            && key.bytes().all(|b| {
+                b.is_ascii_lowercase() || b.is_ascii_digit() || b"_-*/@".contains(&b)
+            });
+        if !key_ok {
+            p.errors.push(format!("invalid key '{key}'"));
+        }
+        let val_ok = !val.is_empty()
+            && val.len() <= 256
+            && val.bytes().all(|b| (0x20..=0x7e).contains(&b) && b != b',' && b != b'=')
+            && !val.ends_with(' ');
+        if !val_ok {
+            p.errors.push(format!("invalid value for '{key}'"));
+        }
+        if keys.contains(&key) {
+            p.errors.push(format!("duplicate key '{key}'"));
+        }
+        keys.push(key);
+    }
+    p
+}
+
+fn b3_trace_id(p: &mut Parsed, trace_id: &str) {
+    if (is_lower_hex(trace_id, 16) || is_lower_hex(trace_id, 32)) && !is_zero(trace_id) {
+        p.trace_id = Some(trace_id.to_string());
+    } else {
+        p.errors.push("trace ID must be 16 or 32 lower-case hex digits".to_string());
+    }
+}
+
+fn b3_span_id(p: &mut Parsed, span_id: &str) {
+    if is_lower_hex(span_id, 16) && !is_zero(span_id) {
+        p.span_id = Some(span_id.to_string());
+    } else {
+        p.errors.push("span ID must be 16 lower-case hex digits".to_string());
+    }
+}
+
+fn b3_sampled(p: &mut Parsed, sampled: &str) {
+    match sampled {
+        "1" | "d" => p.sampled = Some(true),
+        "0" => p.sampled = Some(false),
+        other => p.errors.push(format!("invalid sampling state '{other}'")),
+    }
+}
+
+/// `traceid-spanid[-sampled[-parentspanid]]`, or a lone sampling state.
+fn parse_b3_single(value: &str) -> Parsed {
+    let mut p = Parsed::default();
+    let fields: Vec<&str> = value.trim().split('-').collect();
+    match fields.as_slice() {
+        [sampled] => b3_sampled(&mut p, sampled),
+        [trace_id, span_id, rest @ ..] if rest.len() <= 2 => {
+            b3_trace_id(&mut p, trace_id);
+            b3_span_id(&mut p, span_id);
+            if let Some(sampled) = rest.first() {
+                b3_sampled(&mut p, sampled);
+            }
+            if let Some(parent) = rest.get(1) {
+                if !is_lower_hex(parent, 16) {
+                    p.errors.push("parent span ID must be 16 lower-case hex digits".to_string());
+                }
+            }
+        }
+        _ => p.errors.push("expected traceid-spanid[-sampled[-parentspanid]]".to_string()),
+    }
+    p
+}
+
+/// The `X-B3-*` headers, joined as `name: value` lines by `kind_value`.
+fn parse_b3_multi(value: &str) -> Parsed {
+    let mut p = Parsed::default();
+    for line in value.lines() {
+        let Some((name, v)) = line.split_once(": ") else { continue };
+        match name {
+            "x-b3-traceid" => b3_trace_id(&mut p, v),
+            "x-b3-spanid" => b3_span_id(&mut p, v),
+            "x-b3-sampled" => b3_sampled(&mut p, v),
+            "x-b3-flags" if v != "1" => p.errors.push("X-B3-Flags must be 1".to_string()),
+            _ => {}
+        }
+    }
+    if p.trace_id.is_some() != p.span_id.is_some() {
+        p.errors.push("X-B3-TraceId and X-B3-SpanId must be sent together".to_string());
+    }
+    p
+}
+
+/// `X-Request-ID` has no standard format; it is treated as the trace ID.
+fn parse_request_id(value: &str) -> Parsed {
+    let mut p = Parsed::default();
+    let value = value.trim();
+    if value.is_empty() {
+        p.errors.push("empty".to_string());
+    } else if value.contains(',') {
+        p.errors.push("more than one X-Request-ID".to_string());
+    } else {
+        p.trace_id = Some(value.to_string());
+    }
+    p
+}
+
+/// A kind's value as one string: the header value, repeated headers joined
+/// with `, `, or `name: value` lines for multi-header kinds.
+fn kind_value(kind: &TraceKind, get: &dyn Fn(&str) -> Option<String>) -> Option<String> {
+    if let [name] = kind.headers {
+        return get(name);
+    }
+    let lines: Vec<String> = kind
+        .headers
+        .iter()
+        .filter_map(|name| get(name).map(|v| format!("{name}: {v}")))
+        .collect();
+    (!lines.is_empty()).then(|| lines.join("\n"))
+}
+
+/// What happened to a kind between the client and Sensillum.
+fn status(sent: Option<&Option<String>>, received: &Option<String>, parse: fn(&str) -> Parsed) -> (&'static str, String) {
+    let Some(sent) = sent else {
+        return match received {
+            Some(_) => ("received", "the client did not say what it sent".to_string()),
+            None => ("absent", "not received".to_string()),
+        };
+    };
+    match (sent, received) {
+        (None, None) => ("absent", "neither sent nor received".to_string()),
+        (None, Some(_)) => ("created", "not sent by the client; a proxy added it".to_string()),
+        (Some(_), None) => ("dropped", "sent by the client but did not arrive".to_string()),
+        (Some(s), Some(r)) if s == r => ("forwarded", "arrived unchanged".to_string()),
+        (Some(s), Some(r)) => {
+            let (s, r) = (parse(s), parse(r));
+            match (s.trace_id, r.trace_id) {
+                (Some(a), Some(b)) if a == b => (
+                    "modified",
+                    "same trace ID with a new span or flags; the proxy joined the trace".to_string(),
+                ),
+                (Some(_), Some(_)) => ("new-trace", "the proxy replaced the trace ID and started a new trace".to_string()),
+                _ => ("modified", "the value was changed".to_string()),
+            }
+        }
+    }
+}
+
+/// Trace-context headers as received, validated, and compared with what the
+/// client declared in `X-Sensillum-Trace-Sent`.  Values of redacted headers
+/// are not included.
+pub fn trace_json(headers: &HeaderMap, redact_prefixes: &[String]) -> Value {
+    let received = |name: &str| -> Option<String> {
+        let values: Vec<String> = headers
+            .get_all(name)
+            .iter()
+            .map(|v| String::from_utf8_lossy(v.as_bytes()).into_owned())
+            .collect();
+        (!values.is_empty()).then(|| values.join(", "))
+    };
+    let declared = headers
+        .get(TRACE_SENT_HEADER)
+        .map(|v| parse_query(&String::from_utf8_lossy(v.as_bytes())));
+    let sent_get = |name: &str| -> Option<String> {
+        declared.as_ref().and_then(|q| q.get(name)).map(str::to_string)
+    };
+
+    let mut kinds = Vec::new();
+    let mut trace_id_kept: Option<bool> = None;
+    for kind in KINDS {
+        let value = kind_value(kind, &received);
+        let sent = declared.as_ref().map(|_| kind_value(kind, &sent_get));
+        if value.is_none() && sent.as_ref().is_none_or(|s| s.is_none()) {
+            continue;
+        }
+        let (status, detail) = status(sent.as_ref(), &value, kind.parse);
+        let parsed = value.as_deref().map(kind.parse).unwrap_or_default();
+        // The first kind that carried a trace ID, traceparent before the rest.
+        if trace_id_kept.is_none() {
+            let sent_id = sent.as_ref().and_then(|s| s.as_deref()).map(kind.parse).and_then(|p| p.trace_id);
+            if let Some(sent_id) = sent_id {
+                trace_id_kept = Some(parsed.trace_id.as_deref() == Some(sent_id.as_str()));
+            }
+        }
+        let redacted = kind
+            .headers
+            .iter()
+            .any(|h| redact_prefixes.iter().any(|p| h.starts_with(p.as_str())));
+        let shown = |v: Option<String>| if redacted { None } else { v };
+        kinds.push(json!({
+            "kind": kind.id,
+            "value": shown(value.clone()),
+            "sent": shown(sent.flatten()),
+            "redacted": redacted,
+            "valid": value.is_some() && parsed.errors.is_empty(),
+            "errors": parsed.errors,
+            "trace_id": shown(parsed.trace_id),
+            "span_id": shown(parsed.span_id),
+            "sampled": parsed.sampled,
+            "status": status,
+            "detail": detail,
+        }));
+    }
+
+    json!({
+        "declared": declared.is_some(),
+        "headers": kinds,
+        "trace_id_kept": trace_id_kept,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::headers;
+
+    const TRACE_ID: &str = "4bf92f3577b34da6a3ce929d0e0e4736";
+    const SPAN_ID: &str = "00f067aa0ba902b7";
+
+    #[test]
+    fn valid_traceparent() {
+        let p = parse_traceparent(&format!("00-{TRACE_ID}-{SPAN_ID}-01"));
+        assert!(p.errors.is_empty(), "{:?}", p.errors);
+        assert_eq!(p.trace_id.as_deref(), Some(TRACE_ID));
+        assert_eq!(p.span_id.as_deref(), Some(SPAN_ID));
+        assert_eq!(p.sampled, Some(true));
+
+        // Later versions may add fields.
+        let p = parse_traceparent(&format!("01-{TRACE_ID}-{SPAN_ID}-00-extra"));
+        assert!(p.errors.is_empty(), "{:?}", p.errors);
+        assert_eq!(p.sampled, Some(false));
+    }
+
+    #[test]
+    fn invalid_traceparent() {
+        let errors = |v: &str| parse_traceparent(v).errors;
+        assert_eq!(errors("garbage"), ["expected version-traceid-parentid-flags"]);
+        assert_eq!(errors(&format!("ff-{TRACE_ID}-{SPAN_ID}-01")), ["invalid version 'ff'"]);
+        assert_eq!(errors(&format!("00-{TRACE_ID}-{SPAN_ID}-01-x")), ["version 00 has exactly four fields"]);
+        assert_eq!(
+            errors(&format!("00-{}-{SPAN_ID}-01", TRACE_ID.to_uppercase())),
+            ["trace-id must be 32 lower-case hex digits"]
+        );
+        assert_eq!(errors(&format!("00-{}-{SPAN_ID}-01", "0".repeat(32))), ["trace-id is all zeros"]);
+        assert_eq!(errors(&format!("00-{TRACE_ID}-{}-01", "0".repeat(16))), ["parent-id is all zeros"]);
+        assert_eq!(errors(&format!("00-{TRACE_ID}-{SPAN_ID}-1")), ["invalid trace-flags '1'"]);
+    }
+
+    #[test]
+    fn incoming_parent_needs_one_valid_traceparent() {
+        let value = format!("00-{TRACE_ID}-{SPAN_ID}-01");
+        assert_eq!(
+            incoming_parent(&headers(&[("traceparent", &value)])),
+            Some((TRACE_ID.to_string(), SPAN_ID.to_string(), true))
+        );
+        assert_eq!(incoming_parent(&headers(&[("traceparent", &value), ("traceparent", &value)])), None);
+        assert_eq!(incoming_parent(&headers(&[("traceparent", "00-bad")])), None);
+        assert_eq!(incoming_parent(&HeaderMap::new()), None);
+    }
+
+    #[test]
+    fn tracestate_members() {
+        assert!(parse_tracestate("congo=t61rcWkgMzE, rojo=00f067aa0ba902b7").errors.is_empty());
+        assert!(parse_tracestate("tenant@vendor=x").errors.is_empty());
+        assert_eq!(parse_tracestate("Congo=1").errors, ["invalid key 'Congo'"]);
+        assert_eq!(parse_tracestate("a=1,a=2").errors, ["duplicate key 'a'"]);
+        assert_eq!(parse_tracestate("novalue").errors, ["'novalue' is not key=value"]);
+        let many: Vec<String> = (0..33).map(|i| format!("k{i}=v")).collect();
+        assert_eq!(parse_tracestate(&many.join(",")).errors, ["33 list members; at most 32 are allowed"]);
+    }
+
+    #[test]
+    fn b3_single_header() {
+        let p = parse_b3_single(&format!("{TRACE_ID}-{SPAN_ID}-1-{SPAN_ID}"));
+        assert!(p.errors.is_empty(), "{:?}", p.errors);
+        assert_eq!(p.trace_id.as_deref(), Some(TRACE_ID));
+        assert_eq!(p.sampled, Some(true));
+
+        // 64-bit trace IDs and a lone sampling decision are allowed.
+        assert_eq!(parse_b3_single(&format!("{SPAN_ID}-{SPAN_ID}")).trace_id.as_deref(), Some(SPAN_ID));
+        let p = parse_b3_single("0");
+        assert!(p.errors.is_empty() && p.trace_id.is_none());
+        assert_eq!(p.sampled, Some(false));
+
+        assert_eq!(parse_b3_single(&format!("{TRACE_ID}-{SPAN_ID}-yes")).errors, ["invalid sampling state 'yes'"]);
+        assert_eq!(parse_b3_single("a-b-c-d-e").errors, ["expected traceid-spanid[-sampled[-parentspanid]]"]);
+    }
+
+    #[test]
+    fn b3_multi_headers() {
+        let p = parse_b3_multi(&format!("x-b3-traceid: {TRACE_ID}\nx-b3-spanid: {SPAN_ID}\nx-b3-sampled: 1"));
+        assert!(p.errors.is_empty(), "{:?}", p.errors);
+        assert_eq!(p.span_id.as_deref(), Some(SPAN_ID));
+        assert_eq!(
+            parse_b3_multi(&format!("x-b3-traceid: {TRACE_ID}")).errors,
+            ["X-B3-TraceId and X-B3-SpanId must be sent together"]
+        );
+        assert_eq!(parse_b3_multi("x-b3-flags: 0").errors, ["X-B3-Flags must be 1"]);
+    }
+
+    #[test]
+    fn compares_with_what_the_client_sent() {
+        let sent = format!("00-{TRACE_ID}-{SPAN_ID}-01");
+        let joined = format!("00-{TRACE_ID}-b7ad6b7169203331-01");
+        let declared = format!("traceparent={sent}");
+        let report = trace_json(
+            &headers(&[
+                (TRACE_SENT_HEADER, &declared),
+                ("traceparent", &joined),
+                ("x-request-id", "abc"),
+            ]),
+            &[],
+        );
+        assert_eq!(report["declared"], true);
+        assert_eq!(report["trace_id_kept"], true);
+        assert_eq!(report["headers"][0]["status"], "modified");
+        assert_eq!(report["headers"][1]["kind"], "x-request-id");
+        assert_eq!(report["headers"][1]["status"], "created");
+
+        let other = format!("00-{}-{SPAN_ID}-01", "1".repeat(32));
+        let report = trace_json(&headers(&[(TRACE_SENT_HEADER, &declared), ("traceparent", &other)]), &[]);
+        assert_eq!(report["trace_id_kept"], false);
+        assert_eq!(report["headers"][0]["status"], "new-trace");
+    }
+
+    #[test]
+    fn redacted_values_are_hidden() {
+        let value = format!("00-{TRACE_ID}-{SPAN_ID}-01");
+        let report = trace_json(&headers(&[("traceparent", &value)]), &["trace".to_string()]);
+        let kind = &report["headers"][0];
+        assert_eq!(kind["redacted"], true);
+        assert_eq!(kind["valid"], true);
+        assert!(kind["value"].is_null() && kind["trace_id"].is_null());
+        assert_eq!(kind["status"], "received");
+    }
+}
@@ -0,0 +1,150 @@
+use hyper::HeaderMap;
+use serde_json::{json, Value};
+
+/// One entry of a `Via` header (RFC 9110 §7.6.3):
+/// `received-protocol RWS received-by [ RWS comment ]`.
+pub struct ViaHop {
+    /// Protocol name; RFC 9110 says it is omitted when it is "HTTP".
+    pub protocol: String,
+    pub version: String,
+    /// Host[:port] or pseudonym of the proxy that received the request.
+    pub received_by: String,
+    /// Free-text comment, usually the proxy software, without parentheses.
+    pub comment: Option<String>,
+}
+
+/// Split a `Via` value on commas that are not inside a parenthesised comment.
+fn split_entries(value: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0u32;
+    let mut start = 0;
+    for (i, c) in value.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                parts.push(&value[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&value[start..]);
+    parts
+}
+
+fn parse_entry(entry: &str) -> Result<ViaHop, String> {
+    let entry = entry.trim();
+    let (head, comment) = match entry.find('(') {
+        Some(i) => {
+            let c = entry[i..].trim();
+            let c = c.strip_prefix('(').unwrap_or(c);
+            let c = c.strip_suffix(')').ok_or_else(|| format!("unterminated comment in '{entry}'"))?;
+            (entry[..i].trim(), Some(c.to_string()))
+        }
+        None => (entry, None),
+    };
+    let mut words = head.split_whitespace();
+    let received_protocol = words.next().ok_or("empty entry")?;
+    let received_by = words
+        .next()
+        .ok_or_else(|| format!("'{entry}' has no received-by"))?;
+    if let Some(extra) = words.next() {
+        return Err(format!("unexpected '{extra}' in '{entry}'"));
+    }
+    let (protocol, version) = match received_protocol.split_once('/') {
+        Some((p, v)) => (p.to_string(), v.to_string()),
+        None => ("HTTP".to_string(), received_protocol.to_string()),
+    };
+    Ok(ViaHop {
+        protocol,
+        version,
+        received_by: received_by.to_string(),
+        comment,
+    })
+}
+
+/// Parse every `Via` header into hops, client-side first, with any
+/// malformed entries reported separately.
+pub fn parse_via(headers: &HeaderMap) -> (Vec<ViaHop>, Vec<String>) {
+    let mut hops = Vec::new();
+    let mut errors = Vec::new();
+    for value in headers.get_all("via").iter() {
+        let Ok(value) = value.to_str() else {
+            errors.push("Via header is not valid text".to_string());
+            continue;
+        };
+        for entry in split_entries(value) {
+            if entry.trim().is_empty() {
+                continue;
+            }
+            match parse_entry(entry) {
+                Ok(hop) => hops.push(hop),
+                Err(e) => errors.push(e),
+            }
+        }
+    }
+    (hops, errors)
+}
+
+/// The `Via` chain as JSON, for `build_server_info`.
+pub fn via_json(headers: &HeaderMap) -> Value {
+    let (hops, errors) = parse_via(headers);
+    let hops: Vec<Value> = hops
+        .iter()
+        .map(|h| {
+            json!({
+                "protocol": h.protocol,
+                "version": h.version,
+                "received_by": h.received_by,
+                "comment": h.comment,
+            })
+        })
+        .collect();
+    json!({ "hops": hops, "errors": errors })
+}
+
+/// Compare the `Max-Forwards` value the client says it sent with what arrived
+/// (RFC 9110 §7.6.2).  Every proxy that forwards a TRACE or OPTIONS request
+/// must decrement it, and must answer itself instead of forwarding once it
+/// reaches zero.  The number of `Via` hops is used as the expected decrement.
+pub fn max_forwards_report(headers: &HeaderMap, sent: Option<u32>) -> Value {
+    let raw = headers
+        .get("max-forwards")
+        .map(|v| String::from_utf8_lossy(v.as_bytes()).into_owned());
+    let received: Option<u32> = raw.as_deref().and_then(|v| v.trim().parse().ok());
+    let via_hops = parse_via(headers).0.len() as u32;
+    let proxied = via_hops > 0
+        || headers.contains_key("x-forwarded-for")
+        || headers.contains_key("forwarded");
+
+    let (verdict, detail) = match (sent, received) {
+        (_, None) if raw.is_some() => ("invalid", format!("Max-Forwards arrived as '{}', which is not a number", raw.as_deref().unwrap_or(""))),
+        (Some(s), None) => ("stripped", format!("client sent Max-Forwards: {s} but it did not arrive")),
+        (None, None) => ("absent", "no Max-Forwards header was received".to_string()),
+        (None, Some(r)) => ("unknown", format!("received Max-Forwards: {r}; pass ?max_forwards=N to compare with what was sent")),
+        (Some(s), Some(r)) if r > s => ("increased", format!("client sent {s} but {r} arrived")),
+        (Some(0), Some(_)) if proxied => ("forwarded-at-zero", "a proxy forwarded the request although Max-Forwards was 0".to_string()),
+        (Some(s), Some(r)) => {
+            let dec = s - r;
+            match (dec, via_hops) {
+                (0, 0) if proxied => ("not-decremented", format!("Max-Forwards arrived unchanged ({r}) through a proxy")),
+                (0, 0) => ("unchanged", format!("Max-Forwards arrived unchanged ({r}); no proxy detected")),
+                (0, v) => ("not-decremented", format!("{v} Via hop(s) but Max-Forwards arrived unchanged ({r})")),
+                (d, 0) => ("decremented", format!("decremented by {d}, but no proxy added a Via entry")),
+                (d, v) if d == v => ("ok", format!("decremented by {d}, matching {v} Via hop(s)")),
+                (d, v) => ("mismatch", format!("decremented by {d}, but there are {v} Via hop(s)")),
+            }
+        }
+    };
+
+    json!({
+        "sent": sent,
+        "received": received,
+        "raw": raw,
+        "via_hops": via_hops,
+        "proxied": proxied,
+        "verdict": verdict,
+        "detail": detail,
+    })
+}